@@ -1,7 +1,24 @@
+use md5::Md5;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use tauri::{Emitter, Window};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tauri::{Emitter, Manager, State, Window};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+
+use crate::config::{is_token_expired, CloudConnection};
+use crate::fs_commands::join_untrusted_name;
+use crate::listing_cache::ListingCache;
+use crate::remote::{BoxFuture, Remote, RemoteEntry, RemoteKind};
+use crate::transfers::{
+    bytes_per_sec, BatchProgress, BenchmarkResult, ProgressThrottle, TransferRegistry,
+    BENCHMARK_DEFAULT_BYTES, BENCHMARK_MAX_BYTES,
+};
 
 #[derive(Serialize, Clone)]
 pub struct TransferProgress {
@@ -10,6 +27,9 @@ pub struct TransferProgress {
     pub progress: u64,
     pub total: u64,
     pub status: String,
+    /// Set on the files of a multi-file operation and on its rolled-up progress, see `BatchProgress`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,11 +49,25 @@ struct GoogleDriveFile {
     mimeType: String,
     size: Option<String>,
     modifiedTime: Option<String>,
+    #[serde(default)]
+    md5Checksum: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
+#[allow(non_snake_case)]
 struct GoogleDriveResponse {
     files: Vec<GoogleDriveFile>,
+    #[serde(default)]
+    nextPageToken: Option<String>,
+}
+
+/// Mime type of Google Drive folders.
+const GOOGLE_FOLDER_MIME: &str = "application/vnd.google-apps.folder";
+
+/// Whether a Google Drive file is a Google Docs, Sheets etc. document, which has
+/// no stored content to download as-is.
+fn is_google_native(file: &GoogleDriveFile) -> bool {
+    file.mimeType.starts_with("application/vnd.google-apps.") && file.mimeType != GOOGLE_FOLDER_MIME
 }
 
 #[derive(Deserialize, Debug)]
@@ -44,49 +78,471 @@ struct DropboxFile {
     id: String,
     size: Option<u64>,
     server_modified: Option<String>,
+    #[serde(default)]
+    path_display: Option<String>,
+}
+
+/// Metadata Dropbox sends in the `Dropbox-API-Result` header of a download.
+#[derive(Deserialize, Debug)]
+struct DropboxDownloadResult {
+    #[serde(default)]
+    content_hash: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 struct DropboxListResponse {
     entries: Vec<DropboxFile>,
+    #[serde(default)]
+    cursor: String,
+    #[serde(default)]
+    has_more: bool,
+}
+
+pub type CloudListingCache = ListingCache<Vec<CloudEntry>>;
+
+fn token_fingerprint(token: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.trim().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Cache key for a cloud folder, scoped to the account via a fingerprint of its token.
+fn cloud_cache_key(provider: &str, token: &str, folder_id: &Option<String>) -> String {
+    format!(
+        "{}:{:x}:{}",
+        provider,
+        token_fingerprint(token),
+        folder_id.as_deref().unwrap_or_default()
+    )
+}
+
+/// Drops the cached listing of `folder_id`, e.g. after adding to it.
+fn invalidate_cloud_folder(
+    cache: &CloudListingCache,
+    provider: &str,
+    token: &str,
+    folder_id: &Option<String>,
+) {
+    let key = cloud_cache_key(provider, token, folder_id);
+    cache.invalidate(|k| k == key);
+}
+
+/// Drops the cached listings a change to the file or folder `entry_id` made
+/// stale: those listing it, and its own if it is a folder. A Dropbox path also
+/// drops the listings of its parent folder, which holds entries by id, and of
+/// the folders under it, whose paths change with it.
+fn invalidate_cloud_entry(cache: &CloudListingCache, provider: &str, token: &str, entry_id: &str) {
+    let account = cloud_cache_key(provider, token, &None);
+    let own = cloud_cache_key(provider, token, &Some(entry_id.to_string()));
+    let subtree = format!("{}/", own.trim_end_matches('/'));
+    let parent = (provider == "dropbox" && entry_id.starts_with('/')).then(|| {
+        let parent = entry_id
+            .trim_end_matches('/')
+            .rsplit_once('/')
+            .map_or("", |(parent, _)| parent);
+        cloud_cache_key(provider, token, &Some(parent.to_string()))
+    });
+    cache.invalidate_where(|key, entries| {
+        key.starts_with(&account)
+            && (key == own
+                || parent.as_deref() == Some(key)
+                || (parent.is_some() && key.starts_with(&subtree))
+                || entries.iter().any(|e| e.id.as_deref() == Some(entry_id)))
+    });
+}
+
+/// Drops every cached listing of the account, for changes whose folders aren't known.
+fn invalidate_cloud_account(cache: &CloudListingCache, provider: &str, token: &str) {
+    let account = cloud_cache_key(provider, token, &None);
+    cache.invalidate(|key| key.starts_with(&account));
+}
+
+/// Root namespace of each Dropbox account seen so far, keyed by token fingerprint;
+/// `None` for accounts whose root is their own home folder.
+static DROPBOX_PATH_ROOTS: RwLock<BTreeMap<u64, Option<String>>> = RwLock::new(BTreeMap::new());
+
+/// Remembers the root namespace of the Dropbox account behind `token`, e.g. the
+/// one saved on its connection, so it needn't be looked up again.
+pub fn set_dropbox_path_root(token: &str, root_namespace_id: Option<String>) {
+    if let Ok(mut roots) = DROPBOX_PATH_ROOTS.write() {
+        roots.insert(token_fingerprint(token), root_namespace_id);
+    }
+}
+
+#[derive(Deserialize)]
+struct DropboxAccount {
+    root_info: DropboxRootInfo,
+}
+
+#[derive(Deserialize)]
+struct DropboxRootInfo {
+    root_namespace_id: String,
+    home_namespace_id: String,
+}
+
+/// Looks up the root namespace of a Dropbox account. Team accounts have one
+/// above the member's home folder, holding the team folders; for everyone
+/// else the two are the same and `None` is returned.
+#[tauri::command]
+pub async fn get_dropbox_root_namespace(token: String) -> Result<Option<String>, String> {
+    let res = Client::new()
+        .post(format!(
+            "{}/2/users/get_current_account",
+            api_base(&token, Host::DropboxApi)
+        ))
+        .header("Authorization", format!("Bearer {}", token.trim()))
+        .send()
+        .await
+        .map_err(|e| format!("Dropbox Network request failed: {}", e))?;
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Dropbox API Error: {}", err_text));
+    }
+    let account: DropboxAccount = res
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+    let root = account.root_info;
+    let namespace =
+        (root.root_namespace_id != root.home_namespace_id).then_some(root.root_namespace_id);
+    set_dropbox_path_root(&token, namespace.clone());
+    Ok(namespace)
+}
+
+/// Error code of requests refused because their access token has expired.
+pub const TOKEN_EXPIRED: &str = "TokenExpired";
+
+/// Saved connections keyed by token fingerprint, so a request can tell that its
+/// token has expired before sending it, see `config::is_token_expired`.
+static CONNECTIONS: RwLock<BTreeMap<u64, CloudConnection>> = RwLock::new(BTreeMap::new());
+
+pub fn register_connection(connection: &CloudConnection) {
+    if let Ok(mut connections) = CONNECTIONS.write() {
+        connections.insert(
+            token_fingerprint(connection.access_token.expose()),
+            connection.clone(),
+        );
+    }
+}
+
+/// Fails with `TOKEN_EXPIRED` when `token` belongs to a saved connection whose
+/// access token has expired, instead of sending a request that gets a 401.
+/// Tokens of no saved connection are let through.
+fn ensure_token_valid(token: &str) -> Result<(), String> {
+    let connections = CONNECTIONS.read().map_err(|e| e.to_string())?;
+    match connections.get(&token_fingerprint(token)) {
+        Some(connection) if is_token_expired(connection) => Err(format!(
+            "{}: the access token of {} has expired; refresh it or sign in again",
+            TOKEN_EXPIRED, connection.account_name
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// HTTP client for requests to `provider` made with `token`, once the token is
+/// known not to have expired. Dropbox requests of team accounts carry
+/// `Dropbox-API-Path-Root`, so paths resolve from the team root and team
+/// folders are reachable; the account's root is looked up on first use.
+async fn api_client(provider: &str, token: &str) -> Result<Client, String> {
+    ensure_token_valid(token)?;
+    if provider != "dropbox" {
+        return Ok(Client::new());
+    }
+    let cached = DROPBOX_PATH_ROOTS
+        .read()
+        .ok()
+        .and_then(|roots| roots.get(&token_fingerprint(token)).cloned());
+    let namespace = match cached {
+        Some(namespace) => namespace,
+        // Not fatal: without the header the account's home folder is still reachable
+        None => get_dropbox_root_namespace(token.to_string())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "could not look up the Dropbox root namespace");
+                None
+            }),
+    };
+    let Some(namespace) = namespace else {
+        return Ok(Client::new());
+    };
+
+    let path_root = serde_json::json!({ ".tag": "root", "root": namespace }).to_string();
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        "Dropbox-API-Path-Root",
+        reqwest::header::HeaderValue::from_str(&path_root).map_err(|e| e.to_string())?,
+    );
+    Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Base URLs of the cloud APIs, without a trailing slash. Point them at a mock
+/// server in tests, or at a gateway or proxy that forwards to the real APIs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CloudEndpoints {
+    pub google_api: String,
+    pub dropbox_api: String,
+    pub dropbox_content: String,
+    pub graph_api: String,
+}
+
+impl Default for CloudEndpoints {
+    fn default() -> Self {
+        Self {
+            google_api: "https://www.googleapis.com".into(),
+            dropbox_api: "https://api.dropboxapi.com".into(),
+            dropbox_content: "https://content.dropboxapi.com".into(),
+            graph_api: "https://graph.microsoft.com".into(),
+        }
+    }
+}
+
+/// The configured endpoints; `None` until `set_endpoints`, meaning the defaults.
+static ENDPOINTS: RwLock<Option<CloudEndpoints>> = RwLock::new(None);
+
+/// Routes every later request without a per-connection base URL through `endpoints`.
+pub fn set_endpoints(endpoints: CloudEndpoints) {
+    if let Ok(mut current) = ENDPOINTS.write() {
+        *current = Some(endpoints);
+    }
+}
+
+/// Which of the `CloudEndpoints` a request goes to.
+#[derive(Clone, Copy)]
+enum Host {
+    Google,
+    DropboxApi,
+    DropboxContent,
+    Graph,
+}
+
+/// Base URLs of connections to self-hosted or proxied endpoints, keyed by token
+/// fingerprint, see `CloudConnection::base_url`.
+static BASE_URLS: RwLock<BTreeMap<u64, String>> = RwLock::new(BTreeMap::new());
+
+pub fn set_base_url(token: &str, base_url: Option<String>) {
+    if let Ok(mut urls) = BASE_URLS.write() {
+        match base_url.filter(|url| !url.trim().is_empty()) {
+            Some(url) => urls.insert(
+                token_fingerprint(token),
+                url.trim_end_matches('/').to_string(),
+            ),
+            None => urls.remove(&token_fingerprint(token)),
+        };
+    }
+}
+
+/// Base URL for requests made with `token` to `host`: its connection's custom
+/// base URL if one is set, else the configured `CloudEndpoints`.
+fn api_base(token: &str, host: Host) -> String {
+    if let Some(url) = BASE_URLS
+        .read()
+        .ok()
+        .and_then(|urls| urls.get(&token_fingerprint(token)).cloned())
+    {
+        return url;
+    }
+    let endpoints = ENDPOINTS
+        .read()
+        .ok()
+        .and_then(|endpoints| endpoints.clone())
+        .unwrap_or_default();
+    let url = match host {
+        Host::Google => endpoints.google_api,
+        Host::DropboxApi => endpoints.dropbox_api,
+        Host::DropboxContent => endpoints.dropbox_content,
+        Host::Graph => endpoints.graph_api,
+    };
+    url.trim_end_matches('/').to_string()
+}
+
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Sends `request`, waiting out `429 Too Many Requests` replies (per `Retry-After`,
+/// else with exponential backoff) up to `MAX_RATE_LIMIT_RETRIES` times.
+async fn send_with_retry(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut attempt = 0;
+    loop {
+        // Streaming bodies can't be cloned, so those get a single attempt
+        let Some(retry) = request.try_clone() else {
+            return request.send().await;
+        };
+        let res = retry.send().await?;
+        if res.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+            || attempt >= MAX_RATE_LIMIT_RETRIES
+        {
+            return Ok(res);
+        }
+        let wait = res
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(1 << attempt);
+        tracing::debug!(wait, "rate limited, retrying");
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+        attempt += 1;
+    }
 }
 
 #[tauri::command]
 pub async fn list_cloud_directory(
+    cache: State<'_, CloudListingCache>,
     provider: String,
     token: String,
     folder_id: Option<String>,
 ) -> Result<Vec<CloudEntry>, String> {
-    if provider == "google" {
-        let client = Client::new();
-        let parent_id = folder_id.unwrap_or_else(|| "root".to_string());
+    let key = cloud_cache_key(&provider, &token, &folder_id);
+    if let Some(hit) = cache.get(&key) {
+        return Ok(hit);
+    }
+    let entries = fetch_cloud_directory(provider, token, folder_id).await?;
+    cache.insert(key, entries.clone());
+    Ok(entries)
+}
 
-        let query = format!("'{}' in parents and trashed = false", parent_id);
-        let url = format!(
-            "https://www.googleapis.com/drive/v3/files?q={}&fields=files(id,name,mimeType,size,modifiedTime)&orderBy=folder,name",
-            urlencoding::encode(&query)
+/// Stops a deep or wide tree from turning into thousands of requests.
+const MAX_PREFETCH_FOLDERS: usize = 200;
+
+/// Lists `folder_id` and its subfolders down to `depth` levels, a level at a
+/// time with up to `TransferPolicy::max_concurrency` requests in flight, so expanding the
+/// tree afterwards is served from the listing cache. Returns every listing
+/// fetched, keyed by folder id (the root as requested, "" for the drive root).
+#[tauri::command]
+pub async fn prefetch_cloud_tree(
+    window: Window,
+    cache: State<'_, CloudListingCache>,
+    provider: String,
+    token: String,
+    folder_id: Option<String>,
+    depth: u32,
+) -> Result<HashMap<String, Vec<CloudEntry>>, String> {
+    let concurrency = crate::config::transfer_policy(&window).max_concurrency;
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut listings = HashMap::new();
+    let mut level = vec![folder_id.unwrap_or_default()];
+
+    for current_depth in 0..=depth {
+        let mut visited = Vec::new();
+        let mut pending = Vec::new();
+        for id in level.drain(..) {
+            visited.push(id.clone());
+            let folder = (!id.is_empty()).then(|| id.clone());
+            let key = cloud_cache_key(&provider, &token, &folder);
+            if let Some(hit) = cache.get(&key) {
+                listings.insert(id, hit);
+                continue;
+            }
+            let (provider, token, semaphore) = (provider.clone(), token.clone(), semaphore.clone());
+            pending.push((
+                id,
+                key,
+                tauri::async_runtime::spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    fetch_cloud_directory(provider, token, folder).await
+                }),
+            ));
+        }
+
+        for (id, key, handle) in pending {
+            let entries = handle
+                .await
+                .map_err(|e| format!("Prefetch task failed: {}", e))??;
+            cache.insert(key, entries.clone());
+            listings.insert(id, entries);
+        }
+
+        if current_depth == depth {
+            break;
+        }
+        for id in &visited {
+            let subfolders = listings[id]
+                .iter()
+                .filter(|e| e.is_dir)
+                .filter_map(|e| e.id.clone());
+            for child in subfolders {
+                if listings.len() + level.len() >= MAX_PREFETCH_FOLDERS {
+                    break;
+                }
+                if !listings.contains_key(&child) && !level.contains(&child) {
+                    level.push(child);
+                }
+            }
+        }
+        if level.is_empty() {
+            break;
+        }
+    }
+
+    Ok(listings)
+}
+
+/// Lists the children of a Google Drive folder.
+async fn google_folder_children(
+    token: &str,
+    parent_id: &str,
+) -> Result<Vec<GoogleDriveFile>, String> {
+    let query = format!("'{}' in parents and trashed = false", parent_id);
+    google_query_files(token, &query).await
+}
+
+/// Lists the Google Drive files matching `query`, folders first, following
+/// `nextPageToken` across pages.
+async fn google_query_files(token: &str, query: &str) -> Result<Vec<GoogleDriveFile>, String> {
+    let client = api_client("google", token).await?;
+    let mut files = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut url = format!(
+            "{}/drive/v3/files?q={}&fields=nextPageToken,files(id,name,mimeType,size,modifiedTime)&orderBy=folder,name&pageSize=1000", api_base(token, Host::Google),
+            urlencoding::encode(query)
         );
+        if let Some(page) = page_token.take() {
+            url.push_str(&format!("&pageToken={}", urlencoding::encode(&page)));
+        }
 
-        let res = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", token.trim()))
-            .send()
-            .await
-            .map_err(|e| format!("Network request failed: {}", e))?;
+        let res = send_with_retry(
+            client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", token.trim())),
+        )
+        .await
+        .map_err(|e| format!("Network request failed: {}", e))?;
 
         if !res.status().is_success() {
             let err_text = res.text().await.unwrap_or_default();
             return Err(format!("Google Drive API Error: {}", err_text));
         }
 
-        let drive_res: GoogleDriveResponse = res
+        let mut page: GoogleDriveResponse = res
             .json()
             .await
             .map_err(|e| format!("Failed to parse Google Drive response: {}", e))?;
+        files.append(&mut page.files);
+        match page.nextPageToken {
+            Some(next) => page_token = Some(next),
+            None => return Ok(files),
+        }
+    }
+}
+
+async fn fetch_cloud_directory(
+    provider: String,
+    token: String,
+    folder_id: Option<String>,
+) -> Result<Vec<CloudEntry>, String> {
+    if provider == "google" {
+        let parent_id = folder_id.unwrap_or_else(|| "root".to_string());
+        let files = google_folder_children(&token, &parent_id).await?;
 
         let mut entries = Vec::new();
-        for file in drive_res.files {
-            let is_dir = file.mimeType == "application/vnd.google-apps.folder";
+        for file in files {
+            let is_dir = file.mimeType == GOOGLE_FOLDER_MIME;
             let size = file.size.and_then(|s| s.parse::<u64>().ok());
 
             entries.push(CloudEntry {
@@ -99,7 +555,7 @@ pub async fn list_cloud_directory(
         }
         return Ok(entries);
     } else if provider == "dropbox" {
-        let client = Client::new();
+        let client = api_client("dropbox", &token).await?;
 
         let path = if let Some(id) = folder_id {
             if id.is_empty() {
@@ -111,58 +567,250 @@ pub async fn list_cloud_directory(
             String::new()
         };
 
-        let res = client
-            .post("https://api.dropboxapi.com/2/files/list_folder")
+        let mut request = client
+            .post(format!(
+                "{}/2/files/list_folder",
+                api_base(&token, Host::DropboxApi)
+            ))
             .header("Authorization", format!("Bearer {}", token.trim()))
             .header("Content-Type", "application/json")
             .json(&serde_json::json!({
                 "path": path
-            }))
-            .send()
-            .await
-            .map_err(|e| format!("Dropbox Network request failed: {}", e))?;
+            }));
 
-        if !res.status().is_success() {
-            let err_text = res.text().await.unwrap_or_default();
-            return Err(format!("Dropbox API Error: {}", err_text));
-        }
+        // Large folders come in pages, continued by cursor
+        let mut entries = Vec::new();
+        loop {
+            let res = send_with_retry(request)
+                .await
+                .map_err(|e| format!("Dropbox Network request failed: {}", e))?;
 
-        let box_res: DropboxListResponse = res
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+            if !res.status().is_success() {
+                let err_text = res.text().await.unwrap_or_default();
+                return Err(format!("Dropbox API Error: {}", err_text));
+            }
 
-        let mut entries = Vec::new();
-        for file in box_res.entries {
-            let is_dir = file.tag == "folder";
-            entries.push(CloudEntry {
-                name: file.name,
-                is_dir,
-                size: file.size,
-                last_modified: file.server_modified,
-                id: Some(file.id),
-            });
+            let page: DropboxListResponse = res
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+
+            for file in page.entries {
+                let is_dir = file.tag == "folder";
+                entries.push(CloudEntry {
+                    name: file.name,
+                    is_dir,
+                    size: file.size,
+                    last_modified: file.server_modified,
+                    id: Some(file.id),
+                });
+            }
+            if !page.has_more {
+                return Ok(entries);
+            }
+            request = client
+                .post(format!(
+                    "{}/2/files/list_folder/continue",
+                    api_base(&token, Host::DropboxApi)
+                ))
+                .header("Authorization", format!("Bearer {}", token.trim()))
+                .json(&serde_json::json!({ "cursor": page.cursor }));
         }
-        return Ok(entries);
     }
 
     Err(format!("Provider {} not recognized.", provider))
 }
 
+const DROPBOX_HASH_BLOCK: usize = 4 * 1024 * 1024;
+
+/// Incremental digest in the format a provider reports for stored files.
+enum ContentHasher {
+    /// Google Drive `md5Checksum`.
+    Md5(Md5),
+    /// Dropbox `content_hash`: SHA-256 over the SHA-256 digests of each 4 MiB block.
+    Dropbox {
+        blocks: Sha256,
+        block: Sha256,
+        block_len: usize,
+    },
+}
+
+impl ContentHasher {
+    fn md5() -> Self {
+        Self::Md5(Md5::new())
+    }
+
+    fn dropbox() -> Self {
+        Self::Dropbox {
+            blocks: Sha256::new(),
+            block: Sha256::new(),
+            block_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Dropbox {
+                blocks,
+                block,
+                block_len,
+            } => {
+                while !data.is_empty() {
+                    let take = (DROPBOX_HASH_BLOCK - *block_len).min(data.len());
+                    block.update(&data[..take]);
+                    *block_len += take;
+                    data = &data[take..];
+                    if *block_len == DROPBOX_HASH_BLOCK {
+                        blocks.update(std::mem::take(block).finalize());
+                        *block_len = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        let digest = match self {
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Dropbox {
+                mut blocks,
+                block,
+                block_len,
+            } => {
+                if block_len > 0 {
+                    blocks.update(block.finalize());
+                }
+                blocks.finalize().to_vec()
+            }
+        };
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+async fn fetch_google_md5(
+    client: &Client,
+    token: &str,
+    file_id: &str,
+) -> Result<Option<String>, String> {
+    let url = format!(
+        "{}/drive/v3/files/{}?fields=id,name,mimeType,md5Checksum",
+        api_base(token, Host::Google),
+        file_id
+    );
+    let res = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token.trim()))
+        .send()
+        .await
+        .map_err(|e| format!("Google Drive metadata request failed: {}", e))?;
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Google Drive API Error: {}", err_text));
+    }
+    let file = res
+        .json::<GoogleDriveFile>()
+        .await
+        .map_err(|e| format!("Failed to parse Google Drive metadata: {}", e))?;
+    Ok(file.md5Checksum)
+}
+
+/// Compares the local digest with the provider's, deleting the file on a mismatch.
+/// Files the provider keeps no hash for (e.g. Google Docs exports) are accepted.
+async fn verify_download(
+    local_path: &str,
+    expected: Option<&str>,
+    hasher: ContentHasher,
+) -> Result<(), String> {
+    let Some(expected) = expected else {
+        tracing::warn!("provider reported no checksum, skipping verification");
+        return Ok(());
+    };
+    let actual = hasher.finalize_hex();
+    if !actual.eq_ignore_ascii_case(expected) {
+        let _ = tokio::fs::remove_file(local_path).await;
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            local_path, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the partial file of a cancelled download and tells the frontend it stopped.
+async fn discard_cancelled_download(
+    window: &Window,
+    transfer_id: &str,
+    filename: &str,
+    local_path: &str,
+    progress: u64,
+    total: u64,
+    batch_id: Option<String>,
+) -> String {
+    let _ = tokio::fs::remove_file(local_path).await;
+    let _ = window.emit(
+        "transfer-progress",
+        TransferProgress {
+            transfer_id: transfer_id.to_string(),
+            filename: filename.to_string(),
+            progress,
+            total,
+            status: "cancelled".into(),
+            batch_id,
+        },
+    );
+    tracing::info!(transfer_id, "cloud transfer cancelled");
+    "Transfer cancelled".into()
+}
+
+/// Sends `request`, dropping it mid-flight if `transfer_id` is cancelled.
+async fn send_cancellable(
+    request: reqwest::RequestBuilder,
+    registry: &TransferRegistry,
+    transfer_id: &str,
+) -> Result<Option<reqwest::Response>, reqwest::Error> {
+    let send = request.send();
+    tokio::pin!(send);
+    loop {
+        tokio::select! {
+            res = &mut send => return res.map(Some),
+            _ = tokio::time::sleep(std::time::Duration::from_millis(200)) => {
+                if registry.is_cancelled(transfer_id) {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
 #[tauri::command]
+#[tracing::instrument(skip(window, token))]
 pub async fn download_cloud_file(
     window: Window,
     provider: String,
     token: String,
     file_id: String,
     local_path: String,
+    verify: Option<bool>,
+    batch_id: Option<String>,
 ) -> Result<String, String> {
+    let verify = verify.unwrap_or(false);
     let transfer_id = format!("dl-{}", uuid::Uuid::new_v4());
-    let client = Client::new();
+    let registry = window.state::<TransferRegistry>();
+    let _active = registry.track(&transfer_id);
+    let client = api_client(&provider, &token).await?;
 
     if provider == "google" {
+        let expected_hash = if verify {
+            fetch_google_md5(&client, &token, &file_id).await?
+        } else {
+            None
+        };
+        let mut hasher = verify.then(ContentHasher::md5);
+
         let url = format!(
-            "https://www.googleapis.com/drive/v3/files/{}?alt=media",
+            "{}/drive/v3/files/{}?alt=media",
+            api_base(&token, Host::Google),
             file_id
         );
         let mut res = client
@@ -178,22 +826,42 @@ pub async fn download_cloud_file(
         }
 
         let total_size = res.content_length().unwrap_or(0);
-        let mut file = tokio::fs::File::create(&local_path)
+        crate::fs_commands::ensure_free_space(&local_path, total_size)?;
+        let file = tokio::fs::File::create(&local_path)
             .await
             .map_err(|e| format!("Failed to create local file: {}", e))?;
+        let mut file =
+            tokio::io::BufWriter::with_capacity(crate::config::buffer_size(&window), file);
 
         let mut downloaded = 0u64;
+        let throttle = ProgressThrottle::default();
         while let Some(chunk) = res
             .chunk()
             .await
             .map_err(|e| format!("Error reading stream: {}", e))?
         {
+            if registry.is_cancelled(&transfer_id) {
+                drop(file);
+                return Err(discard_cancelled_download(
+                    &window,
+                    &transfer_id,
+                    &file_id,
+                    &local_path,
+                    downloaded,
+                    total_size,
+                    batch_id.clone(),
+                )
+                .await);
+            }
             file.write_all(&chunk)
                 .await
                 .map_err(|e| format!("Failed to write to local file: {}", e))?;
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&chunk);
+            }
             downloaded += chunk.len() as u64;
 
-            if total_size > 0 {
+            if total_size > 0 && throttle.ready() {
                 let _ = window.emit(
                     "transfer-progress",
                     TransferProgress {
@@ -202,10 +870,18 @@ pub async fn download_cloud_file(
                         progress: downloaded,
                         total: total_size,
                         status: "downloading".into(),
+                        batch_id: batch_id.clone(),
                     },
                 );
             }
         }
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to write to local file: {}", e))?;
+        drop(file);
+        if let Some(hasher) = hasher {
+            verify_download(&local_path, expected_hash.as_deref(), hasher).await?;
+        }
 
         let _ = window.emit(
             "transfer-progress",
@@ -215,9 +891,11 @@ pub async fn download_cloud_file(
                 progress: downloaded,
                 total: total_size,
                 status: "complete".into(),
+                batch_id: batch_id.clone(),
             },
         );
 
+        tracing::info!(bytes = downloaded, "cloud download complete");
         return Ok(format!("Successfully downloaded file to {}", local_path));
     } else if provider == "dropbox" {
         let path_arg = serde_json::json!({
@@ -225,7 +903,10 @@ pub async fn download_cloud_file(
         });
 
         let mut res = client
-            .post("https://content.dropboxapi.com/2/files/download")
+            .post(format!(
+                "{}/2/files/download",
+                api_base(&token, Host::DropboxContent)
+            ))
             .header("Authorization", format!("Bearer {}", token.trim()))
             .header("Dropbox-API-Arg", path_arg.to_string())
             .send()
@@ -237,23 +918,51 @@ pub async fn download_cloud_file(
             return Err(format!("Dropbox Download Error: {}", err_text));
         }
 
+        let expected_hash = res
+            .headers()
+            .get("Dropbox-API-Result")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| serde_json::from_str::<DropboxDownloadResult>(v).ok())
+            .and_then(|r| r.content_hash);
+        let mut hasher = verify.then(ContentHasher::dropbox);
+
         let total_size = res.content_length().unwrap_or(0);
-        let mut file = tokio::fs::File::create(&local_path)
+        crate::fs_commands::ensure_free_space(&local_path, total_size)?;
+        let file = tokio::fs::File::create(&local_path)
             .await
             .map_err(|e| format!("Failed to create local file: {}", e))?;
+        let mut file =
+            tokio::io::BufWriter::with_capacity(crate::config::buffer_size(&window), file);
 
         let mut downloaded = 0u64;
+        let throttle = ProgressThrottle::default();
         while let Some(chunk) = res
             .chunk()
             .await
             .map_err(|e| format!("Error reading stream: {}", e))?
         {
+            if registry.is_cancelled(&transfer_id) {
+                drop(file);
+                return Err(discard_cancelled_download(
+                    &window,
+                    &transfer_id,
+                    &file_id,
+                    &local_path,
+                    downloaded,
+                    total_size,
+                    batch_id.clone(),
+                )
+                .await);
+            }
             file.write_all(&chunk)
                 .await
                 .map_err(|e| format!("Failed to write to local file: {}", e))?;
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&chunk);
+            }
             downloaded += chunk.len() as u64;
 
-            if total_size > 0 {
+            if total_size > 0 && throttle.ready() {
                 let _ = window.emit(
                     "transfer-progress",
                     TransferProgress {
@@ -262,10 +971,18 @@ pub async fn download_cloud_file(
                         progress: downloaded,
                         total: total_size,
                         status: "downloading".into(),
+                        batch_id: batch_id.clone(),
                     },
                 );
             }
         }
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to write to local file: {}", e))?;
+        drop(file);
+        if let Some(hasher) = hasher {
+            verify_download(&local_path, expected_hash.as_deref(), hasher).await?;
+        }
 
         let _ = window.emit(
             "transfer-progress",
@@ -275,153 +992,258 @@ pub async fn download_cloud_file(
                 progress: downloaded,
                 total: total_size,
                 status: "complete".into(),
+                batch_id: batch_id.clone(),
             },
         );
 
+        tracing::info!(bytes = downloaded, "cloud download complete");
         return Ok(format!("Successfully downloaded file to {}", local_path));
     }
 
     Err(format!("Provider {} not recognized.", provider))
 }
 
+/// Builds the Dropbox path of `name` inside `parent`, which may be a path or an `id:` reference.
+fn dropbox_child_path(parent: Option<String>, name: &str) -> String {
+    // Dropbox paths must start with a slash or be completely empty for root
+    let mut parent_path = parent.unwrap_or_default();
+    if parent_path.starts_with("id:") {
+        // Dropbox supports uploading into a folder by ID, so we just append the filename
+        parent_path = if parent_path.ends_with('/') {
+            parent_path
+        } else {
+            format!("{}/", parent_path)
+        };
+    } else {
+        // It's a string path
+        if !parent_path.starts_with('/') && !parent_path.is_empty() {
+            parent_path = format!("/{}", parent_path);
+        }
+        if parent_path != "/" && !parent_path.ends_with('/') {
+            parent_path = format!("{}/", parent_path);
+        }
+        if parent_path == "/" {
+            parent_path = "/".to_string(); // Keep base slash
+        }
+    }
+
+    format!("{}{}", parent_path, name)
+}
+
 #[tauri::command]
+#[tracing::instrument(skip(window, token))]
 pub async fn upload_cloud_file(
-    _window: Window,
+    window: Window,
     provider: String,
     token: String,
     local_path: String,
     remote_parent_id: Option<String>,
+    batch_id: Option<String>,
 ) -> Result<String, String> {
-    let _transfer_id = format!("ul-{}", uuid::Uuid::new_v4());
-    let _file_name = std::path::Path::new(&local_path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown_file");
+    let data = std::fs::read(&local_path)
+        .map_err(|e| format!("Failed to read file into memory: {}", e))?;
+    let result = upload_cloud_data(
+        &window,
+        &provider,
+        &token,
+        &local_path,
+        data,
+        remote_parent_id.clone(),
+        batch_id,
+        None,
+    )
+    .await;
+    let cache = window.state::<CloudListingCache>();
+    invalidate_cloud_folder(&cache, &provider, &token, &remote_parent_id);
+    result
+}
 
-    if provider == "google" {
-        // Read the local file
-        let file = std::fs::File::open(&local_path)
-            .map_err(|e| format!("Failed to open local file: {}", e))?;
+/// Uploads content the frontend has in memory, such as a new or edited text
+/// file, as `file_name` in `remote_parent_id` without going through a local file.
+#[tauri::command]
+#[tracing::instrument(skip(window, token, data))]
+pub async fn upload_cloud_bytes(
+    window: Window,
+    provider: String,
+    token: String,
+    file_name: String,
+    data: Vec<u8>,
+    remote_parent_id: Option<String>,
+) -> Result<String, String> {
+    let result = upload_cloud_data(
+        &window,
+        &provider,
+        &token,
+        &file_name,
+        data,
+        remote_parent_id.clone(),
+        None,
+        None,
+    )
+    .await;
+    let cache = window.state::<CloudListingCache>();
+    invalidate_cloud_folder(&cache, &provider, &token, &remote_parent_id);
+    result
+}
 
-        // Suppress unused metadata warning since we might use it later
-        let _metadata = file
-            .metadata()
-            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+/// Uploads `data` under the file name of `source`, which is also what progress
+/// events report. With `replace`, the content of that existing file is
+/// overwritten instead of adding a new one.
+#[allow(clippy::too_many_arguments)]
+async fn upload_cloud_data(
+    window: &Window,
+    provider: &str,
+    token: &str,
+    source: &str,
+    data: Vec<u8>,
+    remote_parent_id: Option<String>,
+    batch_id: Option<String>,
+    replace: Option<&str>,
+) -> Result<String, String> {
+    let transfer_id = format!("ul-{}", uuid::Uuid::new_v4());
+    let registry = window.state::<TransferRegistry>();
+    let _active = registry.track(&transfer_id);
+    let total = data.len() as u64;
+    let emit_status = |progress: u64, status: &str| {
+        let _ = window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: transfer_id.clone(),
+                filename: source.to_string(),
+                progress,
+                total,
+                status: status.into(),
+                batch_id: batch_id.clone(),
+            },
+        );
+    };
+    // Lets the frontend learn the id so the upload can be cancelled
+    emit_status(0, "starting");
 
-        let file_name = std::path::Path::new(&local_path)
+    if provider == "google" {
+        let file_name = std::path::Path::new(source)
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown_file");
 
-        let client = Client::new();
-        let url = "https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart";
-
-        let parent_id = remote_parent_id.unwrap_or_else(|| "root".to_string());
-        let metadata_json = serde_json::json!({
-            "name": file_name,
-            "parents": [parent_id]
-        });
+        let client = api_client("google", token).await?;
+        let (method, url, metadata_json) = match replace {
+            // Parents can't be set on an update; the file stays where it is
+            Some(file_id) => (
+                reqwest::Method::PATCH,
+                format!(
+                    "{}/upload/drive/v3/files/{}?uploadType=multipart",
+                    api_base(token, Host::Google),
+                    file_id
+                ),
+                serde_json::json!({ "name": file_name }),
+            ),
+            None => (
+                reqwest::Method::POST,
+                format!(
+                    "{}/upload/drive/v3/files?uploadType=multipart",
+                    api_base(token, Host::Google)
+                ),
+                serde_json::json!({
+                    "name": file_name,
+                    "parents": [remote_parent_id.unwrap_or_else(|| "root".to_string())]
+                }),
+            ),
+        };
 
         let metadata_part = reqwest::multipart::Part::text(metadata_json.to_string())
             .mime_str("application/json")
             .unwrap();
 
-        let file_bytes = std::fs::read(&local_path)
-            .map_err(|e| format!("Failed to read file into memory: {}", e))?;
-
-        let media_part =
-            reqwest::multipart::Part::bytes(file_bytes).file_name(file_name.to_string());
+        let media_part = reqwest::multipart::Part::bytes(data).file_name(file_name.to_string());
 
         let form = reqwest::multipart::Form::new()
             .part("metadata", metadata_part)
             .part("file", media_part);
 
-        let res = client
-            .post(url)
+        let request = client
+            .request(method, url)
             .header("Authorization", format!("Bearer {}", token.trim()))
-            .multipart(form)
-            .send()
+            .multipart(form);
+        let Some(res) = send_cancellable(request, &registry, &transfer_id)
             .await
-            .map_err(|e| format!("Upload request failed: {}", e))?;
+            .map_err(|e| format!("Upload request failed: {}", e))?
+        else {
+            emit_status(0, "cancelled");
+            return Err("Transfer cancelled".into());
+        };
 
         if !res.status().is_success() {
+            let status = res.status();
             let err_text = res.text().await.unwrap_or_default();
+            tracing::warn!(%status, "cloud upload failed");
             return Err(format!("Upload API Error: {}", err_text));
         }
 
+        emit_status(total, "complete");
+        tracing::info!("cloud upload complete");
         return Ok(format!("Successfully uploaded {}", file_name));
     } else if provider == "dropbox" {
-        let file_name = std::path::Path::new(&local_path)
+        let file_name = std::path::Path::new(source)
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown_file");
 
-        let client = Client::new();
-
-        // Dropbox paths must start with a slash or be completely empty for root
-        let mut parent_path = remote_parent_id.unwrap_or_default();
-        if parent_path.starts_with("id:") {
-            // Dropbox supports uploading into a folder by ID, so we just append the filename
-            parent_path = if parent_path.ends_with('/') {
-                parent_path
-            } else {
-                format!("{}/", parent_path)
-            };
-        } else {
-            // It's a string path
-            if !parent_path.starts_with('/') && !parent_path.is_empty() {
-                parent_path = format!("/{}", parent_path);
-            }
-            if parent_path != "/" && !parent_path.ends_with('/') {
-                parent_path = format!("{}/", parent_path);
-            }
-            if parent_path == "/" {
-                parent_path = "/".to_string(); // Keep base slash
-            }
-        }
+        let client = api_client("dropbox", token).await?;
 
-        let upload_path = format!("{}{}", parent_path, file_name);
+        let upload_path = dropbox_child_path(remote_parent_id, file_name);
 
         let path_arg = serde_json::json!({
             "path": upload_path,
-            "mode": "add",
-            "autorename": true,
+            "mode": if replace.is_some() { "overwrite" } else { "add" },
+            "autorename": replace.is_none(),
             "mute": false
         });
 
-        let file_bytes = std::fs::read(&local_path)
-            .map_err(|e| format!("Failed to read file into memory: {}", e))?;
-
-        let res = client
-            .post("https://content.dropboxapi.com/2/files/upload")
+        let request = client
+            .post(format!(
+                "{}/2/files/upload",
+                api_base(token, Host::DropboxContent)
+            ))
             .header("Authorization", format!("Bearer {}", token.trim()))
             .header("Dropbox-API-Arg", path_arg.to_string())
             .header("Content-Type", "application/octet-stream")
-            .body(file_bytes)
-            .send()
+            .body(data);
+        let Some(res) = send_cancellable(request, &registry, &transfer_id)
             .await
-            .map_err(|e| format!("Dropbox Upload request failed: {}", e))?;
+            .map_err(|e| format!("Dropbox Upload request failed: {}", e))?
+        else {
+            emit_status(0, "cancelled");
+            return Err("Transfer cancelled".into());
+        };
 
         if !res.status().is_success() {
+            let status = res.status();
             let err_text = res.text().await.unwrap_or_default();
+            tracing::warn!(%status, "cloud upload failed");
             return Err(format!("Dropbox Upload API Error: {}", err_text));
         }
 
+        emit_status(total, "complete");
+        tracing::info!("cloud upload complete");
         return Ok(format!("Successfully uploaded {}", file_name));
     }
 
     Err(format!("Provider {} not recognized.", provider))
 }
 
-#[tauri::command]
-pub async fn delete_cloud_file(
+pub async fn delete_cloud_file_inner(
     provider: String,
     token: String,
     file_id: String,
 ) -> Result<String, String> {
-    let client = Client::new();
+    let client = api_client(&provider, &token).await?;
     if provider == "google" {
-        let url = format!("https://www.googleapis.com/drive/v3/files/{}", file_id);
+        let url = format!(
+            "{}/drive/v3/files/{}",
+            api_base(&token, Host::Google),
+            file_id
+        );
         let res = client
             .delete(&url)
             .header("Authorization", format!("Bearer {}", token.trim()))
@@ -436,7 +1258,10 @@ pub async fn delete_cloud_file(
         return Ok(format!("Successfully deleted file ID: {}", file_id));
     } else if provider == "dropbox" {
         let res = client
-            .post("https://api.dropboxapi.com/2/files/delete_v2")
+            .post(format!(
+                "{}/2/files/delete_v2",
+                api_base(&token, Host::DropboxApi)
+            ))
             .header("Authorization", format!("Bearer {}", token.trim()))
             .header("Content-Type", "application/json")
             .json(&serde_json::json!({
@@ -455,3 +1280,1547 @@ pub async fn delete_cloud_file(
 
     Err(format!("Provider {} not recognized.", provider))
 }
+
+#[tauri::command]
+pub async fn delete_cloud_file(
+    cache: State<'_, CloudListingCache>,
+    provider: String,
+    token: String,
+    file_id: String,
+) -> Result<String, String> {
+    let result = delete_cloud_file_inner(provider.clone(), token.clone(), file_id.clone()).await;
+    invalidate_cloud_entry(&cache, &provider, &token, &file_id);
+    result
+}
+
+#[derive(Deserialize)]
+struct CreatedFile {
+    id: String,
+}
+
+/// Measures request latency and throughput against a cloud provider by
+/// uploading, downloading and deleting a `size_bytes` test file.
+#[tauri::command]
+pub async fn benchmark_cloud_connection(
+    provider: String,
+    token: String,
+    size_bytes: Option<usize>,
+) -> Result<BenchmarkResult, String> {
+    let size = size_bytes
+        .unwrap_or(BENCHMARK_DEFAULT_BYTES)
+        .min(BENCHMARK_MAX_BYTES);
+    let client = api_client(&provider, &token).await?;
+    let auth = format!("Bearer {}", token.trim());
+    let payload = vec![0u8; size];
+    let name = format!(".quicksync-benchmark-{}", uuid::Uuid::new_v4());
+
+    // Each provider's cheapest authenticated request, an upload returning an id to
+    // fetch and delete by, and the matching download/delete requests
+    let (ping, upload) = match provider.as_str() {
+        "google" => (
+            client.get(format!(
+                "{}/drive/v3/about?fields=user",
+                api_base(&token, Host::Google)
+            )),
+            client
+                .post(format!(
+                    "{}/upload/drive/v3/files?uploadType=media",
+                    api_base(&token, Host::Google)
+                ))
+                .body(payload),
+        ),
+        "dropbox" => (
+            client.post(format!(
+                "{}/2/users/get_current_account",
+                api_base(&token, Host::DropboxApi)
+            )),
+            client
+                .post(format!(
+                    "{}/2/files/upload",
+                    api_base(&token, Host::DropboxContent)
+                ))
+                .header("Content-Type", "application/octet-stream")
+                .header(
+                    "Dropbox-API-Arg",
+                    serde_json::json!({ "path": format!("/{}", name), "mode": "overwrite" })
+                        .to_string(),
+                )
+                .body(payload),
+        ),
+        _ => return Err(format!("Provider {} not recognized.", provider)),
+    };
+
+    let started = Instant::now();
+    let res = ping
+        .header("Authorization", &auth)
+        .send()
+        .await
+        .map_err(|e| format!("Benchmark request failed: {}", e))?;
+    let latency = started.elapsed();
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Benchmark request failed: {}", err_text));
+    }
+
+    let started = Instant::now();
+    let res = upload
+        .header("Authorization", &auth)
+        .send()
+        .await
+        .map_err(|e| format!("Benchmark upload failed: {}", e))?;
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Benchmark upload failed: {}", err_text));
+    }
+    let created: CreatedFile = res
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse upload response: {}", e))?;
+    let upload_time = started.elapsed();
+
+    let (download, delete) = if provider == "google" {
+        let url = format!(
+            "{}/drive/v3/files/{}",
+            api_base(&token, Host::Google),
+            created.id
+        );
+        (client.get(format!("{}?alt=media", url)), client.delete(url))
+    } else {
+        let path_arg = serde_json::json!({ "path": created.id });
+        (
+            client
+                .post(format!(
+                    "{}/2/files/download",
+                    api_base(&token, Host::DropboxContent)
+                ))
+                .header("Dropbox-API-Arg", path_arg.to_string()),
+            client
+                .post(format!(
+                    "{}/2/files/delete_v2",
+                    api_base(&token, Host::DropboxApi)
+                ))
+                .json(&path_arg),
+        )
+    };
+
+    let started = Instant::now();
+    let downloaded = async {
+        let res = download
+            .header("Authorization", &auth)
+            .send()
+            .await
+            .map_err(|e| format!("Benchmark download failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Benchmark download failed: {}", err_text));
+        }
+        res.bytes()
+            .await
+            .map(|b| b.len())
+            .map_err(|e| format!("Benchmark download failed: {}", e))
+    }
+    .await;
+    let download_time = started.elapsed();
+
+    // The test file goes whether or not the download worked
+    if let Err(e) = delete.header("Authorization", &auth).send().await {
+        tracing::warn!(error = %e, "failed to delete benchmark file");
+    }
+    let downloaded = downloaded?;
+
+    Ok(BenchmarkResult {
+        latency_ms: latency.as_secs_f64() * 1000.0,
+        upload_bytes_per_sec: bytes_per_sec(size, upload_time),
+        download_bytes_per_sec: bytes_per_sec(downloaded, download_time),
+        bytes: size as u64,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphDriveItem {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(rename = "lastModifiedDateTime", default)]
+    last_modified: Option<String>,
+    #[serde(default)]
+    folder: Option<serde_json::Value>,
+    #[serde(default)]
+    deleted: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphDeltaPage {
+    value: Vec<GraphDriveItem>,
+    #[serde(rename = "@odata.nextLink", default)]
+    next_link: Option<String>,
+    #[serde(rename = "@odata.deltaLink", default)]
+    delta_link: Option<String>,
+}
+
+/// Items changed since the last sync, and the token to pass next time.
+#[derive(Serialize)]
+pub struct CloudChanges {
+    pub changed: Vec<CloudEntry>,
+    /// Ids of items deleted since the last sync.
+    pub deleted: Vec<String>,
+    pub delta_token: String,
+}
+
+/// Pulls the `token` query parameter out of a Graph `@odata.deltaLink`.
+fn delta_token_from_link(link: &str) -> Option<String> {
+    let query = link.split_once('?')?.1;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="))
+        .map(str::to_string)
+}
+
+/// Lists what changed in the drive since `delta_token` via Graph's delta API.
+/// Without a token (or once the old one expired) every item is returned, which
+/// doubles as the initial full listing. Save `delta_token` on the
+/// `CloudConnection` for the next call.
+#[tauri::command]
+pub async fn get_cloud_changes(
+    provider: String,
+    token: String,
+    delta_token: Option<String>,
+) -> Result<CloudChanges, String> {
+    if provider != "onedrive" && provider != "microsoft" {
+        return Err(format!("Change tracking is not supported for {}", provider));
+    }
+    let client = api_client(&provider, &token).await?;
+    let delta_url = format!("{}/v1.0/me/drive/root/delta", api_base(&token, Host::Graph));
+    let start_url = |delta_token: Option<&str>| match delta_token.filter(|t| !t.is_empty()) {
+        Some(t) => format!("{}?token={}", delta_url, t),
+        None => delta_url.clone(),
+    };
+
+    let mut changes = CloudChanges {
+        changed: Vec::new(),
+        deleted: Vec::new(),
+        delta_token: String::new(),
+    };
+    let mut url = start_url(delta_token.as_deref());
+    loop {
+        let res = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .send()
+            .await
+            .map_err(|e| format!("OneDrive request failed: {}", e))?;
+
+        // 410 Gone means the token expired and the client has to resync from scratch
+        if res.status() == reqwest::StatusCode::GONE && url != delta_url {
+            tracing::info!("OneDrive delta token expired, starting a full listing");
+            changes.changed.clear();
+            changes.deleted.clear();
+            url = start_url(None);
+            continue;
+        }
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("OneDrive API Error: {}", err_text));
+        }
+
+        let page: GraphDeltaPage = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse OneDrive response: {}", e))?;
+        for item in page.value {
+            if item.deleted.is_some() {
+                changes.deleted.push(item.id);
+                continue;
+            }
+            changes.changed.push(CloudEntry {
+                name: item.name.unwrap_or_default(),
+                is_dir: item.folder.is_some(),
+                size: item.size,
+                last_modified: item.last_modified,
+                id: Some(item.id),
+            });
+        }
+
+        match (page.next_link, page.delta_link) {
+            (Some(next), _) => url = next,
+            (None, Some(delta)) => {
+                changes.delta_token = delta_token_from_link(&delta)
+                    .ok_or_else(|| "OneDrive returned a delta link without a token".to_string())?;
+                return Ok(changes);
+            }
+            (None, None) => return Err("OneDrive response had no next or delta link".into()),
+        }
+    }
+}
+
+/// A file of a folder being downloaded and where it goes locally.
+struct FolderFile {
+    id: String,
+    local_path: std::path::PathBuf,
+    size: u64,
+}
+
+/// Lists every file under a Google Drive folder, creating the matching local
+/// directories along the way, so the batch total is known before downloading.
+/// Google Docs and other native documents go to `skipped` by local path.
+#[async_recursion::async_recursion]
+async fn google_folder_files(
+    token: &str,
+    folder_id: &str,
+    local_dir: &std::path::Path,
+    files: &mut Vec<FolderFile>,
+    skipped: &mut Vec<String>,
+) -> Result<(), String> {
+    std::fs::create_dir_all(local_dir).map_err(|e| format!("Failed to create local dir: {}", e))?;
+
+    for file in google_folder_children(token, folder_id).await? {
+        let entry_local_path = match join_untrusted_name(local_dir, &file.name) {
+            Ok(path) => path,
+            Err(e) => {
+                tracing::warn!(error = %e, folder_id, "skipping folder entry");
+                continue;
+            }
+        };
+
+        if file.mimeType == GOOGLE_FOLDER_MIME {
+            google_folder_files(token, &file.id, &entry_local_path, files, skipped).await?;
+        } else if is_google_native(&file) {
+            skipped.push(entry_local_path.to_string_lossy().to_string());
+        } else {
+            files.push(FolderFile {
+                id: file.id,
+                local_path: entry_local_path,
+                size: file.size.and_then(|s| s.parse().ok()).unwrap_or(0),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+async fn dropbox_list_recursive(
+    client: &Client,
+    token: &str,
+    path: &str,
+) -> Result<Vec<DropboxFile>, String> {
+    let res = client
+        .post(format!(
+            "{}/2/files/list_folder",
+            api_base(token, Host::DropboxApi)
+        ))
+        .header("Authorization", format!("Bearer {}", token.trim()))
+        .json(&serde_json::json!({ "path": path, "recursive": true }))
+        .send()
+        .await
+        .map_err(|e| format!("Dropbox Network request failed: {}", e))?;
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Dropbox API Error: {}", err_text));
+    }
+    let mut page: DropboxListResponse = res
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+
+    let mut entries = std::mem::take(&mut page.entries);
+    while page.has_more {
+        let res = client
+            .post(format!(
+                "{}/2/files/list_folder/continue",
+                api_base(token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({ "cursor": page.cursor }))
+            .send()
+            .await
+            .map_err(|e| format!("Dropbox Network request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Dropbox API Error: {}", err_text));
+        }
+        page = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+        entries.append(&mut page.entries);
+    }
+
+    Ok(entries)
+}
+
+async fn dropbox_path_display(client: &Client, token: &str, path: &str) -> Result<String, String> {
+    if path.is_empty() {
+        return Ok(String::new());
+    }
+    let res = client
+        .post(format!(
+            "{}/2/files/get_metadata",
+            api_base(token, Host::DropboxApi)
+        ))
+        .header("Authorization", format!("Bearer {}", token.trim()))
+        .json(&serde_json::json!({ "path": path }))
+        .send()
+        .await
+        .map_err(|e| format!("Dropbox Network request failed: {}", e))?;
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Dropbox API Error: {}", err_text));
+    }
+    let meta: DropboxFile = res
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+    Ok(meta.path_display.unwrap_or_default())
+}
+
+/// Recursively downloads a cloud folder into `local_dir`, recreating its tree.
+/// Progress is also reported for the folder as a whole, see `BatchProgress`.
+#[tauri::command]
+pub async fn download_cloud_folder(
+    window: Window,
+    provider: String,
+    token: String,
+    folder_id: String,
+    local_dir: String,
+) -> Result<String, String> {
+    let local_root = std::path::PathBuf::from(&local_dir);
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+
+    if provider == "google" {
+        google_folder_files(&token, &folder_id, &local_root, &mut files, &mut skipped).await?;
+    } else if provider == "dropbox" {
+        let client = api_client("dropbox", &token).await?;
+        let root_display = dropbox_path_display(&client, &token, &folder_id).await?;
+        // Entries come back with absolute paths; strip the folder's own components
+        let skip = root_display.split('/').filter(|c| !c.is_empty()).count();
+
+        let entries = dropbox_list_recursive(&client, &token, &folder_id).await?;
+        for entry in entries {
+            let Some(path_display) = entry.path_display else {
+                continue;
+            };
+            let mut components = path_display
+                .split('/')
+                .filter(|c| !c.is_empty())
+                .skip(skip)
+                .peekable();
+            if components.peek().is_none() {
+                continue;
+            }
+            let entry_local_path = match components.try_fold(local_root.clone(), |dir, name| {
+                join_untrusted_name(&dir, name)
+            }) {
+                Ok(path) => path,
+                Err(e) => {
+                    tracing::warn!(error = %e, "skipping folder entry");
+                    continue;
+                }
+            };
+
+            if entry.tag == "folder" {
+                std::fs::create_dir_all(&entry_local_path)
+                    .map_err(|e| format!("Failed to create local dir: {}", e))?;
+            } else if entry.tag == "file" {
+                if let Some(parent) = entry_local_path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create local dir: {}", e))?;
+                }
+                files.push(FolderFile {
+                    id: entry.id,
+                    local_path: entry_local_path,
+                    size: entry.size.unwrap_or(0),
+                });
+            }
+        }
+    } else {
+        return Err(format!("Provider {} not recognized.", provider));
+    }
+
+    let total: u64 = files.iter().map(|f| f.size).sum();
+    let batch = BatchProgress::start(&window, "dl", &local_dir, total);
+    for path in &skipped {
+        batch.fail(
+            &format!("dl-{}", uuid::Uuid::new_v4()),
+            path,
+            "Google Docs files have no content to download as-is".into(),
+        );
+    }
+    for file in &files {
+        download_cloud_file(
+            window.clone(),
+            provider.clone(),
+            token.clone(),
+            file.id.clone(),
+            file.local_path.to_string_lossy().to_string(),
+            None,
+            Some(batch.id().to_string()),
+        )
+        .await?;
+        batch.advance(file.size, "downloading");
+    }
+    batch.finish();
+
+    let mut summary = format!(
+        "Downloaded {} files ({} bytes) to {}",
+        files.len(),
+        total,
+        local_dir
+    );
+    if !skipped.is_empty() {
+        summary.push_str(&format!(", skipped {} Google Docs file(s)", skipped.len()));
+    }
+    Ok(summary)
+}
+
+#[derive(Deserialize, Debug)]
+struct DropboxCreateFolderResponse {
+    metadata: DropboxFolderMetadata,
+}
+
+#[derive(Deserialize, Debug)]
+struct DropboxFolderMetadata {
+    name: String,
+    id: String,
+}
+
+pub async fn create_cloud_folder_inner(
+    provider: String,
+    token: String,
+    parent_id: Option<String>,
+    name: String,
+) -> Result<CloudEntry, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let parent = parent_id.unwrap_or_else(|| "root".to_string());
+        let res = client
+            .post(format!(
+                "{}/drive/v3/files?fields=id,name,mimeType",
+                api_base(&token, Host::Google)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "name": name,
+                "mimeType": "application/vnd.google-apps.folder",
+                "parents": [parent]
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Google Drive Create Folder request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Google Drive Create Folder Error: {}", err_text));
+        }
+        let folder: GoogleDriveFile = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Google Drive response: {}", e))?;
+
+        return Ok(CloudEntry {
+            name: folder.name,
+            is_dir: true,
+            size: None,
+            last_modified: None,
+            id: Some(folder.id),
+        });
+    } else if provider == "dropbox" {
+        let res = client
+            .post(format!(
+                "{}/2/files/create_folder_v2",
+                api_base(&token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "path": dropbox_child_path(parent_id, &name),
+                "autorename": false
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Dropbox Create Folder request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Dropbox Create Folder Error: {}", err_text));
+        }
+        let created: DropboxCreateFolderResponse = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+
+        return Ok(CloudEntry {
+            name: created.metadata.name,
+            is_dir: true,
+            size: None,
+            last_modified: None,
+            id: Some(created.metadata.id),
+        });
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+/// Creates a folder named `name` under `parent_id` (the root when omitted).
+#[tauri::command]
+pub async fn create_cloud_folder(
+    cache: State<'_, CloudListingCache>,
+    provider: String,
+    token: String,
+    parent_id: Option<String>,
+    name: String,
+) -> Result<CloudEntry, String> {
+    let result =
+        create_cloud_folder_inner(provider.clone(), token.clone(), parent_id.clone(), name).await;
+    invalidate_cloud_folder(&cache, &provider, &token, &parent_id);
+    result
+}
+
+pub async fn rename_cloud_file_inner(
+    provider: String,
+    token: String,
+    file_id: String,
+    new_name: String,
+) -> Result<String, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let url = format!(
+            "{}/drive/v3/files/{}",
+            api_base(&token, Host::Google),
+            file_id
+        );
+        let res = client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({ "name": new_name }))
+            .send()
+            .await
+            .map_err(|e| format!("Google Drive Rename request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Google Drive Rename Error: {}", err_text));
+        }
+        if let Ok(mut parents) = GOOGLE_PARENTS.write() {
+            parents.remove(&(token_fingerprint(&token), file_id.clone()));
+        }
+        return Ok(format!("Renamed {} to {}", file_id, new_name));
+    } else if provider == "dropbox" {
+        // Dropbox renames by moving, which needs the full destination path
+        let from = dropbox_path_display(&client, &token, &file_id).await?;
+        let parent = from.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+        let to = format!("{}/{}", parent, new_name);
+        let res = client
+            .post(format!(
+                "{}/2/files/move_v2",
+                api_base(&token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "from_path": from,
+                "to_path": to,
+                "autorename": false
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Dropbox Rename request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Dropbox Rename Error: {}", err_text));
+        }
+        return Ok(format!("Renamed {} to {}", from, to));
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+/// Renames a file or folder in place; `new_name` is a bare name.
+#[tauri::command]
+pub async fn rename_cloud_file(
+    cache: State<'_, CloudListingCache>,
+    provider: String,
+    token: String,
+    file_id: String,
+    new_name: String,
+) -> Result<String, String> {
+    let result =
+        rename_cloud_file_inner(provider.clone(), token.clone(), file_id.clone(), new_name).await;
+    invalidate_cloud_entry(&cache, &provider, &token, &file_id);
+    result
+}
+
+/// Where a cloud item sits: its path from the drive root and the ids of the
+/// folders above it, outermost first.
+#[derive(Serialize)]
+pub struct CloudPath {
+    pub path: String,
+    /// Google folder ids, starting with the root; Dropbox folder paths, which
+    /// work as ids there.
+    pub ancestor_ids: Vec<String>,
+}
+
+#[derive(Deserialize, Clone)]
+struct GoogleParentInfo {
+    name: String,
+    #[serde(default)]
+    parents: Vec<String>,
+}
+
+/// Names and parents fetched by `resolve_cloud_path`, keyed by token fingerprint
+/// and item id, so resolving siblings doesn't refetch their shared ancestors.
+static GOOGLE_PARENTS: RwLock<BTreeMap<(u64, String), GoogleParentInfo>> =
+    RwLock::new(BTreeMap::new());
+
+/// Stops a corrupt or cyclic parent chain from looping forever.
+const MAX_PATH_DEPTH: usize = 256;
+
+async fn google_parent_info(
+    client: &Client,
+    token: &str,
+    id: &str,
+) -> Result<GoogleParentInfo, String> {
+    let key = (token_fingerprint(token), id.to_string());
+    if let Some(hit) = GOOGLE_PARENTS
+        .read()
+        .ok()
+        .and_then(|parents| parents.get(&key).cloned())
+    {
+        return Ok(hit);
+    }
+    let url = format!(
+        "{}/drive/v3/files/{}?fields=name,parents",
+        api_base(token, Host::Google),
+        id
+    );
+    let res = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token.trim()))
+        .send()
+        .await
+        .map_err(|e| format!("Google Drive Network request failed: {}", e))?;
+    if !res.status().is_success() {
+        let err_text = res.text().await.unwrap_or_default();
+        return Err(format!("Google Drive API Error: {}", err_text));
+    }
+    let info: GoogleParentInfo = res
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Google Drive response: {}", e))?;
+    if let Ok(mut parents) = GOOGLE_PARENTS.write() {
+        parents.insert(key, info.clone());
+    }
+    Ok(info)
+}
+
+/// Resolves the full path of a cloud item, e.g. for breadcrumbs of a search
+/// result. Google is walked up one parent at a time (cached); Dropbox reports
+/// the path directly.
+#[tauri::command]
+pub async fn resolve_cloud_path(
+    provider: String,
+    token: String,
+    file_id: String,
+) -> Result<CloudPath, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let item = google_parent_info(&client, &token, &file_id).await?;
+        let mut names = vec![item.name];
+        let mut ancestor_ids = Vec::new();
+        let mut parent = item.parents.first().cloned();
+        while let Some(id) = parent {
+            if ancestor_ids.len() >= MAX_PATH_DEPTH || ancestor_ids.contains(&id) {
+                return Err(format!("Parent chain of {} does not end", file_id));
+            }
+            let info = google_parent_info(&client, &token, &id).await?;
+            parent = info.parents.first().cloned();
+            // The root ("My Drive") has no parent and is left out of the path
+            if parent.is_some() {
+                names.push(info.name);
+            }
+            ancestor_ids.push(id);
+        }
+        names.reverse();
+        ancestor_ids.reverse();
+        return Ok(CloudPath {
+            path: format!("/{}", names.join("/")),
+            ancestor_ids,
+        });
+    } else if provider == "dropbox" {
+        let path = dropbox_path_display(&client, &token, &file_id).await?;
+        let mut ancestor_ids = vec![String::new()];
+        let mut prefix = String::new();
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        for component in components.iter().take(components.len().saturating_sub(1)) {
+            prefix = format!("{}/{}", prefix, component);
+            ancestor_ids.push(prefix.clone());
+        }
+        return Ok(CloudPath { path, ancestor_ids });
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+/// A stored version of a cloud file, newest last for Google and newest first
+/// for Dropbox, as each provider reports them.
+#[derive(Serialize)]
+pub struct RevisionEntry {
+    pub id: String,
+    pub modified: Option<String>,
+    /// Missing for Google Docs, whose revisions have no binary content.
+    pub size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct GoogleRevision {
+    id: String,
+    modifiedTime: Option<String>,
+    size: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct GoogleRevisionList {
+    #[serde(default)]
+    revisions: Vec<GoogleRevision>,
+    nextPageToken: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DropboxRevision {
+    rev: String,
+    server_modified: Option<String>,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct DropboxRevisionList {
+    #[serde(default)]
+    entries: Vec<DropboxRevision>,
+}
+
+/// Dropbox caps `list_revisions` at this many entries.
+const DROPBOX_MAX_REVISIONS: u32 = 100;
+
+/// Lists the stored versions of a file. Files without version history, such as
+/// folders or Google Drive shortcuts, yield an empty list rather than an error.
+#[tauri::command]
+pub async fn list_cloud_revisions(
+    provider: String,
+    token: String,
+    file_id: String,
+) -> Result<Vec<RevisionEntry>, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let mut revisions = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/drive/v3/files/{}/revisions?fields=nextPageToken,revisions(id,modifiedTime,size)&pageSize=1000", api_base(&token, Host::Google),
+                file_id
+            );
+            if let Some(page) = page_token.take() {
+                url.push_str(&format!("&pageToken={}", urlencoding::encode(&page)));
+            }
+            let res = client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", token.trim()))
+                .send()
+                .await
+                .map_err(|e| format!("Google Drive Revisions request failed: {}", e))?;
+            if !res.status().is_success() {
+                let err_text = res.text().await.unwrap_or_default();
+                if err_text.contains("revisionsNotSupported") {
+                    return Ok(Vec::new());
+                }
+                return Err(format!("Google Drive Revisions Error: {}", err_text));
+            }
+            let page: GoogleRevisionList = res
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse Google Drive response: {}", e))?;
+            revisions.extend(page.revisions.into_iter().map(|r| RevisionEntry {
+                id: r.id,
+                modified: r.modifiedTime,
+                size: r.size.and_then(|s| s.parse().ok()),
+            }));
+            match page.nextPageToken {
+                Some(next) => page_token = Some(next),
+                None => return Ok(revisions),
+            }
+        }
+    } else if provider == "dropbox" {
+        let res = client
+            .post(format!(
+                "{}/2/files/list_revisions",
+                api_base(&token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "path": file_id,
+                "limit": DROPBOX_MAX_REVISIONS
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Dropbox Revisions request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            // Folders and other non-files have no revisions
+            if err_text.contains("not_file") {
+                return Ok(Vec::new());
+            }
+            return Err(format!("Dropbox Revisions Error: {}", err_text));
+        }
+        let list: DropboxRevisionList = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+        return Ok(list
+            .entries
+            .into_iter()
+            .map(|r| RevisionEntry {
+                id: r.rev,
+                modified: r.server_modified,
+                size: r.size,
+            })
+            .collect());
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+pub async fn restore_cloud_revision_inner(
+    provider: String,
+    token: String,
+    file_id: String,
+    revision_id: String,
+) -> Result<String, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let url = format!(
+            "{}/drive/v3/files/{}/revisions/{}?alt=media",
+            api_base(&token, Host::Google),
+            file_id,
+            revision_id
+        );
+        let res = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .send()
+            .await
+            .map_err(|e| format!("Google Drive Revision download failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Google Drive Revision Error: {}", err_text));
+        }
+        let size = res.content_length();
+
+        let url = format!(
+            "{}/upload/drive/v3/files/{}?uploadType=media",
+            api_base(&token, Host::Google),
+            file_id
+        );
+        // The revision is piped into the upload as it downloads, so a large file
+        // is never held in memory
+        let mut request = client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .body(reqwest::Body::wrap_stream(res.bytes_stream()));
+        if let Some(size) = size {
+            request = request.header("Content-Length", size);
+        }
+        let res = request
+            .send()
+            .await
+            .map_err(|e| format!("Google Drive Restore request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Google Drive Restore Error: {}", err_text));
+        }
+        return Ok(format!("Restored {} to revision {}", file_id, revision_id));
+    } else if provider == "dropbox" {
+        // Unlike most endpoints, restore only takes a path, not an id
+        let path = dropbox_path_display(&client, &token, &file_id).await?;
+        let res = client
+            .post(format!(
+                "{}/2/files/restore",
+                api_base(&token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "path": path,
+                "rev": revision_id
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Dropbox Restore request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Dropbox Restore Error: {}", err_text));
+        }
+        return Ok(format!("Restored {} to revision {}", path, revision_id));
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+/// Makes `revision_id` the current content of a file. Dropbox restores in
+/// place; Google has no restore call, so the revision's content is uploaded as
+/// a new revision. Google Docs revisions can't be restored this way.
+#[tauri::command]
+pub async fn restore_cloud_revision(
+    cache: State<'_, CloudListingCache>,
+    provider: String,
+    token: String,
+    file_id: String,
+    revision_id: String,
+) -> Result<String, String> {
+    let result = restore_cloud_revision_inner(
+        provider.clone(),
+        token.clone(),
+        file_id.clone(),
+        revision_id,
+    )
+    .await;
+    invalidate_cloud_entry(&cache, &provider, &token, &file_id);
+    result
+}
+
+/// A Dropbox listing entry with `include_deleted`; deleted entries carry no id.
+#[derive(Deserialize)]
+struct DropboxTrashEntry {
+    #[serde(rename = ".tag")]
+    tag: String,
+    name: String,
+    path_display: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DropboxTrashPage {
+    entries: Vec<DropboxTrashEntry>,
+    #[serde(default)]
+    cursor: String,
+    #[serde(default)]
+    has_more: bool,
+}
+
+/// Lists trashed (Google) or deleted (Dropbox) items. Dropbox ids are the
+/// deleted paths, as deleted items have no id; Dropbox doesn't say whether a
+/// deleted item was a folder, so all are reported as files.
+#[tauri::command]
+pub async fn list_cloud_trash(provider: String, token: String) -> Result<Vec<CloudEntry>, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let files = google_query_files(&token, "trashed = true").await?;
+        return Ok(files
+            .into_iter()
+            .map(|file| CloudEntry {
+                is_dir: file.mimeType == GOOGLE_FOLDER_MIME,
+                size: file.size.and_then(|s| s.parse::<u64>().ok()),
+                name: file.name,
+                last_modified: file.modifiedTime,
+                id: Some(file.id),
+            })
+            .collect());
+    } else if provider == "dropbox" {
+        let mut request = client
+            .post(format!(
+                "{}/2/files/list_folder",
+                api_base(&token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "path": "",
+                "recursive": true,
+                "include_deleted": true
+            }));
+        let mut entries = Vec::new();
+        loop {
+            let res = send_with_retry(request)
+                .await
+                .map_err(|e| format!("Dropbox Trash request failed: {}", e))?;
+            if !res.status().is_success() {
+                let err_text = res.text().await.unwrap_or_default();
+                return Err(format!("Dropbox Trash Error: {}", err_text));
+            }
+            let page: DropboxTrashPage = res
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse Dropbox response: {}", e))?;
+            entries.extend(
+                page.entries
+                    .into_iter()
+                    .filter(|entry| entry.tag == "deleted")
+                    .map(|entry| CloudEntry {
+                        name: entry.name,
+                        is_dir: false,
+                        size: None,
+                        last_modified: None,
+                        id: entry.path_display,
+                    }),
+            );
+            if !page.has_more {
+                return Ok(entries);
+            }
+            request = client
+                .post(format!(
+                    "{}/2/files/list_folder/continue",
+                    api_base(&token, Host::DropboxApi)
+                ))
+                .header("Authorization", format!("Bearer {}", token.trim()))
+                .json(&serde_json::json!({ "cursor": page.cursor }));
+        }
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+pub async fn restore_cloud_file_inner(
+    provider: String,
+    token: String,
+    file_id: String,
+) -> Result<String, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let url = format!(
+            "{}/drive/v3/files/{}",
+            api_base(&token, Host::Google),
+            file_id
+        );
+        let res = client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({ "trashed": false }))
+            .send()
+            .await
+            .map_err(|e| format!("Google Drive Restore request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Google Drive Restore Error: {}", err_text));
+        }
+        return Ok(format!("Restored {}", file_id));
+    } else if provider == "dropbox" {
+        let revisions = list_cloud_revisions(provider, token.clone(), file_id.clone()).await?;
+        let Some(latest) = revisions.into_iter().next() else {
+            return Err(format!(
+                "{} has no revisions to restore; Dropbox folders can only be restored from its website",
+                file_id
+            ));
+        };
+        let res = client
+            .post(format!(
+                "{}/2/files/restore",
+                api_base(&token, Host::DropboxApi)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .json(&serde_json::json!({
+                "path": file_id,
+                "rev": latest.id
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Dropbox Restore request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Dropbox Restore Error: {}", err_text));
+        }
+        return Ok(format!("Restored {}", file_id));
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+/// Restores a trashed or deleted item to where it was. On Dropbox this brings
+/// back the file's latest revision; deleted folders can't be restored there.
+#[tauri::command]
+pub async fn restore_cloud_file(
+    cache: State<'_, CloudListingCache>,
+    provider: String,
+    token: String,
+    file_id: String,
+) -> Result<String, String> {
+    let result = restore_cloud_file_inner(provider.clone(), token.clone(), file_id).await;
+    // Where the item reappears isn't known here
+    invalidate_cloud_account(&cache, &provider, &token);
+    result
+}
+
+/// Permanently deletes everything in the Google Drive trash. Dropbox has no
+/// such call: deleted files expire on their own after the account's retention
+/// period.
+#[tauri::command]
+pub async fn empty_cloud_trash(provider: String, token: String) -> Result<String, String> {
+    let client = api_client(&provider, &token).await?;
+    if provider == "google" {
+        let res = client
+            .delete(format!(
+                "{}/drive/v3/files/trash",
+                api_base(&token, Host::Google)
+            ))
+            .header("Authorization", format!("Bearer {}", token.trim()))
+            .send()
+            .await
+            .map_err(|e| format!("Google Drive Empty Trash request failed: {}", e))?;
+        if !res.status().is_success() {
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(format!("Google Drive Empty Trash Error: {}", err_text));
+        }
+        return Ok("Emptied the Google Drive trash".to_string());
+    } else if provider == "dropbox" {
+        return Err(
+            "Dropbox does not support emptying deleted files; they expire after the retention period"
+                .to_string(),
+        );
+    }
+
+    Err(format!("Provider {} not recognized.", provider))
+}
+
+/// What `upload_cloud_folder` does with a file whose name is already taken in
+/// the cloud folder it goes to.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Leaves the cloud file alone and doesn't upload the local one.
+    #[default]
+    Skip,
+    /// Replaces the content of the cloud file.
+    Overwrite,
+    /// Uploads next to it as "name (1).ext", "name (2).ext", ...
+    Rename,
+}
+
+/// First "stem (n).ext" not in `taken`.
+fn free_name(name: &str, taken: &[String]) -> String {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| format!("{} ({}){}", stem, n, ext))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_default()
+}
+
+#[async_recursion::async_recursion]
+#[allow(clippy::too_many_arguments)]
+async fn upload_folder_recursive(
+    window: &Window,
+    provider: &str,
+    token: &str,
+    local_dir: &std::path::Path,
+    parent_id: Option<String>,
+    conflict: ConflictPolicy,
+    batch: &BatchProgress<'_>,
+    files: &mut u64,
+    skipped: &mut u64,
+) -> Result<(), String> {
+    let folder_name = local_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Invalid folder name: {}", local_dir.display()))?;
+
+    // Reuse a folder that already exists under the parent instead of creating a duplicate
+    let existing =
+        fetch_cloud_directory(provider.to_string(), token.to_string(), parent_id.clone())
+            .await?
+            .into_iter()
+            .find(|e| e.is_dir && e.name == folder_name);
+    let (folder, existing_files) = match existing {
+        Some(folder) => {
+            let children =
+                fetch_cloud_directory(provider.to_string(), token.to_string(), folder.id.clone())
+                    .await?;
+            (folder, children)
+        }
+        None => {
+            let folder = create_cloud_folder_inner(
+                provider.to_string(),
+                token.to_string(),
+                parent_id,
+                folder_name,
+            )
+            .await?;
+            (folder, Vec::new())
+        }
+    };
+    let folder_id = folder.id;
+    let mut taken: Vec<String> = existing_files.iter().map(|e| e.name.clone()).collect();
+
+    let read_dir = std::fs::read_dir(local_dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", local_dir.display(), e))?;
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        // Symlinks aren't followed, so a link back up the tree can't loop
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_symlink() {
+            tracing::warn!(path = %path.display(), "skipping symlink in folder upload");
+        } else if file_type.is_dir() {
+            upload_folder_recursive(
+                window,
+                provider,
+                token,
+                &path,
+                folder_id.clone(),
+                conflict,
+                batch,
+                files,
+                skipped,
+            )
+            .await?;
+        } else if file_type.is_file() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let name = entry.file_name().to_string_lossy().to_string();
+            let clash = existing_files.iter().find(|e| !e.is_dir && e.name == name);
+            let (source, replace) = match (clash, conflict) {
+                (Some(_), ConflictPolicy::Skip) => {
+                    *skipped += 1;
+                    batch.advance(size, "uploading");
+                    continue;
+                }
+                (Some(existing), ConflictPolicy::Overwrite) => {
+                    (path.clone(), Some(existing.id.as_str()))
+                }
+                (Some(_), ConflictPolicy::Rename) => {
+                    (path.with_file_name(free_name(&name, &taken)), None)
+                }
+                (None, _) => (path.clone(), None),
+            };
+            let data = std::fs::read(&path)
+                .map_err(|e| format!("Failed to read file into memory: {}", e))?;
+            let source = source.to_string_lossy().to_string();
+            upload_cloud_data(
+                window,
+                provider,
+                token,
+                &source,
+                data,
+                folder_id.clone(),
+                Some(batch.id().to_string()),
+                replace,
+            )
+            .await?;
+            if let Some(uploaded) = std::path::Path::new(&source).file_name() {
+                taken.push(uploaded.to_string_lossy().to_string());
+            }
+            *files += 1;
+            batch.advance(size, "uploading");
+        }
+    }
+
+    Ok(())
+}
+
+/// Total size of the files under `dir`, counted the way `upload_folder_recursive`
+/// walks it: symlinks are skipped and unreadable entries count as empty.
+fn local_dir_size(dir: &std::path::Path) -> u64 {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    read_dir
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => local_dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Recursively uploads `local_dir` (the folder itself and its contents) under
+/// `parent_id`. Existing folders are reused; files whose name is already taken
+/// are handled per `conflict`, skipped by default.
+#[tauri::command]
+pub async fn upload_cloud_folder(
+    window: Window,
+    provider: String,
+    token: String,
+    local_dir: String,
+    parent_id: Option<String>,
+    conflict: Option<ConflictPolicy>,
+) -> Result<String, String> {
+    let local_root = std::path::PathBuf::from(&local_dir);
+    if !local_root.is_dir() {
+        return Err(format!("Not a directory: {}", local_dir));
+    }
+    if provider != "google" && provider != "dropbox" {
+        return Err(format!("Provider {} not recognized.", provider));
+    }
+
+    let total = local_dir_size(&local_root);
+    let batch = BatchProgress::start(&window, "ul", &local_dir, total);
+    let mut files = 0u64;
+    let mut skipped = 0u64;
+    let result = upload_folder_recursive(
+        &window,
+        &provider,
+        &token,
+        &local_root,
+        parent_id,
+        conflict.unwrap_or_default(),
+        &batch,
+        &mut files,
+        &mut skipped,
+    )
+    .await;
+    // Dropbox folder ids are paths, so listings cached under the new folders'
+    // paths may be stale as well as the parent's
+    invalidate_cloud_account(&window.state::<CloudListingCache>(), &provider, &token);
+    result?;
+    batch.finish();
+
+    let mut summary = format!(
+        "Uploaded {} files ({} bytes) from {}",
+        files, total, local_dir
+    );
+    if skipped > 0 {
+        summary.push_str(&format!(", skipped {} existing file(s)", skipped));
+    }
+    Ok(summary)
+}
+
+/// `Remote` over a cloud drive account. Paths are the provider's item ids.
+pub struct CloudRemote<'w> {
+    window: &'w Window,
+    provider: String,
+    token: String,
+}
+
+impl<'w> CloudRemote<'w> {
+    pub fn new(window: &'w Window, kind: RemoteKind, token: String) -> Self {
+        let provider = match kind {
+            RemoteKind::Dropbox => "dropbox",
+            _ => "google",
+        };
+        Self {
+            window,
+            provider: provider.to_string(),
+            token,
+        }
+    }
+
+    /// Drops the account's cached listings after a change, since any of them may be stale.
+    fn invalidate(&self) {
+        invalidate_cloud_account(
+            &self.window.state::<CloudListingCache>(),
+            &self.provider,
+            &self.token,
+        );
+    }
+}
+
+impl Remote for CloudRemote<'_> {
+    fn list<'a>(
+        &'a self,
+        path: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<RemoteEntry>, String>> {
+        Box::pin(async move {
+            let entries = list_cloud_directory(
+                self.window.state(),
+                self.provider.clone(),
+                self.token.clone(),
+                path.map(String::from),
+            )
+            .await?;
+            Ok(entries
+                .into_iter()
+                .map(|e| RemoteEntry {
+                    path: e.id.unwrap_or_else(|| e.name.clone()),
+                    name: e.name,
+                    is_dir: e.is_dir,
+                    size: e.size,
+                    modified: e.last_modified,
+                })
+                .collect())
+        })
+    }
+
+    fn download<'a>(
+        &'a self,
+        path: &'a str,
+        local_path: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(download_cloud_file(
+            self.window.clone(),
+            self.provider.clone(),
+            self.token.clone(),
+            path.to_string(),
+            local_path.to_string(),
+            None,
+            None,
+        ))
+    }
+
+    fn upload<'a>(
+        &'a self,
+        local_path: &'a str,
+        parent: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let result = upload_cloud_file(
+                self.window.clone(),
+                self.provider.clone(),
+                self.token.clone(),
+                local_path.to_string(),
+                parent.map(String::from),
+                None,
+            )
+            .await;
+            self.invalidate();
+            result
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let result = delete_cloud_file_inner(
+                self.provider.clone(),
+                self.token.clone(),
+                path.to_string(),
+            )
+            .await;
+            self.invalidate();
+            result
+        })
+    }
+
+    fn mkdir<'a>(
+        &'a self,
+        parent: Option<&'a str>,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let created = create_cloud_folder_inner(
+                self.provider.clone(),
+                self.token.clone(),
+                parent.map(String::from),
+                name.to_string(),
+            )
+            .await;
+            self.invalidate();
+            Ok(created?.id.unwrap_or_else(|| name.to_string()))
+        })
+    }
+
+    fn rename<'a>(
+        &'a self,
+        path: &'a str,
+        new_name: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let result = rename_cloud_file_inner(
+                self.provider.clone(),
+                self.token.clone(),
+                path.to_string(),
+                new_name.to_string(),
+            )
+            .await;
+            self.invalidate();
+            result
+        })
+    }
+}
@@ -1,7 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::oauth::OAuthTokens;
+use crate::secret::SecretString;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FtpConnection {
@@ -10,9 +18,37 @@ pub struct FtpConnection {
     pub host: String,
     pub port: u16,
     pub username: String,
-    pub password: Option<String>,
+    pub password: Option<SecretString>,
     #[serde(default)]
     pub secure: bool,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+    #[serde(default)]
+    pub proxy: Option<crate::ftp_client::ProxyConfig>,
+    #[serde(default)]
+    pub remote_encoding: Option<String>,
+    #[serde(default)]
+    pub fix_passive_nat: bool,
+    #[serde(default)]
+    pub keep_alive: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// How a cloud connection authenticates.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum AuthMode {
+    /// Tokens from `start_oauth_flow`, refreshed when they expire.
+    #[default]
+    #[serde(rename = "oauth")]
+    OAuth,
+    /// A static key for self-hosted endpoints, sent as the access token and never refreshed.
+    #[serde(rename = "api_key")]
+    ApiKey,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -20,29 +56,379 @@ pub struct CloudConnection {
     pub id: String,
     pub provider: String, // "google", "dropbox", "onedrive"
     pub account_name: String,
-    pub access_token: String,
-    pub refresh_token: Option<String>,
+    pub access_token: SecretString,
+    pub refresh_token: Option<SecretString>,
+    /// When `access_token` expires, in unix seconds; unknown for older connections.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Token from the last `get_cloud_changes` call, so the next sync only fetches changes.
+    #[serde(default)]
+    pub delta_token: Option<String>,
+    /// Dropbox team root namespace, see `get_dropbox_root_namespace`.
+    #[serde(default)]
+    pub root_namespace_id: Option<String>,
+    #[serde(default)]
+    pub auth_mode: AuthMode,
+    /// Replaces the provider's API hosts, e.g. `https://files.example.com`, for
+    /// self-hosted or compatible servers.
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl CloudConnection {
+    /// Points requests made with this connection's token at its `base_url`, and
+    /// has them refused once the token expires.
+    pub fn register(&self) {
+        crate::cloud_client::set_base_url(self.access_token.expose(), self.base_url.clone());
+        crate::cloud_client::register_connection(self);
+    }
+}
+
+/// Tokens are treated as expired this long before they actually are, so a
+/// request started just before the deadline doesn't fail halfway.
+const TOKEN_EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// Whether the connection's access token has expired (or is about to). Connections
+/// with no recorded expiry, and API keys, are assumed valid until a request says otherwise.
+pub fn is_token_expired(connection: &CloudConnection) -> bool {
+    connection.auth_mode == AuthMode::OAuth
+        && connection.expires_at.is_some_and(|expires_at| {
+            chrono::Utc::now().timestamp() + TOKEN_EXPIRY_MARGIN_SECS >= expires_at
+        })
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppConfig {
     pub ftp_connections: Vec<FtpConnection>,
     pub cloud_connections: Vec<CloudConnection>,
     #[serde(default)]
     pub theme: Option<String>,
+    /// Menu accelerator overrides keyed by menu item id (e.g. `"refresh" -> "CmdOrCtrl+R"`).
+    #[serde(default)]
+    pub shortcuts: HashMap<String, String>,
+    /// How long directory listings are served from cache; 0 disables caching.
+    #[serde(default = "default_listing_cache_ttl")]
+    pub listing_cache_ttl_secs: u64,
+    /// Log filter directive, e.g. `"info"` or `"debug"`.
+    #[serde(default)]
+    pub log_level: Option<String>,
+    /// Buffer size from before `transfer_policy`, moved there on load.
+    #[serde(default, skip_serializing)]
+    pub buffer_size: Option<usize>,
+    #[serde(default)]
+    pub transfer_policy: TransferPolicy,
+    /// Show a desktop notification when a transfer finishes.
+    #[serde(default = "default_true")]
+    pub notifications_enabled: bool,
+    /// Recursive transfers larger than this ask for confirmation first; 0 disables the check.
+    #[serde(default = "default_confirm_threshold")]
+    pub confirm_threshold_bytes: u64,
+    /// Where one-click downloads go; the home directory when unset.
+    #[serde(default)]
+    pub default_download_dir: Option<String>,
+    /// Extensions (without the dot, case-insensitive) that `TransferType::Auto`
+    /// sends in ASCII mode; everything else goes as binary. See `DEFAULT_TEXT_EXTENSIONS`.
+    #[serde(default = "default_text_extensions")]
+    pub text_extensions: Vec<String>,
+    /// Recently visited directories, newest first, keyed by `"local"` or a connection id.
+    #[serde(default)]
+    pub recent_paths: HashMap<String, Vec<String>>,
+    /// Bookmarked directories, keyed like `recent_paths`.
+    #[serde(default)]
+    pub favorite_paths: HashMap<String, Vec<String>>,
+    /// Cloud API base URLs; connections with their own `base_url` ignore them.
+    #[serde(default)]
+    pub cloud_endpoints: crate::cloud_client::CloudEndpoints,
 }
 
-fn get_config_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let mut config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+// Written out so a missing config file gets the same defaults as missing fields
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            ftp_connections: Vec::new(),
+            cloud_connections: Vec::new(),
+            theme: None,
+            shortcuts: HashMap::new(),
+            listing_cache_ttl_secs: default_listing_cache_ttl(),
+            log_level: None,
+            buffer_size: None,
+            transfer_policy: TransferPolicy::default(),
+            notifications_enabled: true,
+            confirm_threshold_bytes: default_confirm_threshold(),
+            default_download_dir: None,
+            text_extensions: default_text_extensions(),
+            recent_paths: HashMap::new(),
+            favorite_paths: HashMap::new(),
+            cloud_endpoints: Default::default(),
+        }
+    }
+}
+
+pub const DEFAULT_BUFFER_SIZE: usize = 16 * 1024;
+pub const MIN_BUFFER_SIZE: usize = 4 * 1024;
+pub const MAX_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+fn default_buffer_size() -> usize {
+    DEFAULT_BUFFER_SIZE
+}
+
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+fn default_read_timeout() -> u64 {
+    30
+}
+
+fn default_retries() -> u32 {
+    1
+}
+
+fn default_backoff_base() -> u64 {
+    500
+}
+
+fn default_max_concurrency() -> usize {
+    4
+}
+
+/// Timeouts, retries and sizing shared by the transfer commands.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransferPolicy {
+    /// Limit on opening a connection, TLS upgrade and login included.
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+    /// Limit on waiting for a directory listing.
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout_secs: u64,
+    /// Reconnect attempts after a command hits a dropped FTP session.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after it.
+    #[serde(default = "default_backoff_base")]
+    pub backoff_base_ms: u64,
+    /// Read/write buffer used by the transfer loops, in bytes.
+    ///
+    /// Larger buffers mean fewer syscalls and progress events per megabyte and
+    /// help on high bandwidth-delay links; smaller ones give smoother progress
+    /// and lower memory use per concurrent transfer. Clamped to 4 KiB..=4 MiB.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    /// Requests in flight at once where an operation fans out, e.g. `prefetch_cloud_tree`.
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl Default for TransferPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout(),
+            read_timeout_secs: default_read_timeout(),
+            retries: default_retries(),
+            backoff_base_ms: default_backoff_base(),
+            buffer_size: default_buffer_size(),
+            max_concurrency: default_max_concurrency(),
+        }
+    }
+}
+
+impl TransferPolicy {
+    /// The policy with every value pulled into its supported range.
+    pub fn clamped(self) -> Self {
+        Self {
+            connect_timeout_secs: self.connect_timeout_secs.clamp(1, 300),
+            read_timeout_secs: self.read_timeout_secs.clamp(1, 600),
+            retries: self.retries.min(10),
+            backoff_base_ms: self.backoff_base_ms.min(60_000),
+            buffer_size: self.buffer_size.clamp(MIN_BUFFER_SIZE, MAX_BUFFER_SIZE),
+            max_concurrency: self.max_concurrency.clamp(1, 16),
+        }
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs)
+    }
+
+    /// Wait before retry number `attempt`, counting from 0.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_millis(self.backoff_base_ms.saturating_mul(1 << attempt.min(10)))
+    }
+}
+
+pub const DEFAULT_CONFIRM_THRESHOLD: u64 = 1024 * 1024 * 1024;
+
+fn default_confirm_threshold() -> u64 {
+    DEFAULT_CONFIRM_THRESHOLD
+}
+
+/// Plain-text formats whose line endings are safe to convert between platforms.
+pub const DEFAULT_TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "csv", "tsv", "html", "htm", "css", "js", "json", "xml", "md", "ini", "cfg", "conf",
+    "log", "sh", "php", "py", "sql", "yml", "yaml",
+];
+
+fn default_text_extensions() -> Vec<String> {
+    DEFAULT_TEXT_EXTENSIONS
+        .iter()
+        .map(|ext| ext.to_string())
+        .collect()
+}
+
+/// Transfer settings that commands read at runtime, kept in sync with the saved config.
+pub struct TransferSettings {
+    policy: RwLock<TransferPolicy>,
+    notifications_enabled: AtomicBool,
+    confirm_threshold_bytes: AtomicU64,
+    text_extensions: RwLock<Vec<String>>,
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self {
+            policy: RwLock::new(TransferPolicy::default()),
+            notifications_enabled: AtomicBool::new(true),
+            confirm_threshold_bytes: AtomicU64::new(DEFAULT_CONFIRM_THRESHOLD),
+            text_extensions: RwLock::new(default_text_extensions()),
+        }
+    }
+}
+
+impl TransferSettings {
+    pub fn apply(&self, config: &AppConfig) {
+        if let Ok(mut policy) = self.policy.write() {
+            *policy = config.transfer_policy.clone().clamped();
+        }
+        self.notifications_enabled
+            .store(config.notifications_enabled, Ordering::Relaxed);
+        self.confirm_threshold_bytes
+            .store(config.confirm_threshold_bytes, Ordering::Relaxed);
+        if let Ok(mut extensions) = self.text_extensions.write() {
+            *extensions = config
+                .text_extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect();
+        }
+    }
+}
+
+/// The configured transfer policy, looked up through any handle/window.
+pub fn transfer_policy<R: Runtime>(manager: &impl Manager<R>) -> TransferPolicy {
+    manager
+        .try_state::<TransferSettings>()
+        .and_then(|s| s.policy.read().ok().map(|p| p.clone()))
+        .unwrap_or_default()
+}
+
+/// The configured transfer buffer size, looked up through any handle/window.
+pub fn buffer_size<R: Runtime>(manager: &impl Manager<R>) -> usize {
+    transfer_policy(manager).buffer_size
+}
+
+pub fn notifications_enabled<R: Runtime>(manager: &impl Manager<R>) -> bool {
+    manager
+        .try_state::<TransferSettings>()
+        .map(|s| s.notifications_enabled.load(Ordering::Relaxed))
+        .unwrap_or(true)
+}
+
+pub fn confirm_threshold_bytes<R: Runtime>(manager: &impl Manager<R>) -> u64 {
+    manager
+        .try_state::<TransferSettings>()
+        .map(|s| s.confirm_threshold_bytes.load(Ordering::Relaxed))
+        .unwrap_or(DEFAULT_CONFIRM_THRESHOLD)
+}
+
+/// Whether `file_name` has one of the configured text extensions.
+pub fn is_text_file<R: Runtime>(manager: &impl Manager<R>, file_name: &str) -> bool {
+    let Some(ext) = std::path::Path::new(file_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+    else {
+        return false;
+    };
+    match manager.try_state::<TransferSettings>() {
+        Some(settings) => settings
+            .text_extensions
+            .read()
+            .map(|extensions| extensions.contains(&ext))
+            .unwrap_or(false),
+        None => DEFAULT_TEXT_EXTENSIONS.contains(&ext.as_str()),
+    }
+}
+
+fn default_listing_cache_ttl() -> u64 {
+    30
+}
+
+/// Default keyboard accelerators for menu actions, keyed by menu item id.
+pub const DEFAULT_SHORTCUTS: &[(&str, &str)] = &[
+    ("refresh", "CmdOrCtrl+R"),
+    ("disconnect", "CmdOrCtrl+D"),
+    ("toggle_theme", "CmdOrCtrl+Shift+T"),
+];
+
+impl AppConfig {
+    fn has_connections(&self) -> bool {
+        !self.ftp_connections.is_empty() || !self.cloud_connections.is_empty()
+    }
+
+    /// Returns the accelerator for a menu item, preferring the user's remapping.
+    /// An empty string in `shortcuts` unbinds the action.
+    pub fn shortcut(&self, id: &str) -> Option<String> {
+        match self.shortcuts.get(id) {
+            Some(accel) if accel.is_empty() => None,
+            Some(accel) => Some(accel.clone()),
+            None => DEFAULT_SHORTCUTS
+                .iter()
+                .find(|(action, _)| *action == id)
+                .map(|(_, accel)| accel.to_string()),
+        }
+    }
+
+    fn quick_paths_mut(&mut self, kind: QuickPathKind, scope: &str) -> &mut Vec<String> {
+        let lists = match kind {
+            QuickPathKind::Recent => &mut self.recent_paths,
+            QuickPathKind::Favorite => &mut self.favorite_paths,
+        };
+        lists.entry(scope.to_string()).or_default()
+    }
+}
+
+/// The app config directory, created if it doesn't exist yet.
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
 
     if !config_dir.exists() {
         fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
     }
 
-    config_dir.push("connections.json");
     Ok(config_dir)
 }
 
+fn get_config_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(config_dir(app)?.join("connections.json"))
+}
+
+/// Where `connections.json` lives, for display.
+#[tauri::command]
+pub fn get_config_dir(app: AppHandle) -> Result<String, String> {
+    config_dir(&app).map(|dir| dir.to_string_lossy().to_string())
+}
+
+/// Opens the config directory in the system file manager, e.g. to back up the config.
+#[tauri::command]
+pub fn open_config_dir(app: AppHandle) -> Result<(), String> {
+    let dir = config_dir(&app)?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open config directory: {}", e))
+}
+
 #[tauri::command]
 pub fn load_config(app: AppHandle) -> Result<AppConfig, String> {
     let config_path = get_config_path(&app)?;
@@ -52,17 +438,292 @@ pub fn load_config(app: AppHandle) -> Result<AppConfig, String> {
     }
 
     let content = fs::read_to_string(config_path).map_err(|e| e.to_string())?;
-    let config: AppConfig = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let mut config: AppConfig = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    if let Some(size) = config.buffer_size.take() {
+        config.transfer_policy.buffer_size = size;
+    }
 
     Ok(config)
 }
 
-#[tauri::command]
-pub fn save_config(app: AppHandle, config: AppConfig) -> Result<(), String> {
-    let config_path = get_config_path(&app)?;
+/// Serializes read-modify-write updates of the config file.
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+/// Writes the config through a temp file and rename, so a crash mid-write
+/// can't leave a truncated file behind.
+fn write_config(app: &AppHandle, config: &AppConfig) -> Result<(), String> {
+    let config_path = get_config_path(app)?;
+    let tmp_path = config_path.with_extension("json.tmp");
 
-    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
-    fs::write(config_path, json).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(&tmp_path, json).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, &config_path).map_err(|e| e.to_string())?;
+
+    if let Some(settings) = app.try_state::<TransferSettings>() {
+        settings.apply(config);
+    }
+    if let Some(ftp) = app.try_state::<crate::ftp_client::FtpState>() {
+        ftp.set_policy(config.transfer_policy.clone());
+    }
+    crate::cloud_client::set_endpoints(config.cloud_endpoints.clone());
+    for conn in &config.cloud_connections {
+        conn.register();
+    }
 
     Ok(())
 }
+
+/// Writes a config changed behind the frontend's back, by a command other than
+/// `save_config`, and emits `config-changed` so the frontend reloads it rather
+/// than saving its older copy over the change.
+fn update_config(app: &AppHandle, config: &AppConfig) -> Result<(), String> {
+    write_config(app, config)?;
+    let _ = app.emit("config-changed", ());
+    Ok(())
+}
+
+/// Saves the config. Replacing saved connections with none at all is refused
+/// unless `allow_clear` is set, so a frontend sending an empty config by
+/// mistake can't wipe them.
+#[tauri::command]
+pub fn save_config(
+    app: AppHandle,
+    config: AppConfig,
+    allow_clear: Option<bool>,
+) -> Result<(), String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    if !allow_clear.unwrap_or(false) && !config.has_connections() {
+        let existing = load_config(app.clone())?;
+        if existing.has_connections() {
+            return Err(format!(
+                "Refusing to replace {} saved connection(s) with an empty config; pass allow_clear to confirm",
+                existing.ftp_connections.len() + existing.cloud_connections.len()
+            ));
+        }
+    }
+    write_config(&app, &config)
+}
+
+/// Stores freshly issued tokens on a saved cloud connection, e.g. after a refresh.
+/// A response without a refresh token keeps the existing one.
+#[tauri::command]
+pub fn update_cloud_token(
+    app: AppHandle,
+    connection_id: String,
+    tokens: OAuthTokens,
+) -> Result<(), String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    let conn = config
+        .cloud_connections
+        .iter_mut()
+        .find(|c| c.id == connection_id)
+        .ok_or_else(|| format!("No saved cloud connection with id {}", connection_id))?;
+    if conn.auth_mode == AuthMode::ApiKey {
+        return Err(format!(
+            "{} uses an API key, which has no tokens to refresh",
+            conn.account_name
+        ));
+    }
+
+    conn.access_token = tokens.access_token;
+    conn.expires_at = tokens.expires_at();
+    if tokens.refresh_token.is_some() {
+        conn.refresh_token = tokens.refresh_token;
+    }
+    if conn.root_namespace_id.is_some() {
+        crate::cloud_client::set_dropbox_path_root(
+            conn.access_token.expose(),
+            conn.root_namespace_id.clone(),
+        );
+    }
+
+    update_config(&app, &config)
+}
+
+/// Changes the stored password of one saved FTP connection, leaving the rest
+/// of the config untouched. An empty password clears it.
+#[tauri::command]
+pub fn update_ftp_password(
+    app: AppHandle,
+    connection_id: String,
+    new_password: String,
+) -> Result<(), String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    let conn = config
+        .ftp_connections
+        .iter_mut()
+        .find(|c| c.id == connection_id)
+        .ok_or_else(|| format!("No saved FTP connection with id {}", connection_id))?;
+
+    conn.password = (!new_password.is_empty()).then(|| new_password.into());
+
+    update_config(&app, &config)
+}
+
+/// A saved connection of either kind, tagged for the frontend.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SavedConnection {
+    Ftp(FtpConnection),
+    Cloud(CloudConnection),
+}
+
+/// Saves a copy of a connection under a new id, named "<name> (copy)" (cloud
+/// connections get the suffix on the account name), and returns the copy.
+#[tauri::command]
+pub fn duplicate_connection(
+    app: AppHandle,
+    connection_id: String,
+) -> Result<SavedConnection, String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let copy = if let Some(conn) = config
+        .ftp_connections
+        .iter()
+        .find(|c| c.id == connection_id)
+    {
+        let mut copy = conn.clone();
+        copy.id = id;
+        copy.name = format!("{} (copy)", copy.name);
+        config.ftp_connections.push(copy.clone());
+        SavedConnection::Ftp(copy)
+    } else if let Some(conn) = config
+        .cloud_connections
+        .iter()
+        .find(|c| c.id == connection_id)
+    {
+        let mut copy = conn.clone();
+        copy.id = id;
+        copy.account_name = format!("{} (copy)", copy.account_name);
+        config.cloud_connections.push(copy.clone());
+        SavedConnection::Cloud(copy)
+    } else {
+        return Err(format!("No saved connection with id {}", connection_id));
+    };
+
+    update_config(&app, &config)?;
+    Ok(copy)
+}
+
+#[tauri::command]
+pub fn get_transfer_policy(app: AppHandle) -> TransferPolicy {
+    transfer_policy(&app)
+}
+
+/// Saves `policy`, with values outside the supported ranges clamped, and returns what was stored.
+#[tauri::command]
+pub fn set_transfer_policy(
+    app: AppHandle,
+    policy: TransferPolicy,
+) -> Result<TransferPolicy, String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    config.transfer_policy = policy.clamped();
+    update_config(&app, &config)?;
+    Ok(config.transfer_policy)
+}
+
+/// Recent paths kept per scope; visiting another drops the oldest.
+pub const MAX_RECENT_PATHS: usize = 20;
+/// Scope of the local file system in `recent_paths` and `favorite_paths`.
+pub const LOCAL_SCOPE: &str = "local";
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuickPathKind {
+    Recent,
+    Favorite,
+}
+
+/// Directories for a quick-jump menu.
+#[derive(Serialize, Default)]
+pub struct QuickPaths {
+    pub recent: Vec<String>,
+    pub favorites: Vec<String>,
+}
+
+/// Drops a trailing separator so `/a/b/` and `/a/b` count as the same entry.
+fn normalize_quick_path(path: &str) -> String {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        path.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Adds `path` to the recent or favorite list of `scope` (`"local"` or a
+/// connection id). A recent path moves to the front and the list is capped at
+/// `MAX_RECENT_PATHS`; favorites keep the order they were added in.
+#[tauri::command]
+pub fn add_quick_path(
+    app: AppHandle,
+    scope: String,
+    kind: QuickPathKind,
+    path: String,
+) -> Result<(), String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    let path = normalize_quick_path(&path);
+    let list = config.quick_paths_mut(kind, &scope);
+
+    match kind {
+        QuickPathKind::Recent => {
+            list.retain(|p| *p != path);
+            list.insert(0, path);
+            list.truncate(MAX_RECENT_PATHS);
+        }
+        QuickPathKind::Favorite if list.contains(&path) => return Ok(()),
+        QuickPathKind::Favorite => list.push(path),
+    }
+
+    update_config(&app, &config)
+}
+
+#[tauri::command]
+pub fn remove_quick_path(
+    app: AppHandle,
+    scope: String,
+    kind: QuickPathKind,
+    path: String,
+) -> Result<(), String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    let path = normalize_quick_path(&path);
+    config.quick_paths_mut(kind, &scope).retain(|p| *p != path);
+    update_config(&app, &config)
+}
+
+/// The recent and favorite paths of `scope`. Local directories that no longer
+/// exist are pruned from both lists; remote ones are kept, as checking them
+/// would need a round trip per entry.
+#[tauri::command]
+pub fn list_quick_paths(app: AppHandle, scope: String) -> Result<QuickPaths, String> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut config = load_config(app.clone())?;
+    let mut paths = QuickPaths {
+        recent: config.recent_paths.get(&scope).cloned().unwrap_or_default(),
+        favorites: config
+            .favorite_paths
+            .get(&scope)
+            .cloned()
+            .unwrap_or_default(),
+    };
+
+    if scope == LOCAL_SCOPE {
+        let before = paths.recent.len() + paths.favorites.len();
+        paths.recent.retain(|p| Path::new(p).is_dir());
+        paths.favorites.retain(|p| Path::new(p).is_dir());
+        if paths.recent.len() + paths.favorites.len() != before {
+            *config.quick_paths_mut(QuickPathKind::Recent, &scope) = paths.recent.clone();
+            *config.quick_paths_mut(QuickPathKind::Favorite, &scope) = paths.favorites.clone();
+            update_config(&app, &config)?;
+        }
+    }
+
+    Ok(paths)
+}
@@ -1,22 +1,166 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State, Window};
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+use tauri_plugin_opener::OpenerExt;
 
-#[derive(Serialize)]
+use crate::sort::{SortSpec, Sortable};
+
+#[derive(Serialize, Clone)]
 pub struct FileEntry {
     pub name: String,
     pub path: String,
     pub is_dir: bool,
     pub size: u64,
+    /// Last modification time in ISO 8601, when the filesystem reports one.
+    pub modified: Option<String>,
+}
+
+impl Sortable for FileEntry {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+    fn size(&self) -> Option<u64> {
+        (!self.is_dir).then_some(self.size)
+    }
+    fn modified(&self) -> Option<&str> {
+        self.modified.as_deref()
+    }
+}
+
+fn default_local_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("C:\\"))
+}
+
+/// The local working directory, mirroring the remote `cwd`/`pwd` model so
+/// commands can take paths relative to it.
+pub struct LocalNav {
+    cwd: Mutex<PathBuf>,
+}
+
+impl Default for LocalNav {
+    fn default() -> Self {
+        Self {
+            cwd: Mutex::new(default_local_dir()),
+        }
+    }
+}
+
+impl LocalNav {
+    pub fn cwd(&self) -> PathBuf {
+        self.cwd
+            .lock()
+            .map(|cwd| cwd.clone())
+            .unwrap_or_else(|_| default_local_dir())
+    }
+
+    /// Resolves `path` against the working directory; empty means the directory itself.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        if path.is_empty() {
+            return self.cwd();
+        }
+        let path = Path::new(path);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.cwd().join(path)
+        }
+    }
+
+    fn set(&self, dir: PathBuf) -> Result<String, String> {
+        if !dir.is_dir() {
+            return Err(format!("Not a directory: {}", dir.display()));
+        }
+        // Fold "." and ".." lexically; canonicalize() would add a \\?\ prefix on Windows
+        let dir = dir.components().fold(PathBuf::new(), |mut acc, c| {
+            match c {
+                Component::ParentDir => {
+                    acc.pop();
+                }
+                Component::CurDir => {}
+                other => acc.push(other),
+            }
+            acc
+        });
+        let display = dir.to_string_lossy().to_string();
+        if let Ok(mut cwd) = self.cwd.lock() {
+            *cwd = dir;
+        }
+        Ok(display)
+    }
+}
+
+/// Joins a file name that came from a server onto `dir`. Anything but a single
+/// plain component is refused, so a hostile listing entry such as
+/// `../../etc/passwd`, an absolute path or a drive prefix can't land outside `dir`.
+pub fn join_untrusted_name(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(dir.join(name)),
+        _ => Err(format!("Unsafe file name from server: {:?}", name)),
+    }
 }
 
 #[tauri::command]
-pub fn list_directory(path: String) -> Result<Vec<FileEntry>, String> {
-    let dir_path = if path.is_empty() {
-        dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("C:\\"))
-    } else {
-        std::path::PathBuf::from(&path)
-    };
+pub fn get_local_cwd(nav: State<'_, LocalNav>) -> String {
+    nav.cwd().to_string_lossy().to_string()
+}
+
+/// Changes the local working directory; `path` may be relative to the current one.
+#[tauri::command]
+pub fn set_local_cwd(nav: State<'_, LocalNav>, path: String) -> Result<String, String> {
+    nav.set(nav.resolve(&path))
+}
+
+/// Moves the local working directory to its parent and returns it.
+#[tauri::command]
+pub fn go_up_local_cwd(nav: State<'_, LocalNav>) -> Result<String, String> {
+    let cwd = nav.cwd();
+    let parent = cwd
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory", cwd.display()))?;
+    nav.set(parent.to_path_buf())
+}
+
+/// Runs filesystem work on the blocking pool, so a slow disk or network mount
+/// stalls only the command waiting on it instead of the async runtime.
+async fn run_blocking<T, F>(what: &str, work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("{} failed: {}", what, e))?
+}
+
+#[tauri::command]
+pub async fn list_directory(
+    nav: State<'_, LocalNav>,
+    path: String,
+    sort: Option<SortSpec>,
+    extensions: Option<Vec<String>>,
+    dirs_only: Option<bool>,
+) -> Result<Vec<FileEntry>, String> {
+    let dir_path = nav.resolve(&path);
+    run_blocking("Directory listing", move || {
+        read_directory(dir_path, sort, extensions, dirs_only)
+    })
+    .await
+}
 
+pub(crate) fn read_directory(
+    dir_path: PathBuf,
+    sort: Option<SortSpec>,
+    extensions: Option<Vec<String>>,
+    dirs_only: Option<bool>,
+) -> Result<Vec<FileEntry>, String> {
     if !dir_path.exists() {
         return Err(format!("Path does not exist: {}", dir_path.display()));
     }
@@ -29,31 +173,235 @@ pub fn list_directory(path: String) -> Result<Vec<FileEntry>, String> {
     match fs::read_dir(&dir_path) {
         Ok(read_dir) => {
             for entry in read_dir.flatten() {
-                let metadata = entry.metadata();
-                let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
-                let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-
-                entries.push(FileEntry {
-                    name: entry.file_name().to_string_lossy().to_string(),
-                    path: entry.path().to_string_lossy().to_string(),
-                    is_dir,
-                    size,
-                });
+                entries.push(file_entry(&entry));
             }
         }
         Err(e) => return Err(format!("Failed to read directory: {}", e)),
     }
 
-    // Sort: directories first, then files, both alphabetically
-    entries.sort_by(|a, b| {
-        b.is_dir
-            .cmp(&a.is_dir)
-            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-    });
+    if let Some(extensions) = extensions {
+        crate::sort::filter_extensions(&mut entries, &extensions);
+    }
+    // For folder pickers
+    if dirs_only.unwrap_or(false) {
+        entries.retain(|e| e.is_dir);
+    }
+    crate::sort::sort_entries(&mut entries, sort.unwrap_or_default());
 
     Ok(entries)
 }
 
+fn file_entry(entry: &fs::DirEntry) -> FileEntry {
+    let metadata = entry.metadata();
+    let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+    let modified = metadata.ok().and_then(|m| m.modified().ok()).map(|t| {
+        chrono::DateTime::<chrono::Local>::from(t)
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    });
+
+    FileEntry {
+        name: entry.file_name().to_string_lossy().to_string(),
+        path: entry.path().to_string_lossy().to_string(),
+        is_dir,
+        size,
+        modified,
+    }
+}
+
+const STREAM_BATCH_SIZE: usize = 500;
+
+#[derive(Serialize, Clone)]
+pub struct DirectoryBatch {
+    pub listing_id: String,
+    pub entries: Vec<FileEntry>,
+    pub done: bool,
+}
+
+/// Lists `path` incrementally, emitting `directory-batch` events of up to
+/// `STREAM_BATCH_SIZE` entries so huge folders show their first results right
+/// away. The last event has `done: true`. Entries are sorted within each batch
+/// only when `sort` is set. Returns the total number of entries.
+#[tauri::command]
+pub async fn list_directory_stream(
+    window: Window,
+    nav: State<'_, LocalNav>,
+    listing_id: String,
+    path: String,
+    sort: Option<bool>,
+) -> Result<usize, String> {
+    let dir_path = nav.resolve(&path);
+    if !dir_path.is_dir() {
+        return Err(format!("Not a directory: {}", dir_path.display()));
+    }
+    let sort = sort.unwrap_or(false);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let read_dir =
+            fs::read_dir(&dir_path).map_err(|e| format!("Failed to read directory: {}", e))?;
+        let emit = |mut entries: Vec<FileEntry>, done: bool| {
+            if sort {
+                crate::sort::sort_entries(&mut entries, SortSpec::default());
+            }
+            let _ = window.emit(
+                "directory-batch",
+                DirectoryBatch {
+                    listing_id: listing_id.clone(),
+                    entries,
+                    done,
+                },
+            );
+        };
+
+        let mut total = 0;
+        let mut batch = Vec::with_capacity(STREAM_BATCH_SIZE);
+        for entry in read_dir.flatten() {
+            batch.push(file_entry(&entry));
+            total += 1;
+            if batch.len() == STREAM_BATCH_SIZE {
+                emit(std::mem::take(&mut batch), false);
+            }
+        }
+        emit(batch, true);
+        Ok(total)
+    })
+    .await
+    .map_err(|e| format!("Directory listing failed: {}", e))?
+}
+
+#[derive(Serialize, Clone)]
+pub struct DuplicateProgress {
+    /// "scanning" while walking the tree, then "hashing".
+    pub phase: &'static str,
+    pub files_done: usize,
+    /// Files to hash; 0 while scanning since the count isn't known yet.
+    pub files_total: usize,
+}
+
+/// Files under the scanned root with identical contents.
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    /// SHA-256 of the contents, hex encoded.
+    pub hash: String,
+    pub paths: Vec<String>,
+}
+
+/// Emit a progress event every this many files.
+const DUPLICATE_PROGRESS_EVERY: usize = 100;
+
+/// Groups the regular files under `dir` by size. Symlinks are not followed so
+/// a file is never counted twice, and empty files are left out since they
+/// trivially match each other.
+fn files_by_size(
+    dir: &Path,
+    groups: &mut BTreeMap<u64, Vec<PathBuf>>,
+    scanned: &mut usize,
+    on_file: &dyn Fn(usize),
+) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read_dir.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            files_by_size(&entry.path(), groups, scanned, on_file);
+        } else if file_type.is_file() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if size > 0 {
+                groups.entry(size).or_default().push(entry.path());
+            }
+            *scanned += 1;
+            if *scanned % DUPLICATE_PROGRESS_EVERY == 0 {
+                on_file(*scanned);
+            }
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Finds files with identical contents under `root`. Files are grouped by size
+/// first and only same-size files are hashed. Emits `duplicate-progress` while
+/// scanning and hashing; returns the groups largest first.
+#[tauri::command]
+pub async fn find_duplicates(
+    window: Window,
+    nav: State<'_, LocalNav>,
+    root: String,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let root = nav.resolve(&root);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let emit = |phase: &'static str, files_done: usize, files_total: usize| {
+            let _ = window.emit(
+                "duplicate-progress",
+                DuplicateProgress {
+                    phase,
+                    files_done,
+                    files_total,
+                },
+            );
+        };
+
+        let mut by_size = BTreeMap::new();
+        let mut scanned = 0;
+        files_by_size(&root, &mut by_size, &mut scanned, &|n| {
+            emit("scanning", n, 0)
+        });
+        emit("scanning", scanned, 0);
+
+        let candidates: Vec<(u64, Vec<PathBuf>)> = by_size
+            .into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .collect();
+        let to_hash = candidates.iter().map(|(_, paths)| paths.len()).sum();
+        let mut hashed = 0;
+        let mut groups = Vec::new();
+        for (size, paths) in candidates.into_iter().rev() {
+            let mut by_hash: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for path in paths {
+                // Unreadable files can't be compared, so they are left out
+                match hash_file(&path) {
+                    Ok(hash) => by_hash
+                        .entry(hash)
+                        .or_default()
+                        .push(path.to_string_lossy().to_string()),
+                    Err(e) => {
+                        tracing::warn!(path = %path.display(), error = %e, "could not hash file")
+                    }
+                }
+                hashed += 1;
+                if hashed % DUPLICATE_PROGRESS_EVERY == 0 {
+                    emit("hashing", hashed, to_hash);
+                }
+            }
+            groups.extend(
+                by_hash
+                    .into_iter()
+                    .filter(|(_, paths)| paths.len() > 1)
+                    .map(|(hash, paths)| DuplicateGroup { size, hash, paths }),
+            );
+        }
+        emit("hashing", hashed, to_hash);
+        Ok(groups)
+    })
+    .await
+    .map_err(|e| format!("Duplicate scan failed: {}", e))?
+}
+
 #[tauri::command]
 pub fn get_home_dir() -> Result<String, String> {
     dirs::home_dir()
@@ -61,6 +409,88 @@ pub fn get_home_dir() -> Result<String, String> {
         .ok_or_else(|| "Could not determine home directory".to_string())
 }
 
+/// Well-known user folders for quick-access shortcuts, keyed by name (e.g.
+/// "Documents"). Folders the platform doesn't define or that don't exist are left out.
+#[tauri::command]
+pub fn get_special_dirs() -> BTreeMap<String, String> {
+    [
+        ("Home", dirs::home_dir()),
+        ("Desktop", dirs::desktop_dir()),
+        ("Documents", dirs::document_dir()),
+        ("Downloads", dirs::download_dir()),
+        ("Pictures", dirs::picture_dir()),
+        ("Music", dirs::audio_dir()),
+        ("Videos", dirs::video_dir()),
+        ("Public", dirs::public_dir()),
+    ]
+    .into_iter()
+    .filter_map(|(name, dir)| {
+        dir.filter(|d| d.is_dir())
+            .map(|d| (name.to_string(), d.to_string_lossy().to_string()))
+    })
+    .collect()
+}
+
+/// Walks up from `path` to the closest ancestor that exists on disk.
+fn nearest_existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.exists())
+}
+
+#[tauri::command]
+pub async fn get_free_space(path: String) -> Result<u64, String> {
+    run_blocking("Free space query", move || free_space(&path)).await
+}
+
+fn free_space(path: &str) -> Result<u64, String> {
+    let target = PathBuf::from(path);
+    let existing = nearest_existing_ancestor(&target)
+        .ok_or_else(|| format!("No existing ancestor for {}", path))?;
+    fs2::available_space(existing).map_err(|e| format!("Failed to query free space: {}", e))
+}
+
+/// Whether files can be created in the directory `path`, tried by creating and
+/// removing a probe file. A directory that doesn't exist yet is judged by its
+/// nearest existing ancestor, where it would be created.
+#[tauri::command]
+pub async fn is_writable(path: String) -> Result<bool, String> {
+    run_blocking("Write check", move || Ok(writable(Path::new(&path)))).await
+}
+
+fn writable(path: &Path) -> bool {
+    let Some(dir) = nearest_existing_ancestor(path).filter(|dir| dir.is_dir()) else {
+        return false;
+    };
+    let probe = dir.join(format!(".quicksync-write-test-{}", uuid::Uuid::new_v4()));
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(file) => {
+            drop(file);
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Fails with an "insufficient disk space" error when `needed` bytes won't fit
+/// on the volume that `path` would be written to. Unknown sizes (0) always pass.
+pub fn ensure_free_space(path: &str, needed: u64) -> Result<(), String> {
+    if needed == 0 {
+        return Ok(());
+    }
+    let available = free_space(path)?;
+    if needed > available {
+        return Err(format!(
+            "Insufficient disk space: {} bytes needed, {} bytes available",
+            needed, available
+        ));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_file_icon(ext: String) -> Result<String, String> {
     use base64::{engine::general_purpose, Engine as _};
@@ -86,7 +516,11 @@ pub fn get_file_icon(ext: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub fn delete_local_file(path: String) -> Result<String, String> {
+pub async fn delete_local_file(path: String) -> Result<String, String> {
+    run_blocking("Delete", move || delete_local_path(path)).await
+}
+
+fn delete_local_path(path: String) -> Result<String, String> {
     let p = std::path::PathBuf::from(&path);
     if !p.exists() {
         return Err(format!("Path does not exist: {}", path));
@@ -102,7 +536,11 @@ pub fn delete_local_file(path: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub fn copy_to_local(source_path: String, dest_dir: String) -> Result<String, String> {
+pub async fn copy_to_local(source_path: String, dest_dir: String) -> Result<String, String> {
+    run_blocking("Copy", move || copy_local_file(source_path, dest_dir)).await
+}
+
+fn copy_local_file(source_path: String, dest_dir: String) -> Result<String, String> {
     let source = std::path::PathBuf::from(&source_path);
     let dest_dir_path = std::path::PathBuf::from(&dest_dir);
 
@@ -124,3 +562,94 @@ pub fn copy_to_local(source_path: String, dest_dir: String) -> Result<String, St
         Err(e) => Err(format!("Failed to copy file: {}", e)),
     }
 }
+
+/// Opens the folder containing `path` in the system file manager with the item
+/// selected. Linux file managers have no common "select" flag, so there the
+/// containing folder is simply opened.
+#[tauri::command]
+pub fn reveal_in_file_manager(app: AppHandle, path: String) -> Result<(), String> {
+    let target = PathBuf::from(&path);
+    if !target.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        let _ = app;
+        // Explorer wants the quotes around the path only, so bypass Rust's argument quoting
+        std::process::Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", target.display()))
+            .spawn()
+            .map_err(|e| format!("Failed to open Explorer: {}", e))?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let _ = app;
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(&target)
+            .spawn()
+            .map_err(|e| format!("Failed to open Finder: {}", e))?;
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let folder = if target.is_dir() {
+            target.as_path()
+        } else {
+            target.parent().unwrap_or(target.as_path())
+        };
+        app.opener()
+            .open_path(folder.to_string_lossy(), None::<&str>)
+            .map_err(|e| format!("Failed to open file manager: {}", e))?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardMode {
+    /// The clipboard holds the file itself, pasteable in a file manager.
+    File,
+    /// Only the path text could be copied.
+    Text,
+}
+
+/// Kept alive between copies: on Linux the clipboard contents go away with the
+/// `Clipboard` that set them unless a clipboard manager takes them over.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Puts `path` on the system clipboard as a file reference, so it can be pasted
+/// into Explorer/Finder, falling back to the path as text where the platform
+/// clipboard can't hold files. Returns which of the two was copied.
+#[tauri::command]
+pub fn copy_path_to_clipboard(path: String) -> Result<ClipboardMode, String> {
+    let target = PathBuf::from(&path);
+    if !target.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    let target = std::path::absolute(&target).map_err(|e| e.to_string())?;
+
+    let mut guard = CLIPBOARD.lock().map_err(|e| e.to_string())?;
+    if guard.is_none() {
+        *guard =
+            Some(arboard::Clipboard::new().map_err(|e| format!("Clipboard unavailable: {}", e))?);
+    }
+    let Some(clipboard) = guard.as_mut() else {
+        return Err("Clipboard unavailable".into());
+    };
+
+    match clipboard.set().file_list(std::slice::from_ref(&target)) {
+        Ok(()) => Ok(ClipboardMode::File),
+        Err(e) => {
+            tracing::debug!(error = %e, "clipboard can't hold files, copying the path text");
+            clipboard
+                .set_text(target.to_string_lossy())
+                .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+            Ok(ClipboardMode::Text)
+        }
+    }
+}
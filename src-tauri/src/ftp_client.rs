@@ -1,15 +1,33 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Utc};
+use globset::{Glob, GlobMatcher};
 use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
 use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
 use rustls::{DigitallySignedStruct, SignatureScheme};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use suppaftp::tokio::{AsyncFtpStream, AsyncRustlsConnector, AsyncRustlsFtpStream};
-use suppaftp::types::Mode;
-use tauri::{Emitter, State, Window};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use suppaftp::types::{FileType, FormatControl, Mode};
+use suppaftp::{FtpError, Status};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Window};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::sync::Mutex;
 use tokio::time::timeout;
+use tokio_socks::tcp::Socks5Stream;
+use zeroize::Zeroize;
+
+use crate::config::TransferPolicy;
+use crate::fs_commands::join_untrusted_name;
+use crate::listing_cache::ListingCache;
+use crate::remote::{Remote, RemoteEntry};
+use crate::secret::SecretString;
+use crate::sort::{SortSpec, Sortable};
+use crate::transfers::{
+    bytes_per_sec, BatchProgress, BenchmarkResult, FailedFile, ProgressThrottle, TransferRegistry,
+    BENCHMARK_DEFAULT_BYTES, BENCHMARK_MAX_BYTES,
+};
 
 #[derive(Debug)]
 struct DummyVerifier(Arc<dyn ServerCertVerifier>);
@@ -69,6 +87,74 @@ type SecureStream = AsyncRustlsFtpStream;
 pub struct FtpState {
     pub client: Mutex<Option<PlainStream>>,
     pub secure_client: Mutex<Option<SecureStream>>,
+    /// Settings of the last successful connect, kept so a dropped session can be re-established.
+    pub last_config: Mutex<Option<FtpConfigPayload>>,
+    pub listing_cache: ListingCache<Vec<RemoteFileEntry>>,
+    /// Bumped by every connect. An attempt that finds it has moved on by the time
+    /// its session is ready was superseded, and discards the session instead of storing it.
+    pub connect_generation: AtomicU64,
+    pub server_info: Mutex<Option<ServerInfo>>,
+    /// Set while a single-file download or upload has its data connection open.
+    pub data_transfer_active: AtomicBool,
+    /// Wakes the running transfer loop so it can send ABOR, see `abort_data_transfer`.
+    pub data_abort: tokio::sync::Notify,
+    /// Set by `abort_data_transfer` until the running transfer has seen it, so an
+    /// abort sent while the loop isn't waiting on `data_abort` isn't lost.
+    pub data_abort_requested: AtomicBool,
+    /// Whether the session's representation type was last set to ASCII rather than binary.
+    pub ascii_type: AtomicBool,
+    /// Timeouts and retries from the config, kept in sync by `set_policy`.
+    policy: std::sync::RwLock<TransferPolicy>,
+    /// How the current session's listings are read, set on connect.
+    dialect: std::sync::RwLock<Arc<ListingDialect>>,
+    /// The `keep_alive` loop, aborted on exit.
+    keep_alive_task: std::sync::Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+    /// Where `remote_cd` or a listing last left the session, so a reconnect can
+    /// return there instead of the login directory.
+    working_dir: std::sync::Mutex<Option<String>>,
+}
+
+impl FtpState {
+    pub fn set_policy(&self, policy: TransferPolicy) {
+        if let Ok(mut current) = self.policy.write() {
+            *current = policy.clamped();
+        }
+    }
+
+    fn policy(&self) -> TransferPolicy {
+        self.policy.read().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    fn dialect(&self) -> Arc<ListingDialect> {
+        self.dialect.read().map(|d| d.clone()).unwrap_or_default()
+    }
+
+    fn set_dialect(&self, dialect: ListingDialect) {
+        if let Ok(mut current) = self.dialect.write() {
+            *current = Arc::new(dialect);
+        }
+    }
+
+    fn working_dir(&self) -> Option<String> {
+        self.working_dir.lock().ok().and_then(|dir| dir.clone())
+    }
+
+    fn set_working_dir(&self, dir: Option<String>) {
+        if let Ok(mut current) = self.working_dir.lock() {
+            *current = dir;
+        }
+    }
+}
+
+/// What the server said about itself when the session was opened.
+#[derive(Serialize, Clone)]
+pub struct ServerInfo {
+    /// Greeting sent on connect, often carrying maintenance or quota notices.
+    pub welcome: Option<String>,
+    /// `SYST` reply, e.g. "UNIX Type: L8" or "Windows_NT".
+    pub system: Option<String>,
+    /// Extensions advertised in the `FEAT` reply, e.g. "MDTM", "REST STREAM", "UTF8".
+    pub features: Vec<String>,
 }
 
 impl Default for FtpState {
@@ -76,47 +162,398 @@ impl Default for FtpState {
         Self {
             client: Mutex::new(None),
             secure_client: Mutex::new(None),
+            last_config: Mutex::new(None),
+            listing_cache: ListingCache::default(),
+            connect_generation: AtomicU64::new(0),
+            server_info: Mutex::new(None),
+            data_transfer_active: AtomicBool::new(false),
+            data_abort: tokio::sync::Notify::new(),
+            data_abort_requested: AtomicBool::new(false),
+            ascii_type: AtomicBool::new(false),
+            policy: std::sync::RwLock::new(TransferPolicy::default()),
+            dialect: std::sync::RwLock::default(),
+            keep_alive_task: std::sync::Mutex::new(None),
+            working_dir: std::sync::Mutex::new(None),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FtpConfigPayload {
     pub host: String,
     pub port: u16,
     pub username: String,
-    pub password: Option<String>,
+    pub password: Option<SecretString>,
     pub secure: bool,
+    /// Sent with `ACCT` when the server asks for an account after login, as
+    /// z/OS and other mainframe servers do.
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Month names used by the server's LIST output, January first, for locales
+    /// not covered by the built-in table.
+    #[serde(default)]
+    pub month_names: Option<Vec<String>>,
+    /// Transparently reconnect and retry when a command hits a dropped session,
+    /// as often as `TransferPolicy::retries` allows.
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Character set of the server's file names, as an encoding label such as
+    /// "windows-1252" or "shift_jis". Defaults to UTF-8.
+    #[serde(default)]
+    pub remote_encoding: Option<String>,
+    /// Dial passive data connections at the control connection's host instead of
+    /// the address in the PASV reply, for servers behind NAT that advertise a private IP.
+    #[serde(default)]
+    pub fix_passive_nat: bool,
+    /// Ping the session while idle and reconnect it after the machine wakes from
+    /// sleep, see `keep_alive`.
+    #[serde(default)]
+    pub keep_alive: bool,
 }
 
-#[derive(Serialize, Clone)]
+impl FtpConfigPayload {
+    /// Masks this connection's passwords in `message`, see `secret::redact`.
+    fn redact(&self, message: &str) -> String {
+        let proxy_password = self.proxy.as_ref().and_then(|p| p.password.as_ref());
+        let secrets: Vec<&SecretString> = self.password.iter().chain(proxy_password).collect();
+        crate::secret::redact(message, &secrets)
+    }
+}
+
+/// SOCKS5 proxy used for the FTP control connection.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProxyConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+}
+
+/// Opens a TCP connection to `host:port` tunnelled through a SOCKS5 proxy.
+/// Only the control connection is proxied; passive data connections still dial directly.
+async fn dial_via_proxy(
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+    connect_timeout: Duration,
+) -> Result<tokio::net::TcpStream, String> {
+    let proxy_addr = format!("{}:{}", proxy.host, proxy.port);
+    let target = (host, port);
+
+    let connect = async {
+        match (&proxy.username, &proxy.password) {
+            (Some(user), Some(pass)) => {
+                Socks5Stream::connect_with_password(
+                    proxy_addr.as_str(),
+                    target,
+                    user,
+                    pass.expose(),
+                )
+                .await
+            }
+            _ => Socks5Stream::connect(proxy_addr.as_str(), target).await,
+        }
+    };
+
+    let stream = timeout(connect_timeout, connect)
+        .await
+        .map_err(|_| "Proxy connection timed out".to_string())?
+        .map_err(|e| match e {
+            tokio_socks::Error::Io(io) => format!("Could not reach proxy {}: {}", proxy_addr, io),
+            other => format!(
+                "Proxy {} refused the connection to {}:{}: {}",
+                proxy_addr, host, port, other
+            ),
+        })?;
+
+    Ok(stream.into_inner())
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct TransferProgress {
     pub transfer_id: String,
     pub filename: String,
     pub progress: u64,
     pub total: u64,
     pub status: String,
+    /// Set on the files of a multi-file operation and on its rolled-up progress, see `BatchProgress`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+}
+
+/// FTP representation type for a single-file transfer.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferType {
+    /// ASCII for the extensions in `AppConfig::text_extensions`, binary otherwise.
+    #[default]
+    Auto,
+    /// Converts line endings; only safe for plain text.
+    Ascii,
+    Binary,
+}
+
+impl TransferType {
+    fn file_type<R: Runtime>(self, window: &Window<R>, file_name: &str) -> FileType {
+        let ascii = match self {
+            TransferType::Auto => crate::config::is_text_file(window, file_name),
+            TransferType::Ascii => true,
+            TransferType::Binary => false,
+        };
+        if ascii {
+            FileType::Ascii(FormatControl::Default)
+        } else {
+            FileType::Binary
+        }
+    }
 }
 
 #[tauri::command]
 pub async fn connect_ftp(
     state: State<'_, FtpState>,
     config: FtpConfigPayload,
+) -> Result<String, String> {
+    connect_with_config(&state, config).await
+}
+
+/// Connects using a connection saved in the app config, looked up by its id.
+#[tauri::command]
+pub async fn connect_saved_ftp(
+    app: AppHandle,
+    state: State<'_, FtpState>,
+    connection_id: String,
+) -> Result<String, String> {
+    let config = saved_connection_config(app, &connection_id)?;
+    connect_with_config(&state, config).await
+}
+
+fn saved_connection_config(
+    app: AppHandle,
+    connection_id: &str,
+) -> Result<FtpConfigPayload, String> {
+    let app_config = crate::config::load_config(app)?;
+    let conn = app_config
+        .ftp_connections
+        .iter()
+        .find(|c| c.id == connection_id)
+        .ok_or_else(|| format!("No saved FTP connection with id {}", connection_id))?;
+
+    Ok(FtpConfigPayload {
+        host: conn.host.clone(),
+        port: conn.port,
+        username: conn.username.clone(),
+        password: conn.password.clone(),
+        secure: conn.secure,
+        account: conn.account.clone(),
+        month_names: None,
+        auto_reconnect: conn.auto_reconnect,
+        proxy: conn.proxy.clone(),
+        remote_encoding: conn.remote_encoding.clone(),
+        fix_passive_nat: conn.fix_passive_nat,
+        keep_alive: conn.keep_alive,
+    })
+}
+
+/// Clears a busy flag such as `FtpState::data_transfer_active` when dropped,
+/// however the guarded operation ends.
+struct FlagGuard<'a>(&'a AtomicBool);
+
+impl Drop for FlagGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Runs the data connection step `io` unless the transfer is cancelled or
+/// aborted first, in which case it returns `None`.
+async fn unless_aborted<T>(
+    state: &FtpState,
+    registry: &TransferRegistry,
+    transfer_id: &str,
+    io: impl std::future::Future<Output = T>,
+) -> Option<T> {
+    // Registered before the flags are checked, so a wake-up sent in between
+    // isn't missed
+    let aborted = state.data_abort.notified();
+    tokio::pin!(aborted);
+    aborted.as_mut().enable();
+    if registry.is_cancelled(transfer_id) || state.data_abort_requested.load(Ordering::SeqCst) {
+        return None;
+    }
+    tokio::select! {
+        out = io => Some(out),
+        _ = aborted => None,
+    }
+}
+
+/// Sends QUIT on any open session and removes it from state.
+async fn close_sessions(state: &FtpState) {
+    if let Some(mut client) = state.secure_client.lock().await.take() {
+        let _ = timeout(Duration::from_secs(5), client.quit()).await;
+    }
+    if let Some(mut client) = state.client.lock().await.take() {
+        let _ = timeout(Duration::from_secs(5), client.quit()).await;
+    }
+    state.server_info.lock().await.take();
+}
+
+/// Prefix of the error an earlier connect attempt returns once a newer one has
+/// replaced it, e.g. after the user picked another server mid-connect.
+pub const CONNECT_SUPERSEDED: &str = "ConnectSuperseded";
+
+/// Connects with `config`, superseding any attempt still in flight.
+async fn connect_with_config(state: &FtpState, config: FtpConfigPayload) -> Result<String, String> {
+    let generation = state.connect_generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+    // Replacing a live session without QUIT would leave it dangling on the server
+    close_sessions(state).await;
+
+    let message = establish_connection(state, &config, generation)
+        .await
+        .map_err(|e| config.redact(&e))
+        .inspect_err(|e| tracing::warn!(host = %config.host, error = %e, "FTP connect failed"))?;
+    let mut last_config = state.last_config.lock().await;
+    if state.connect_generation.load(Ordering::SeqCst) == generation {
+        *last_config = Some(config);
+        state.set_working_dir(None);
+    }
+    state.listing_cache.clear();
+    Ok(message)
+}
+
+fn superseded_error(host: &str) -> String {
+    format!(
+        "{}: the connection to {} was replaced by a newer attempt",
+        CONNECT_SUPERSEDED, host
+    )
+}
+
+/// Best-effort session tuning after login: identifies the client with `CLNT`,
+/// enables the `OPTS` the server advertises in `FEAT` and switches to binary
+/// type. Replies are ignored so servers that reject any of these still connect.
+/// Evaluates to the advertised features, with their parameters.
+///
+/// Expanded per stream type for the same reason as `with_active_client!`.
+macro_rules! negotiate_options {
+    ($stream:expr) => {{
+        let mut advertised = Vec::new();
+        if let Ok(Ok(features)) = timeout(Duration::from_secs(5), $stream.feat()).await {
+            let mut commands = Vec::new();
+            if features.contains_key("CLNT") {
+                commands.push(format!("CLNT QuickSyncDrives/{}", env!("CARGO_PKG_VERSION")));
+            }
+            if features.contains_key("UTF8") {
+                commands.push("OPTS UTF8 ON".to_string());
+            }
+            // Ask for the facts the server offers, e.g. "type*;size*;modify*;" -> "type;size;modify;"
+            if let Some(Some(facts)) = features.get("MLST") {
+                commands.push(format!("OPTS MLST {}", facts.replace('*', "")));
+            }
+            for command in commands {
+                let reply = timeout(
+                    Duration::from_secs(5),
+                    $stream.custom_command(command.as_str(), &[Status::CommandOk, Status::Name]),
+                )
+                .await;
+                if !matches!(reply, Ok(Ok(_))) {
+                    tracing::debug!(%command, "Server rejected optional command");
+                }
+            }
+            advertised = features
+                .into_iter()
+                .map(|(name, params)| match params {
+                    Some(params) => format!("{} {}", name, params),
+                    None => name,
+                })
+                .collect();
+            advertised.sort();
+        }
+        // Servers may start in ASCII, which would mangle transfers that don't set a type
+        let _ = timeout(Duration::from_secs(5), $stream.transfer_type(FileType::Binary)).await;
+        advertised
+    }};
+}
+
+/// Sends USER/PASS, followed by `ACCT` when the server replies 332 asking for
+/// an account. Evaluates to a `Result<(), String>`.
+///
+/// Expanded per stream type for the same reason as `with_active_client!`.
+macro_rules! log_in {
+    ($stream:expr, $config:expr, $wait:expr, $action:expr) => {{
+        let login = timeout(
+            $wait,
+            $stream.login(
+                $config.username.as_str(),
+                $config.password.as_ref().map_or("", SecretString::expose),
+            ),
+        )
+        .await
+        .map_err(|_| format!("{} timed out", $action))?;
+        match login {
+            Err(FtpError::UnexpectedResponse(resp)) if resp.status == Status::LoginNeedAccount => {
+                match $config.account.as_deref().filter(|a| !a.is_empty()) {
+                    Some(account) => timeout(
+                        $wait,
+                        $stream.custom_command(
+                            format!("ACCT {}", account),
+                            &[Status::LoggedIn, Status::CommandOk],
+                        ),
+                    )
+                    .await
+                    .map_err(|_| format!("{} timed out", $action))?
+                    .map(|_| ())
+                    .map_err(|e| login_error($action, e)),
+                    None => Err(format!(
+                        "{}: The server requires an account (ACCT) for {}; add one to the connection settings",
+                        ACCOUNT_REQUIRED, $config.username
+                    )),
+                }
+            }
+            result => result.map_err(|e| login_error($action, e)),
+        }
+    }};
+}
+
+/// Opens a session with `config` and stores it in `state`, unless a connect newer
+/// than `generation` started meanwhile. The session is then closed and
+/// `CONNECT_SUPERSEDED` returned.
+#[tracing::instrument(skip_all, fields(host = %config.host, port = config.port, secure = config.secure))]
+async fn establish_connection(
+    state: &FtpState,
+    config: &FtpConfigPayload,
+    generation: u64,
 ) -> Result<String, String> {
     let host_port = format!("{}:{}", config.host, config.port);
+    let mut dialect = ListingDialect::from_config(config)?;
+    let connect_timeout = state.policy().connect_timeout();
 
     if config.secure {
         // For FTPS: Use AsyncRustlsFtpStream::connect() which creates a stream
         // typed as ImplAsyncFtpStream<AsyncRustlsStream>, so into_secure
         // can properly resolve AsyncTlsConnector<Stream = AsyncRustlsStream>.
-        let ftp_stream = timeout(
-            Duration::from_secs(10),
-            AsyncRustlsFtpStream::connect(&host_port),
-        )
-        .await
+        let ftp_stream = match config.proxy {
+            Some(ref proxy) => {
+                let tcp = dial_via_proxy(proxy, &config.host, config.port, connect_timeout).await?;
+                timeout(
+                    connect_timeout,
+                    AsyncRustlsFtpStream::connect_with_stream(tcp),
+                )
+                .await
+            }
+            None => timeout(connect_timeout, AsyncRustlsFtpStream::connect(&host_port)).await,
+        }
         .map_err(|_| "Connection timed out".to_string())?
         .map_err(|e| format!("Connection failed: {}", e))?;
 
+        // The greeting is read on connect, before the TLS upgrade
+        let welcome = ftp_stream.get_welcome_msg().map(str::to_string);
+
         // Prepare Rustls config (rustls 0.23 API)
         let _ = rustls::crypto::ring::default_provider().install_default();
 
@@ -134,744 +571,4766 @@ pub async fn connect_ftp(
         tls_config
             .dangerous()
             .set_certificate_verifier(Arc::new(DummyVerifier::new(root_store_arc)));
+        // rustls caches sessions by default, and data connections share this
+        // config, so they resume the control connection's session as strict
+        // servers (vsftpd's require_ssl_reuse) insist on
 
         let tls_connector = suppaftp::tokio_rustls::TlsConnector::from(Arc::new(tls_config));
+        if let Ok(server_name) = ServerName::try_from(config.host.clone()) {
+            dialect.data_tls = Some(DataTls {
+                connector: tls_connector.clone(),
+                server_name,
+            });
+        }
         let connector = AsyncRustlsConnector::from(tls_connector);
 
         // Upgrade to TLS
         let mut secure_stream = timeout(
-            Duration::from_secs(10),
+            connect_timeout,
             ftp_stream.into_secure(connector, &config.host),
         )
         .await
         .map_err(|_| "TLS upgrade timed out".to_string())?
-        .map_err(|e| format!("TLS upgrade failed: {}", e))?;
+        .map_err(|e| {
+            format!(
+                "{}: TLS handshake on the control connection failed: {}",
+                CONTROL_TLS_FAILED, e
+            )
+        })?;
 
-        timeout(
-            Duration::from_secs(10),
-            secure_stream.login(
-                config.username.as_str(),
-                config.password.as_deref().unwrap_or(""),
-            ),
-        )
-        .await
-        .map_err(|_| "Secure Login timed out".to_string())?
-        .map_err(|e| format!("Secure Login failed: {}", e))?;
+        log_in!(secure_stream, config, connect_timeout, "Secure Login")?;
 
         // Enable passive mode so data connections work through firewalls/NAT
         secure_stream.set_mode(Mode::Passive);
+        secure_stream.set_passive_nat_workaround(config.fix_passive_nat);
+        let features = negotiate_options!(secure_stream);
+
+        let system = timeout(
+            Duration::from_secs(5),
+            secure_stream.custom_command("SYST", &[Status::Name]),
+        )
+        .await
+        .ok()
+        .and_then(|r| r.ok())
+        .map(|r| syst_text(&r.body));
 
+        // Checked under the session lock, which a newer connect takes to close sessions
         let mut lock = state.secure_client.lock().await;
+        if state.connect_generation.load(Ordering::SeqCst) != generation {
+            drop(lock);
+            let _ = timeout(Duration::from_secs(5), secure_stream.quit()).await;
+            return Err(superseded_error(&config.host));
+        }
+        state.ascii_type.store(false, Ordering::Relaxed);
+        state.set_dialect(dialect);
+        *state.server_info.lock().await = Some(ServerInfo {
+            welcome,
+            system,
+            features,
+        });
         *lock = Some(secure_stream);
         Ok(format!("Securely connected to {}", config.host))
     } else {
         // Plain FTP: connect and login directly
-        let mut ftp_stream = timeout(Duration::from_secs(10), AsyncFtpStream::connect(&host_port))
-            .await
-            .map_err(|_| "Connection timed out".to_string())?
-            .map_err(|e| format!("Connection failed: {}", e))?;
+        let mut ftp_stream = match config.proxy {
+            Some(ref proxy) => {
+                let tcp = dial_via_proxy(proxy, &config.host, config.port, connect_timeout).await?;
+                timeout(connect_timeout, AsyncFtpStream::connect_with_stream(tcp)).await
+            }
+            None => timeout(connect_timeout, AsyncFtpStream::connect(&host_port)).await,
+        }
+        .map_err(|_| "Connection timed out".to_string())?
+        .map_err(|e| format!("Connection failed: {}", e))?;
 
-        timeout(
-            Duration::from_secs(10),
-            ftp_stream.login(
-                config.username.as_str(),
-                config.password.as_deref().unwrap_or(""),
-            ),
-        )
-        .await
-        .map_err(|_| "Login timed out".to_string())?
-        .map_err(|e| format!("Login failed: {}", e))?;
+        log_in!(ftp_stream, config, connect_timeout, "Login")?;
 
         // Enable passive mode so data connections work through firewalls/NAT
         ftp_stream.set_mode(Mode::Passive);
+        ftp_stream.set_passive_nat_workaround(config.fix_passive_nat);
+        let features = negotiate_options!(ftp_stream);
+
+        let welcome = ftp_stream.get_welcome_msg().map(str::to_string);
+        let system = timeout(
+            Duration::from_secs(5),
+            ftp_stream.custom_command("SYST", &[Status::Name]),
+        )
+        .await
+        .ok()
+        .and_then(|r| r.ok())
+        .map(|r| syst_text(&r.body));
 
         let mut lock = state.client.lock().await;
+        if state.connect_generation.load(Ordering::SeqCst) != generation {
+            drop(lock);
+            let _ = timeout(Duration::from_secs(5), ftp_stream.quit()).await;
+            return Err(superseded_error(&config.host));
+        }
+        state.ascii_type.store(false, Ordering::Relaxed);
+        state.set_dialect(dialect);
+        *state.server_info.lock().await = Some(ServerInfo {
+            welcome,
+            system,
+            features,
+        });
         *lock = Some(ftp_stream);
         Ok(format!("Connected to {}", config.host))
     }
 }
 
-#[tauri::command]
-pub async fn disconnect_ftp(state: State<'_, FtpState>) -> Result<String, String> {
-    // Try to disconnect secure client first
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let _ = timeout(Duration::from_secs(5), client.quit()).await;
-            *lock = None;
-            return Ok("Disconnected secure session".into());
-        }
+/// Error prefixes for rejected logins, so the frontend can tell the causes apart.
+pub const AUTH_FAILED: &str = "AuthFailed";
+pub const SERVICE_UNAVAILABLE: &str = "ServiceUnavailable";
+pub const ACCESS_DENIED: &str = "AccessDenied";
+pub const ACCOUNT_REQUIRED: &str = "AccountRequired";
+
+/// Turns a failed USER/PASS exchange into an actionable message, keyed by the reply code.
+fn login_error(action: &str, err: FtpError) -> String {
+    let FtpError::UnexpectedResponse(resp) = &err else {
+        return format!("{} failed: {}", action, err);
+    };
+    let reply = String::from_utf8_lossy(&resp.body).trim().to_string();
+    match resp.status {
+        Status::NotLoggedIn => format!(
+            "{}: Wrong username or password ({})",
+            AUTH_FAILED, reply
+        ),
+        Status::NotAvailable => format!(
+            "{}: The server is refusing connections, often because too many are open; try again later ({})",
+            SERVICE_UNAVAILABLE, reply
+        ),
+        Status::FileUnavailable => format!(
+            "{}: This account is not allowed to log in ({})",
+            ACCESS_DENIED, reply
+        ),
+        _ => format!("{} failed: {}", action, err),
     }
+}
 
-    // Then plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let _ = timeout(Duration::from_secs(5), client.quit()).await;
-            *lock = None;
-            return Ok("Disconnected plain session".into());
+/// Strips the status code from a raw `215 UNIX Type: L8` reply.
+fn syst_text(body: &[u8]) -> String {
+    String::from_utf8_lossy(body)
+        .trim()
+        .trim_start_matches("215")
+        .trim_start_matches(['-', ' '])
+        .to_string()
+}
+
+/// Prefix of errors where the control connection works but the passive data
+/// connection for a transfer or listing couldn't be opened.
+pub const DATA_CONNECTION_BLOCKED: &str = "DataConnectionBlocked";
+
+/// Prefixes of TLS failures on the control connection (the session never came
+/// up) and on a data connection (the session works but transfers don't).
+pub const CONTROL_TLS_FAILED: &str = "ControlTlsFailed";
+pub const DATA_TLS_FAILED: &str = "DataTlsFailed";
+
+/// Maps the error of a command that needs a data connection (LIST, RETR, STOR),
+/// singling out a data connection that never opened: a 425 reply, or a dial
+/// that timed out or was refused. Failed TLS handshakes on the data connection,
+/// which rustls reports as invalid data, or a 522 reply refusing it, are
+/// reported as `DATA_TLS_FAILED`.
+fn data_command_error(action: &str, err: FtpError) -> String {
+    let tls_cause = match &err {
+        FtpError::SecureError(e) => Some(e.clone()),
+        FtpError::ConnectionError(io) if io.kind() == std::io::ErrorKind::InvalidData => {
+            Some(io.to_string())
+        }
+        FtpError::UnexpectedResponse(resp) if resp.body.starts_with(b"522") => {
+            Some(String::from_utf8_lossy(&resp.body).trim().to_string())
         }
+        _ => None,
+    };
+    if let Some(cause) = tls_cause {
+        return format!(
+            "{}: {} could not secure its data connection with TLS ({}). The server may \
+             require data connections to reuse the control connection's TLS session.",
+            DATA_TLS_FAILED, action, cause
+        );
     }
-
-    Err("No active connection".into())
+    let cause = match &err {
+        FtpError::UnexpectedResponse(resp) if resp.status == Status::CannotOpenDataConnection => {
+            String::from_utf8_lossy(&resp.body).trim().to_string()
+        }
+        FtpError::ConnectionError(io)
+            if matches!(
+                io.kind(),
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            io.to_string()
+        }
+        _ => return ftp_failure(format!("{} failed", action), err),
+    };
+    data_connection_blocked(action, cause)
 }
 
-#[derive(Serialize)]
-pub struct RemoteFileEntry {
-    pub name: String,
-    pub is_dir: bool,
-    pub size: u64,
-    pub permissions: String,
-    pub modified: String,
+/// The `DATA_CONNECTION_BLOCKED` error of `action`, also used when a command
+/// needing a data connection times out while the control connection answers.
+fn data_connection_blocked(action: &str, cause: impl std::fmt::Display) -> String {
+    format!(
+        "{}: {} could not open its data connection ({}). A firewall or NAT is probably \
+         blocking the passive mode ports; try active mode or check the firewall.",
+        DATA_CONNECTION_BLOCKED, action, cause
+    )
 }
 
-fn parse_list_line(line: &str) -> Option<RemoteFileEntry> {
-    // Parse Unix-style LIST output:
-    // drwxr-xr-x   2 user group  4096 Jan  1 12:00 dirname
-    // -rw-r--r--   1 user group 12345 Jan  1 12:00 filename.txt
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() < 9 {
-        return None;
-    }
+/// Prefix `ftp_failure` gives errors that mean the control connection is gone.
+const CONNECTION_LOST: &str = "ConnectionLost";
 
-    let perms = parts[0];
-    let is_dir = perms.starts_with('d');
-    let size = parts[4].parse::<u64>().unwrap_or(0);
-    let modified = format!("{} {} {}", parts[5], parts[6], parts[7]);
-    // Name can contain spaces, so join everything from index 8 onwards
-    let name = parts[8..].join(" ");
+/// Whether `err` means the control connection itself is gone, as opposed to
+/// the server rejecting the command: an I/O failure, or a 421 reply.
+fn is_dead_connection(err: &FtpError) -> bool {
+    match err {
+        FtpError::ConnectionError(_) => true,
+        FtpError::UnexpectedResponse(resp) => resp.status == Status::NotAvailable,
+        _ => false,
+    }
+}
 
-    // Skip . and ..
-    if name == "." || name == ".." {
-        return None;
+/// Formats a failed command as "<action>: <err>", marked with `CONNECTION_LOST`
+/// when `is_dead_connection` holds, so it is still recognizable once a string.
+fn ftp_failure(action: impl std::fmt::Display, err: FtpError) -> String {
+    if is_dead_connection(&err) {
+        format!("{}: {}: {}", CONNECTION_LOST, action, err)
+    } else {
+        format!("{}: {}", action, err)
     }
+}
 
-    Some(RemoteFileEntry {
-        name,
-        is_dir,
-        size,
-        permissions: perms.to_string(),
-        modified,
-    })
+/// Whether an error from `ftp_failure` means the control connection is gone.
+fn is_connection_error(err: &str) -> bool {
+    err.starts_with(CONNECTION_LOST)
 }
 
-#[tauri::command]
-pub async fn list_remote_directory(
-    state: State<'_, FtpState>,
-    path: Option<String>,
-) -> Result<Vec<RemoteFileEntry>, String> {
-    let dir_path = path.as_deref();
+/// Prefix of the error returned once a dropped session has been cleared from state,
+/// so the frontend can tell "reconnect needed" apart from a failed command.
+pub const NOT_CONNECTED: &str = "NotConnected";
 
-    // Try secure client first
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            if let Some(p) = dir_path {
-                timeout(Duration::from_secs(5), client.cwd(p))
-                    .await
-                    .map_err(|_| "CWD timed out".to_string())?
-                    .map_err(|e| format!("CWD failed: {}", e))?;
-            }
-            let lines = timeout(Duration::from_secs(30), client.list(None))
-                .await
-                .map_err(|_| "LIST timed out".to_string())?
-                .map_err(|e| format!("LIST failed: {}", e))?;
-            let mut entries: Vec<RemoteFileEntry> =
-                lines.iter().filter_map(|l| parse_list_line(l)).collect();
-            entries.sort_by(|a, b| {
-                b.is_dir
-                    .cmp(&a.is_dir)
-                    .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-            });
-            return Ok(entries);
+/// Forgets sessions whose control connection is gone. No QUIT is sent since
+/// there is nobody left to receive it.
+async fn drop_dead_sessions(state: &FtpState) {
+    *state.secure_client.lock().await = None;
+    *state.client.lock().await = None;
+    state.server_info.lock().await.take();
+    state.listing_cache.clear();
+}
+
+/// Runs `$body` with `$client` bound to whichever session is connected, preferring
+/// the secure one, or fails with `NOT_CONNECTED` when there is none.
+///
+/// The plain and secure streams are distinct types with no shared trait for their
+/// async methods, so the body is expanded once per type instead of taking a closure.
+macro_rules! with_active_client {
+    ($state:expr, |$client:ident| $body:block) => {{
+        let mut secure = $state.secure_client.lock().await;
+        if let Some($client) = secure.as_mut() {
+            $body
+        } else {
+            drop(secure);
+            let mut plain = $state.client.lock().await;
+            match plain.as_mut() {
+                Some($client) => $body,
+                None => Err(format!("{}: No active FTP connection", NOT_CONNECTED)),
+            }
         }
-    }
+    }};
+}
 
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            if let Some(p) = dir_path {
-                timeout(Duration::from_secs(5), client.cwd(p))
-                    .await
-                    .map_err(|_| "CWD timed out".to_string())?
-                    .map_err(|e| format!("CWD failed: {}", e))?;
-            }
-            let lines = timeout(Duration::from_secs(30), client.list(None))
-                .await
-                .map_err(|_| "LIST timed out".to_string())?
-                .map_err(|e| format!("LIST failed: {}", e))?;
-            let mut entries: Vec<RemoteFileEntry> =
-                lines.iter().filter_map(|l| parse_list_line(l)).collect();
-            entries.sort_by(|a, b| {
-                b.is_dir
-                    .cmp(&a.is_dir)
-                    .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-            });
-            return Ok(entries);
+async fn reconnect(state: &FtpState) -> Result<(), String> {
+    let config = state
+        .last_config
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "No active FTP connection".to_string())?;
+
+    let generation = state.connect_generation.load(Ordering::SeqCst);
+
+    // Drop the dead sessions before dialing again
+    drop_dead_sessions(state).await;
+
+    establish_connection(state, &config, generation)
+        .await
+        .map_err(|e| config.redact(&e))?;
+
+    // The new session starts in the login directory, which would send relative
+    // paths, and a retried `remote_cd`, somewhere else
+    if let Some(dir) = state.working_dir() {
+        if let Err(e) = restore_working_dir(state, &dir).await {
+            tracing::warn!(dir = %dir, error = %e, "could not return to the working directory");
+            state.set_working_dir(None);
         }
     }
+    Ok(())
+}
 
-    Err("No active FTP connection".into())
+async fn restore_working_dir(state: &FtpState, dir: &str) -> Result<(), String> {
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.cwd(dir))
+            .await
+            .map_err(|_| "CWD timed out".to_string())?
+            .map_err(|e| ftp_failure("CWD failed", e))
+    })
 }
 
-#[tauri::command]
-pub async fn get_remote_pwd(state: State<'_, FtpState>) -> Result<String, String> {
-    // Try secure client first
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            return timeout(Duration::from_secs(5), client.pwd())
-                .await
-                .map_err(|_| "PWD timed out".to_string())?
-                .map_err(|e| format!("PWD failed: {}", e));
+/// Runs `op`, and if it failed because the session dropped, reconnects with the
+/// cached settings and retries it, up to `TransferPolicy::retries` times with
+/// backoff in between. A session that stays dead is removed from state and
+/// reported as `NOT_CONNECTED`.
+async fn with_reconnect<T, F, Fut>(state: &FtpState, op: F) -> Result<T, String>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    let err = match op().await {
+        Err(e) if is_connection_error(&e) => e,
+        result => return result,
+    };
+
+    let enabled = state
+        .last_config
+        .lock()
+        .await
+        .as_ref()
+        .map(|c| c.auto_reconnect)
+        .unwrap_or(false);
+    if enabled {
+        let policy = state.policy();
+        for attempt in 0..policy.retries {
+            tracing::warn!(error = %err, attempt, "FTP session dropped, reconnecting");
+            tokio::time::sleep(policy.backoff(attempt)).await;
+            match reconnect(state).await {
+                Ok(()) => match op().await {
+                    Err(e) if is_connection_error(&e) => {}
+                    result => return result,
+                },
+                Err(reconnect_err) => {
+                    tracing::warn!(error = %reconnect_err, "FTP reconnect failed");
+                }
+            }
         }
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            return timeout(Duration::from_secs(5), client.pwd())
-                .await
-                .map_err(|_| "PWD timed out".to_string())?
-                .map_err(|e| format!("PWD failed: {}", e));
+
+    drop_dead_sessions(state).await;
+    Err(format!(
+        "{}: the server closed the connection ({}). Please reconnect.",
+        NOT_CONNECTED, err
+    ))
+}
+
+/// `with_reconnect` for commands that aren't safe to repeat, such as uploads,
+/// renames and deletes: a dropped session is reconnected for the next command,
+/// but `op` is not run again, as it may already have taken effect.
+async fn with_reconnect_once<T, Fut>(state: &FtpState, op: Fut) -> Result<T, String>
+where
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    let err = match op.await {
+        Err(e) if is_connection_error(&e) => e,
+        result => return result,
+    };
+
+    let enabled = state
+        .last_config
+        .lock()
+        .await
+        .as_ref()
+        .map(|c| c.auto_reconnect)
+        .unwrap_or(false);
+    if enabled {
+        tracing::warn!(error = %err, "FTP session dropped, reconnecting without retrying");
+        match reconnect(state).await {
+            Ok(()) => {
+                return Err(format!(
+                    "The connection dropped during the command and was restored; \
+                     check whether the change was made before trying again ({})",
+                    err
+                ))
+            }
+            Err(reconnect_err) => {
+                tracing::warn!(error = %reconnect_err, "FTP reconnect failed");
+            }
         }
     }
-    Err("No active FTP connection".into())
+
+    drop_dead_sessions(state).await;
+    Err(format!(
+        "{}: the server closed the connection ({}). Please reconnect.",
+        NOT_CONNECTED, err
+    ))
 }
 
-#[tauri::command]
-pub async fn download_remote_file(
-    window: Window,
+/// How often an idle session is pinged when `FtpConfigPayload::keep_alive` is on.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Wall-clock time between two pings past which the machine is assumed to have
+/// slept. Monotonic timers pause during suspend on some platforms, so the gap
+/// only shows up on the system clock.
+const SLEEP_GAP: Duration = Duration::from_secs(3 * 60);
+
+/// Payload of the `connection-restored` and `connection-lost` events.
+#[derive(Serialize, Clone)]
+struct ConnectionEvent {
+    host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn noop_succeeded(reply: Result<Result<(), FtpError>, tokio::time::error::Elapsed>) -> bool {
+    match reply {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => !is_dead_connection(&e),
+        Err(_) => false,
+    }
+}
+
+/// Sends NOOP on the session unless a command is using it, in which case it is
+/// evidently alive. `None` when there is nothing to ping.
+async fn ping_idle_session(state: &FtpState) -> Option<bool> {
+    let wait = state.policy().read_timeout();
+    let Ok(mut secure) = state.secure_client.try_lock() else {
+        return Some(true);
+    };
+    if let Some(client) = secure.as_mut() {
+        return Some(noop_succeeded(timeout(wait, client.noop()).await));
+    }
+    drop(secure);
+    let Ok(mut plain) = state.client.try_lock() else {
+        return Some(true);
+    };
+    let client = plain.as_mut()?;
+    Some(noop_succeeded(timeout(wait, client.noop()).await))
+}
+
+/// Keeps sessions with `keep_alive` set from going stale: pings them while idle,
+/// and after a NOOP fails or the machine wakes from sleep, reconnects with the
+/// cached settings straight away rather than on the next command. Emits
+/// `connection-restored` or `connection-lost` with the outcome.
+pub fn keep_alive(app: &AppHandle) {
+    let handle = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut last_tick = std::time::SystemTime::now();
+        loop {
+            ticker.tick().await;
+            let now = std::time::SystemTime::now();
+            let woke = now
+                .duration_since(last_tick)
+                .is_ok_and(|gap| gap > SLEEP_GAP);
+            last_tick = now;
+
+            let state = handle.state::<FtpState>();
+            let Some(config) = state.last_config.lock().await.clone() else {
+                continue;
+            };
+            if !config.keep_alive {
+                continue;
+            }
+            if !woke && ping_idle_session(&state).await != Some(false) {
+                continue;
+            }
+
+            tracing::info!(host = %config.host, woke, "FTP session went stale, reconnecting");
+            match reconnect(&state).await {
+                Ok(()) => {
+                    let _ = handle.emit(
+                        "connection-restored",
+                        ConnectionEvent {
+                            host: config.host,
+                            error: None,
+                        },
+                    );
+                }
+                // A newer connect or a disconnect took over in the meantime
+                Err(e) if e.starts_with(CONNECT_SUPERSEDED) => {}
+                Err(_) if state.last_config.lock().await.is_none() => {}
+                Err(e) => {
+                    tracing::warn!(host = %config.host, error = %e, "FTP reconnect after wake failed");
+                    drop_dead_sessions(&state).await;
+                    let _ = handle.emit(
+                        "connection-lost",
+                        ConnectionEvent {
+                            host: config.host,
+                            error: Some(e),
+                        },
+                    );
+                }
+            }
+        }
+    });
+    if let Ok(mut slot) = app.state::<FtpState>().keep_alive_task.lock() {
+        *slot = Some(task);
+    }
+}
+
+/// Upper bound on how long exit waits for servers to acknowledge QUIT.
+const EXIT_CLEANUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Closes the session politely on app exit so it doesn't linger on servers with
+/// low connection limits until they time it out.
+pub fn shutdown(app: &AppHandle) {
+    let state = app.state::<FtpState>();
+    if let Some(task) = state.keep_alive_task.lock().ok().and_then(|mut t| t.take()) {
+        task.abort();
+    }
+    tauri::async_runtime::block_on(async {
+        // Nothing may reconnect while the session is being closed
+        state.last_config.lock().await.take();
+        if timeout(EXIT_CLEANUP_TIMEOUT, close_sessions(&state))
+            .await
+            .is_err()
+        {
+            tracing::warn!("FTP server did not answer QUIT before exit");
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn disconnect_ftp(state: State<'_, FtpState>) -> Result<String, String> {
+    // Supersedes any connect or reconnect still in flight, so it discards its
+    // session instead of storing it after the disconnect
+    state.connect_generation.fetch_add(1, Ordering::SeqCst);
+
+    // Forget the cached credentials so nothing can silently reconnect
+    if let Some(mut config) = state.last_config.lock().await.take() {
+        if let Some(ref mut password) = config.password {
+            password.zeroize();
+        }
+        if let Some(password) = config.proxy.as_mut().and_then(|p| p.password.as_mut()) {
+            password.zeroize();
+        }
+    }
+    state.listing_cache.clear();
+    state.server_info.lock().await.take();
+
+    // Try to disconnect secure client first
+    {
+        let mut lock = state.secure_client.lock().await;
+        if let Some(ref mut client) = *lock {
+            let _ = timeout(Duration::from_secs(5), client.quit()).await;
+            *lock = None;
+            return Ok("Disconnected secure session".into());
+        }
+    }
+
+    // Then plain client
+    {
+        let mut lock = state.client.lock().await;
+        if let Some(ref mut client) = *lock {
+            let _ = timeout(Duration::from_secs(5), client.quit()).await;
+            *lock = None;
+            return Ok("Disconnected plain session".into());
+        }
+    }
+
+    Err("No active connection".into())
+}
+
+/// Returns the welcome banner and `SYST` reply captured when the session was opened.
+#[tauri::command]
+pub async fn get_server_welcome(state: State<'_, FtpState>) -> Result<ServerInfo, String> {
+    state
+        .server_info
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "No active FTP connection".to_string())
+}
+
+/// Verbs that open a data connection, which a raw command has no way to read.
+const DATA_CONNECTION_VERBS: [&str; 7] = ["LIST", "NLST", "MLSD", "RETR", "STOR", "STOU", "APPE"];
+
+/// Verbs that change login, TLS, transfer or directory state the client keeps
+/// its own view of, so sending them raw would desync it from the server.
+const SESSION_STATE_VERBS: [&str; 21] = [
+    "USER", "PASS", "ACCT", "REIN", "QUIT", "AUTH", "PBSZ", "PROT", "CCC", "TYPE", "MODE", "STRU",
+    "PASV", "EPSV", "PORT", "EPRT", "REST", "CWD", "CDUP", "XCWD", "XCUP",
+];
+
+/// A server's reply to a raw command, whether or not it signals success.
+#[derive(Serialize)]
+pub struct RawReply {
+    pub code: u32,
+    /// Reply text including the code, with continuation lines of multi-line replies.
+    pub text: String,
+}
+
+/// Rejects commands that could smuggle in a second command, would leave a
+/// data connection open or would change session state behind the client's
+/// back. Evaluates to the uppercased verb.
+fn check_raw_command(command: &str) -> Result<String, String> {
+    if command.contains(['\r', '\n', '\0']) {
+        return Err("Command must be a single line".into());
+    }
+    let verb = command
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| "Empty command".to_string())?
+        .to_ascii_uppercase();
+    if DATA_CONNECTION_VERBS.contains(&verb.as_str()) {
+        return Err(format!(
+            "{} needs a data connection, use the transfer commands instead",
+            verb
+        ));
+    }
+    if SESSION_STATE_VERBS.contains(&verb.as_str()) {
+        return Err(format!(
+            "{} changes the session state the app depends on and can't be sent raw",
+            verb
+        ));
+    }
+    Ok(verb)
+}
+
+async fn send_raw_command_inner(state: &FtpState, command: String) -> Result<RawReply, String> {
+    let verb = check_raw_command(&command)?;
+    with_active_client!(state, |client| {
+        let reply = timeout(
+            state.policy().read_timeout(),
+            client.custom_command(command.as_str(), &[Status::CommandOk]),
+        )
+        .await
+        .map_err(|_| format!("{} timed out", verb))?;
+        let reply = match reply {
+            Ok(reply) | Err(FtpError::UnexpectedResponse(reply)) => reply,
+            Err(e) => return Err(ftp_failure(format!("{} failed", verb), e)),
+        };
+        Ok(RawReply {
+            code: reply.status.code(),
+            text: String::from_utf8_lossy(&reply.body).trim_end().to_string(),
+        })
+    })
+}
+
+/// Sends `command` as is and logs it with the reply, passwords masked.
+async fn run_raw_command(state: &FtpState, command: String) -> Result<RawReply, String> {
+    let config = state.last_config.lock().await.clone();
+    let redact = |message: &str| match &config {
+        Some(config) => config.redact(message),
+        None => crate::secret::redact(message, &[]),
+    };
+    let result = with_reconnect_once(state, send_raw_command_inner(state, command.clone())).await;
+    match &result {
+        Ok(reply) => tracing::debug!(
+            command = %redact(&command),
+            reply = %redact(&reply.text),
+            "raw command"
+        ),
+        Err(e) => {
+            tracing::debug!(command = %redact(&command), error = %redact(e), "raw command failed")
+        }
+    }
+    // The command may have changed anything on the server
+    state.listing_cache.clear();
+    result
+}
+
+/// Sends `SITE <command>` to the active server and returns its reply, for
+/// server-specific settings such as quotas or idle timeouts.
+#[tauri::command]
+pub async fn send_site_command(
+    state: State<'_, FtpState>,
+    command: String,
+) -> Result<RawReply, String> {
+    run_raw_command(state.inner(), format!("SITE {}", command)).await
+}
+
+/// Sends any single-line FTP command to the active server and returns its
+/// reply, including error replies. Commands that open a data connection or
+/// change the session state, such as TYPE or CWD, are refused.
+#[tauri::command]
+pub async fn send_raw_command(
+    state: State<'_, FtpState>,
+    command: String,
+) -> Result<RawReply, String> {
+    run_raw_command(state.inner(), command).await
+}
+
+/// How the active session is set up, for diagnosing transfer problems.
+#[derive(Serialize)]
+pub struct ConnectionInfo {
+    pub host: String,
+    pub port: u16,
+    /// Whether the control connection is protected by TLS (explicit FTPS).
+    pub tls: bool,
+    /// Data connection mode; the client always uses "passive".
+    pub mode: String,
+    /// Whether passive data connections go to the control host instead of the PASV address.
+    pub passive_nat_workaround: bool,
+    /// Representation type currently set on the session.
+    pub transfer_type: TransferType,
+    pub remote_encoding: Option<String>,
+    /// `SYST` reply.
+    pub system: Option<String>,
+    /// `FEAT` reply, see `ServerInfo::features`.
+    pub features: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn get_connection_info(state: State<'_, FtpState>) -> Result<ConnectionInfo, String> {
+    let config = state
+        .last_config
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "No active FTP connection".to_string())?;
+    let server = state
+        .server_info
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "No active FTP connection".to_string())?;
+    let transfer_type = if state.ascii_type.load(Ordering::Relaxed) {
+        TransferType::Ascii
+    } else {
+        TransferType::Binary
+    };
+
+    Ok(ConnectionInfo {
+        host: config.host,
+        port: config.port,
+        tls: config.secure,
+        mode: "passive".into(),
+        passive_nat_workaround: config.fix_passive_nat,
+        transfer_type,
+        remote_encoding: config.remote_encoding,
+        system: server.system,
+        features: server.features,
+    })
+}
+
+#[derive(Serialize, Clone)]
+pub struct RemoteFileEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub permissions: String,
+    pub modified: String,
+    /// `modified` normalized to ISO 8601 when the date could be parsed.
+    pub modified_iso: Option<String>,
+    /// Where the entry points when it is a symlink.
+    pub symlink_target: Option<String>,
+}
+
+impl Sortable for RemoteFileEntry {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+    fn size(&self) -> Option<u64> {
+        (!self.is_dir).then_some(self.size)
+    }
+    fn modified(&self) -> Option<&str> {
+        self.modified_iso.as_deref()
+    }
+}
+
+fn parse_list_line(line: &str, dialect: &ListingDialect) -> Option<RemoteFileEntry> {
+    // Parse Unix-style LIST output:
+    // drwxr-xr-x   2 user group  4096 Jan  1 12:00 dirname
+    // -rw-r--r--   1 user group 12345 Jan  1 12:00 filename.txt
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 9 {
+        return None;
+    }
+
+    let perms = parts[0];
+    let mut is_dir = perms.starts_with('d');
+    let size = parts[4].parse::<u64>().unwrap_or(0);
+    let modified = format!("{} {} {}", parts[5], parts[6], parts[7]);
+    // Name can contain spaces, so join everything from index 8 onwards
+    let mut name = parts[8..].join(" ");
+
+    // Symlinks are listed as "name -> target"
+    let mut symlink_target = None;
+    if perms.starts_with('l') {
+        if let Some((link, target)) = name.split_once(" -> ") {
+            // LIST doesn't say what the target is; a trailing slash is the only hint
+            is_dir = target.ends_with('/');
+            symlink_target = Some(target.to_string());
+            name = link.to_string();
+        }
+    }
+
+    // Skip . and ..
+    if name == "." || name == ".." {
+        return None;
+    }
+
+    let modified_iso =
+        parse_list_time(&modified, dialect).map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string());
+
+    Some(RemoteFileEntry {
+        name,
+        is_dir,
+        size,
+        permissions: perms.to_string(),
+        modified,
+        modified_iso,
+        symlink_target,
+    })
+}
+
+/// Month abbreviations as various server locales print them in LIST output
+/// (English, French, German, Spanish, Italian, Dutch, Portuguese).
+const MONTH_NAMES: [&[&str]; 12] = [
+    &["jan", "janv", "ene", "gen"],
+    &["feb", "févr", "fevr", "fév", "fev"],
+    &["mar", "mars", "mär", "märz", "mrt"],
+    &["apr", "avr", "abr"],
+    &["may", "mai", "mei", "mag"],
+    &["jun", "juin", "giu"],
+    &["jul", "juil", "lug"],
+    &["aug", "août", "aout", "ago"],
+    &["sep", "sept", "set"],
+    &["oct", "okt", "ott", "out"],
+    &["nov"],
+    &["dec", "déc", "dez", "dic"],
+];
+
+/// How the LIST replies of one session are read, from its `FtpConfigPayload`.
+#[derive(Default)]
+pub struct ListingDialect {
+    /// Encoding of remote file names; `None` means UTF-8.
+    encoding: Option<&'static encoding_rs::Encoding>,
+    /// Extra month names from `FtpConfigPayload::month_names`.
+    month_names: Vec<(String, u32)>,
+    /// `FtpConfigPayload::fix_passive_nat`, for the data connections `list_lines` opens itself.
+    fix_passive_nat: bool,
+    /// How `list_lines` secures those data connections on an FTPS session.
+    data_tls: Option<DataTls>,
+}
+
+/// The control connection's TLS setup, reused for data connections so they can
+/// resume its session.
+struct DataTls {
+    connector: suppaftp::tokio_rustls::TlsConnector,
+    server_name: ServerName<'static>,
+}
+
+impl ListingDialect {
+    fn from_config(config: &FtpConfigPayload) -> Result<Self, String> {
+        let encoding = match config.remote_encoding.as_deref().filter(|l| !l.is_empty()) {
+            Some(label) => encoding_rs::Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| format!("Unknown remote encoding: {}", label))?,
+            None => encoding_rs::UTF_8,
+        };
+        let month_names = config
+            .month_names
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .take(12)
+            .enumerate()
+            .map(|(i, n)| (n.trim_end_matches('.').to_lowercase(), i as u32 + 1))
+            .collect();
+        Ok(Self {
+            encoding: (encoding != encoding_rs::UTF_8).then_some(encoding),
+            month_names,
+            fix_passive_nat: config.fix_passive_nat,
+            data_tls: None,
+        })
+    }
+}
+
+/// Splits a raw LIST reply into lines decoded with `encoding`. Malformed
+/// sequences become U+FFFD instead of failing the whole listing.
+fn decode_listing(raw: &[u8], encoding: &'static encoding_rs::Encoding) -> Vec<String> {
+    raw.split(|b| *b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .map(|line| encoding.decode_without_bom_handling(line).0.into_owned())
+        .collect()
+}
+
+/// Converts a `rwxr-sr-t`-style permission string (with or without the leading
+/// type character) to its octal mode, e.g. `"755"` or `"3755"` when special bits are set.
+fn permissions_to_octal(perms: &str) -> Option<String> {
+    let chars: Vec<char> = perms.chars().collect();
+    let bits = match chars.len() {
+        10 => &chars[1..],
+        9 => &chars[..],
+        _ => return None,
+    };
+
+    let mut mode = 0u32;
+    let mut special = 0u32;
+    for (group, triple) in bits.chunks(3).enumerate() {
+        let mut value = 0;
+        if triple[0] == 'r' {
+            value |= 4;
+        } else if triple[0] != '-' {
+            return None;
+        }
+        if triple[1] == 'w' {
+            value |= 2;
+        } else if triple[1] != '-' {
+            return None;
+        }
+        // The execute slot doubles as setuid/setgid/sticky: lowercase means the
+        // special bit and execute are both set, uppercase means special bit only.
+        match triple[2] {
+            'x' => value |= 1,
+            '-' => {}
+            's' | 't' => {
+                value |= 1;
+                special |= 4 >> group;
+            }
+            'S' | 'T' => special |= 4 >> group,
+            _ => return None,
+        }
+        mode = (mode << 3) | value;
+    }
+
+    if special > 0 {
+        Some(format!("{:o}{:03o}", special, mode))
+    } else {
+        Some(format!("{:03o}", mode))
+    }
+}
+
+fn month_from_name(name: &str, dialect: &ListingDialect) -> Option<u32> {
+    let lower = name.trim_end_matches('.').to_lowercase();
+    if let Some((_, m)) = dialect.month_names.iter().find(|(n, _)| *n == lower) {
+        return Some(*m);
+    }
+    MONTH_NAMES
+        .iter()
+        .position(|names| names.contains(&lower.as_str()))
+        .map(|i| i as u32 + 1)
+}
+
+/// Turns the "Mon DD HH:MM" / "Mon DD YYYY" date from a LIST line into a timestamp.
+/// Recent entries omit the year, so those are assumed to fall within the last year.
+fn parse_list_time(modified: &str, dialect: &ListingDialect) -> Option<NaiveDateTime> {
+    let parts: Vec<&str> = modified.split_whitespace().collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let month = month_from_name(parts[0], dialect)?;
+    let day = parts[1].parse::<u32>().ok()?;
+
+    if let Some((h, m)) = parts[2].split_once(':') {
+        let hour = h.parse::<u32>().ok()?;
+        let minute = m.parse::<u32>().ok()?;
+        let today = Utc::now().date_naive();
+        let mut date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+        if date > today.succ_opt().unwrap_or(today) {
+            date = NaiveDate::from_ymd_opt(today.year() - 1, month, day)?;
+        }
+        date.and_hms_opt(hour, minute, 0)
+    } else {
+        let year = parts[2].parse::<i32>().ok()?;
+        NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(0, 0, 0)
+    }
+}
+
+/// Identifies the connection cached listings came from, so listings of two
+/// servers, or two accounts on one, are never mixed up.
+async fn connection_key(state: &FtpState) -> String {
+    match state.last_config.lock().await.as_ref() {
+        Some(config) => format!(
+            "ftp://{}@{}:{}",
+            urlencoding::encode(&config.username),
+            config.host,
+            config.port
+        ),
+        None => String::new(),
+    }
+}
+
+/// The absolute directory a listing cache key is for, see `connection_key`.
+pub(crate) fn listing_key_dir(key: &str) -> &str {
+    key.strip_prefix("ftp://")
+        .and_then(|rest| rest.find('/').map(|at| &rest[at..]))
+        .unwrap_or(key)
+}
+
+/// Whether the directory `dir` is `root` or below it.
+fn is_within(dir: &str, root: &str) -> bool {
+    let (dir, root) = (dir.trim_end_matches('/'), root.trim_end_matches('/'));
+    dir == root || dir.starts_with(&format!("{}/", root))
+}
+
+/// Drops the cached listings a change to `paths` made stale: the directory
+/// holding each path, and the path itself with everything below it in case it
+/// is a directory. Relative paths are resolved against the current directory;
+/// if that can't be queried, the whole cache goes.
+async fn invalidate_listings(state: &FtpState, paths: &[&str]) {
+    let cwd = if paths.iter().any(|p| !p.starts_with('/')) {
+        match get_remote_pwd_inner(state).await {
+            Ok(cwd) => cwd,
+            Err(_) => return state.listing_cache.clear(),
+        }
+    } else {
+        String::new()
+    };
+    let stale: Vec<(String, String)> = paths
+        .iter()
+        .map(|path| {
+            let path = absolute_remote_path(&cwd, path);
+            (remote_parent(&path).to_string(), path)
+        })
+        .collect();
+    let connection = connection_key(state).await;
+    state.listing_cache.invalidate(|key| {
+        let Some(dir) = key.strip_prefix(connection.as_str()) else {
+            return false;
+        };
+        stale.iter().any(|(parent, path)| {
+            dir.trim_end_matches('/') == parent.trim_end_matches('/') || is_within(dir, path)
+        })
+    });
+}
+
+async fn list_remote_directory_inner(
+    state: &FtpState,
+    path: Option<String>,
+) -> Result<Vec<RemoteFileEntry>, String> {
+    let dir_path = path.as_deref();
+    let connection = connection_key(state).await;
+    let dialect = state.dialect();
+
+    with_active_client!(state, |client| {
+        if let Some(p) = dir_path {
+            timeout(Duration::from_secs(5), client.cwd(p))
+                .await
+                .map_err(|_| "CWD timed out".to_string())?
+                .map_err(|e| ftp_failure("CWD failed", e))?;
+        }
+        // Key the cache by the resolved directory so relative paths can't collide
+        let cwd = client.pwd().await.ok();
+        if dir_path.is_some() && cwd.is_some() {
+            state.set_working_dir(cwd.clone());
+        }
+        let cache_key = cwd.map(|dir| format!("{}{}", connection, dir));
+        if let Some(hit) = cache_key
+            .as_deref()
+            .and_then(|k| state.listing_cache.get(k))
+        {
+            return Ok(hit);
+        }
+        let lines = timeout(state.policy().read_timeout(), client.list_lines(&dialect))
+            .await
+            .map_err(|_| data_connection_blocked("LIST", "timed out"))?
+            .map_err(|e| data_command_error("LIST", e))?;
+        let entries: Vec<RemoteFileEntry> = lines
+            .iter()
+            .filter_map(|l| parse_list_line(l, &dialect))
+            .collect();
+        if let Some(key) = cache_key {
+            state.listing_cache.insert(key, entries.clone());
+        }
+        Ok(entries)
+    })
+}
+
+#[tauri::command]
+pub async fn list_remote_directory(
+    state: State<'_, FtpState>,
+    path: Option<String>,
+    sort: Option<SortSpec>,
+    extensions: Option<Vec<String>>,
+    dirs_only: Option<bool>,
+) -> Result<Vec<RemoteFileEntry>, String> {
+    let state = state.inner();
+    let mut entries =
+        with_reconnect(state, || list_remote_directory_inner(state, path.clone())).await?;
+    if let Some(extensions) = extensions {
+        crate::sort::filter_extensions(&mut entries, &extensions);
+    }
+    // For folder pickers
+    if dirs_only.unwrap_or(false) {
+        entries.retain(|e| e.is_dir);
+    }
+    crate::sort::sort_entries(&mut entries, sort.unwrap_or_default());
+    Ok(entries)
+}
+
+async fn get_remote_pwd_inner(state: &FtpState) -> Result<String, String> {
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.pwd())
+            .await
+            .map_err(|_| "PWD timed out".to_string())?
+            .map_err(|e| ftp_failure("PWD failed", e))
+    })
+}
+
+#[tauri::command]
+pub async fn get_remote_pwd(state: State<'_, FtpState>) -> Result<String, String> {
+    let state = state.inner();
+    with_reconnect(state, || get_remote_pwd_inner(state)).await
+}
+
+async fn remote_cd_inner(state: &FtpState, path: Option<String>) -> Result<String, String> {
+    with_active_client!(state, |client| {
+        match &path {
+            Some(path) => timeout(Duration::from_secs(5), client.cwd(path))
+                .await
+                .map_err(|_| "CWD timed out".to_string())?
+                .map_err(|e| ftp_failure("CWD failed", e))?,
+            None => timeout(Duration::from_secs(5), client.cdup())
+                .await
+                .map_err(|_| "CDUP timed out".to_string())?
+                .map_err(|e| ftp_failure("CDUP failed", e))?,
+        }
+        let dir = timeout(Duration::from_secs(5), client.pwd())
+            .await
+            .map_err(|_| "PWD timed out".to_string())?
+            .map_err(|e| ftp_failure("PWD failed", e))?;
+        state.set_working_dir(Some(dir.clone()));
+        Ok(dir)
+    })
+}
+
+/// Changes the session's working directory and returns the new one as the
+/// server reports it; `path` may be relative to the current directory.
+#[tauri::command]
+pub async fn remote_cd(state: State<'_, FtpState>, path: String) -> Result<String, String> {
+    let state = state.inner();
+    with_reconnect(state, || remote_cd_inner(state, Some(path.clone()))).await
+}
+
+/// Moves the session's working directory to its parent and returns it.
+#[tauri::command]
+pub async fn remote_cd_up(state: State<'_, FtpState>) -> Result<String, String> {
+    let state = state.inner();
+    with_reconnect(state, || remote_cd_inner(state, None)).await
+}
+
+/// The session's working directory; the counterpart of `remote_cd`.
+#[tauri::command]
+pub async fn remote_pwd(state: State<'_, FtpState>) -> Result<String, String> {
+    let state = state.inner();
+    with_reconnect(state, || get_remote_pwd_inner(state)).await
+}
+
+/// Runs the transfer `op`, then switches the session back to binary if it left
+/// it in ASCII, whether it succeeded, failed or was aborted. Recursive
+/// transfers, ranged reads and copies expect binary and don't send TYPE.
+async fn restoring_binary_type<T>(
+    state: &FtpState,
+    op: impl std::future::Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    let result = op.await;
+    if state.ascii_type.load(Ordering::Relaxed) {
+        if let Err(e) = set_binary_type(state).await {
+            tracing::warn!(error = %e, "could not switch the session back to binary");
+        }
+    }
+    result
+}
+
+async fn set_binary_type(state: &FtpState) -> Result<(), String> {
+    with_active_client!(state, |client| {
+        timeout(
+            Duration::from_secs(5),
+            client.transfer_type(FileType::Binary),
+        )
+        .await
+        .map_err(|_| "TYPE timed out".to_string())?
+        .map_err(|e| ftp_failure("TYPE failed", e))?;
+        state.ascii_type.store(false, Ordering::Relaxed);
+        Ok(())
+    })
+}
+
+#[tracing::instrument(skip(window, state, local_path))]
+async fn download_remote_file_inner<R: Runtime>(
+    window: &Window<R>,
+    state: &FtpState,
+    remote_name: String,
+    local_path: String,
+    transfer_type: TransferType,
+) -> Result<String, String> {
+    restoring_binary_type(
+        state,
+        retr_to_file(window, state, remote_name, local_path, transfer_type),
+    )
+    .await
+}
+
+async fn retr_to_file<R: Runtime>(
+    window: &Window<R>,
+    state: &FtpState,
+    remote_name: String,
+    local_path: String,
+    transfer_type: TransferType,
+) -> Result<String, String> {
+    // Generate a unique ID for this transfer
+    let transfer_id = format!("dl-{}", uuid::Uuid::new_v4());
+    let registry = window.state::<TransferRegistry>();
+    let _active = registry.track(&transfer_id);
+
+    // Get file size for progress bar
+    let _size = {
+        // We try to get size from LIST or just use 0 if unknown
+        // For simplicity, we'll try MDTM or just use a default
+        0 // Placeholder if we can't get it easily without a separate call
+    };
+
+    with_active_client!(state, |client| {
+        // Try to get size
+        let total_size = timeout(Duration::from_secs(5), client.size(&remote_name))
+            .await
+            .map_err(|_| "SIZE timed out".to_string())?
+            .unwrap_or(0) as u64;
+        crate::fs_commands::ensure_free_space(&local_path, total_size)?;
+
+        let file_type = transfer_type.file_type(window, &remote_name);
+        let ascii = matches!(file_type, FileType::Ascii(_));
+        timeout(Duration::from_secs(5), client.transfer_type(file_type))
+            .await
+            .map_err(|_| "TYPE timed out".to_string())?
+            .map_err(|e| ftp_failure("TYPE failed", e))?;
+        state.ascii_type.store(ascii, Ordering::Relaxed);
+
+        let mut stream = timeout(Duration::from_secs(10), client.retr_as_stream(&remote_name))
+            .await
+            .map_err(|_| data_connection_blocked("Download", "timed out"))?
+            .map_err(|e| data_command_error("Download", e))?;
+
+        let mut file = tokio::fs::File::create(&local_path)
+            .await
+            .map_err(|e| format!("Capture failed: {}", e))?;
+
+        let mut buffer = vec![0u8; crate::config::buffer_size(window)];
+        let mut downloaded = 0u64;
+        let throttle = ProgressThrottle::default();
+        state.data_abort_requested.store(false, Ordering::SeqCst);
+        state.data_transfer_active.store(true, Ordering::SeqCst);
+        let _busy = FlagGuard(&state.data_transfer_active);
+
+        loop {
+            let read =
+                unless_aborted(state, &registry, &transfer_id, stream.read(&mut buffer)).await;
+            let Some(read) = read else {
+                let _ = timeout(Duration::from_secs(10), client.abort(stream)).await;
+                drop(file);
+                let _ = tokio::fs::remove_file(&local_path).await;
+                let _ = window.emit(
+                    "transfer-progress",
+                    TransferProgress {
+                        transfer_id: transfer_id.clone(),
+                        filename: remote_name.clone(),
+                        progress: downloaded,
+                        total: total_size,
+                        status: "cancelled".into(),
+                        batch_id: None,
+                    },
+                );
+                return Err(format!("Download of {} was aborted", remote_name));
+            };
+            let n = read.map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buffer[..n])
+                .await
+                .map_err(|e| e.to_string())?;
+            downloaded += n as u64;
+
+            // Emit progress
+            if total_size > 0 && throttle.ready() {
+                let _ = window.emit(
+                    "transfer-progress",
+                    TransferProgress {
+                        transfer_id: transfer_id.clone(),
+                        filename: remote_name.clone(),
+                        progress: downloaded,
+                        total: total_size,
+                        status: "downloading".into(),
+                        batch_id: None,
+                    },
+                );
+            }
+        }
+
+        timeout(Duration::from_secs(10), client.finalize_retr_stream(stream))
+            .await
+            .map_err(|_| "Finalize timed out".to_string())?
+            .map_err(|e| ftp_failure("Finalize failed", e))?;
+
+        // Final emit
+        let _ = window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: transfer_id.clone(),
+                filename: remote_name.clone(),
+                progress: downloaded,
+                total: total_size,
+                status: "complete".into(),
+                batch_id: None,
+            },
+        );
+
+        tracing::info!(bytes = downloaded, "download complete");
+        Ok(format!("Downloaded {}", remote_name))
+    })
+}
+
+#[tauri::command]
+pub async fn download_remote_file<R: Runtime>(
+    window: Window<R>,
+    state: State<'_, FtpState>,
+    remote_name: String,
+    local_path: String,
+    transfer_type: Option<TransferType>,
+) -> Result<String, String> {
+    let state = state.inner();
+    let transfer_type = transfer_type.unwrap_or_default();
+    with_reconnect(state, || {
+        download_remote_file_inner(
+            &window,
+            state,
+            remote_name.clone(),
+            local_path.clone(),
+            transfer_type,
+        )
+    })
+    .await
+}
+
+/// Downloads `remote_name` into the configured default download directory
+/// (or the home directory), creating it if needed.
+#[tauri::command]
+pub async fn download_remote_file_default(
+    app: AppHandle,
+    window: Window,
+    state: State<'_, FtpState>,
+    remote_name: String,
+) -> Result<String, String> {
+    let dir = crate::config::load_config(app)?
+        .default_download_dir
+        .filter(|d| !d.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(dirs::home_dir)
+        .ok_or_else(|| "Could not determine a download directory".to_string())?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let file_name = remote_name
+        .rsplit('/')
+        .next()
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| format!("No file name in {}", remote_name))?;
+    let local_path = dir.join(file_name).to_string_lossy().to_string();
+
+    let state = state.inner();
+    with_reconnect(state, || {
+        download_remote_file_inner(
+            &window,
+            state,
+            remote_name.clone(),
+            local_path.clone(),
+            TransferType::Auto,
+        )
+    })
+    .await
+}
+
+#[tracing::instrument(skip(window, state))]
+async fn upload_file_inner<R: Runtime>(
+    window: &Window<R>,
+    state: &FtpState,
+    local_path: String,
+    remote_name: String,
+    resume: bool,
+    transfer_type: TransferType,
+) -> Result<String, String> {
+    let file = tokio::fs::File::open(&local_path)
+        .await
+        .map_err(|e| format!("Read failed: {}", e))?;
+    let metadata = file.metadata().await.map_err(|e| e.to_string())?;
+    let file_type = transfer_type.file_type(window, &local_path);
+    upload_from_reader(
+        window,
+        state,
+        file,
+        metadata.len(),
+        remote_name,
+        resume,
+        file_type,
+    )
+    .await
+}
+
+/// Streams `total_size` bytes from `reader` to `remote_name`, the upload path
+/// shared by files on disk and in-memory content. With `resume`, a shorter
+/// remote file is appended to from the matching offset of `reader`.
+async fn upload_from_reader<R>(
+    window: &Window<impl Runtime>,
+    state: &FtpState,
+    reader: R,
+    total_size: u64,
+    remote_name: String,
+    resume: bool,
+    file_type: FileType,
+) -> Result<String, String>
+where
+    R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin + Send,
+{
+    restoring_binary_type(
+        state,
+        stor_from_reader(
+            window,
+            state,
+            reader,
+            total_size,
+            remote_name,
+            resume,
+            file_type,
+        ),
+    )
+    .await
+}
+
+async fn stor_from_reader<R>(
+    window: &Window<impl Runtime>,
+    state: &FtpState,
+    mut reader: R,
+    total_size: u64,
+    remote_name: String,
+    resume: bool,
+    file_type: FileType,
+) -> Result<String, String>
+where
+    R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin + Send,
+{
+    let transfer_id = format!("ul-{}", uuid::Uuid::new_v4());
+    let registry = window.state::<TransferRegistry>();
+    let _active = registry.track(&transfer_id);
+
+    with_active_client!(state, |client| {
+        // Let the UI draw a determinate bar before the first chunk goes out; an
+        // empty file only gets its "complete" event
+        if total_size > 0 {
+            let _ = window.emit(
+                "transfer-progress",
+                TransferProgress {
+                    transfer_id: transfer_id.clone(),
+                    filename: remote_name.clone(),
+                    progress: 0,
+                    total: total_size,
+                    status: "starting".into(),
+                    batch_id: None,
+                },
+            );
+        }
+
+        let ascii = matches!(file_type, FileType::Ascii(_));
+        timeout(Duration::from_secs(5), client.transfer_type(file_type))
+            .await
+            .map_err(|_| "TYPE timed out".to_string())?
+            .map_err(|e| ftp_failure("TYPE failed", e))?;
+        state.ascii_type.store(ascii, Ordering::Relaxed);
+
+        // A partial remote file smaller than the local one is continued where it stopped;
+        // without SIZE support we can't tell, so the upload starts over. ASCII
+        // conversion changes sizes, so text uploads always start over.
+        let mut offset = 0u64;
+        if resume && !ascii {
+            if let Ok(Ok(size)) = timeout(Duration::from_secs(5), client.size(&remote_name)).await {
+                if (size as u64) <= total_size {
+                    offset = size as u64;
+                }
+            }
+        }
+
+        let mut stream = None;
+        if offset > 0 {
+            match timeout(
+                Duration::from_secs(10),
+                client.append_with_stream(&remote_name),
+            )
+            .await
+            {
+                Ok(Ok(s)) => {
+                    reader
+                        .seek(std::io::SeekFrom::Start(offset))
+                        .await
+                        .map_err(|e| format!("Read failed: {}", e))?;
+                    stream = Some(s);
+                }
+                _ => {
+                    tracing::warn!("APPE not available, restarting upload from zero");
+                    offset = 0;
+                }
+            }
+        }
+        let mut stream = match stream {
+            Some(s) => s,
+            None => timeout(
+                Duration::from_secs(10),
+                client.put_with_stream(&remote_name),
+            )
+            .await
+            .map_err(|_| data_connection_blocked("Upload", "timed out"))?
+            .map_err(|e| data_command_error("Upload", e))?,
+        };
+
+        let mut buffer = vec![0u8; crate::config::buffer_size(window)];
+        let mut uploaded = offset;
+        let throttle = ProgressThrottle::default();
+        state.data_abort_requested.store(false, Ordering::SeqCst);
+        state.data_transfer_active.store(true, Ordering::SeqCst);
+        let _busy = FlagGuard(&state.data_transfer_active);
+
+        loop {
+            let n = reader.read(&mut buffer).await.map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            let written = unless_aborted(
+                state,
+                &registry,
+                &transfer_id,
+                stream.write_all(&buffer[..n]),
+            )
+            .await;
+            let Some(written) = written else {
+                // The partial remote file is kept so the upload can be resumed
+                let _ = timeout(Duration::from_secs(10), client.abort(stream)).await;
+                let _ = window.emit(
+                    "transfer-progress",
+                    TransferProgress {
+                        transfer_id: transfer_id.clone(),
+                        filename: remote_name.clone(),
+                        progress: uploaded,
+                        total: total_size,
+                        status: "cancelled".into(),
+                        batch_id: None,
+                    },
+                );
+                return Err(format!("Upload of {} was aborted", remote_name));
+            };
+            written.map_err(|e| format!("Upload failed: {}", e))?;
+            uploaded += n as u64;
+
+            if throttle.ready() {
+                let _ = window.emit(
+                    "transfer-progress",
+                    TransferProgress {
+                        transfer_id: transfer_id.clone(),
+                        filename: remote_name.clone(),
+                        progress: uploaded,
+                        total: total_size,
+                        status: "uploading".into(),
+                        batch_id: None,
+                    },
+                );
+            }
+        }
+
+        timeout(Duration::from_secs(10), client.finalize_put_stream(stream))
+            .await
+            .map_err(|_| "Finalize timed out".to_string())?
+            .map_err(|e| ftp_failure("Finalize failed", e))?;
+
+        // Some servers drop a STOR that carried no data instead of creating an
+        // empty file; a 550 on SIZE means that happened. Other SIZE errors
+        // (e.g. no SIZE support) can't tell, so those pass.
+        if total_size == 0 {
+            if let Ok(Err(e)) = timeout(Duration::from_secs(5), client.size(&remote_name)).await {
+                if is_file_unavailable(&e) {
+                    return Err(format!(
+                        "Server did not create the empty file {}: {}",
+                        remote_name, e
+                    ));
+                }
+            }
+        }
+
+        if offset > 0 {
+            let remote_size = timeout(Duration::from_secs(5), client.size(&remote_name))
+                .await
+                .map_err(|_| "SIZE timed out".to_string())?
+                .map_err(|e| ftp_failure("Could not verify resumed upload", e))?
+                as u64;
+            if remote_size != total_size {
+                return Err(format!(
+                    "Resumed upload is {} bytes on the server, expected {}",
+                    remote_size, total_size
+                ));
+            }
+        }
+
+        let _ = window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: transfer_id.clone(),
+                filename: remote_name.clone(),
+                progress: total_size,
+                total: total_size,
+                status: "complete".into(),
+                batch_id: None,
+            },
+        );
+
+        tracing::info!(bytes = total_size, resumed_from = offset, "upload complete");
+        Ok(format!("Uploaded {}", remote_name))
+    })
+}
+
+#[tauri::command]
+pub async fn upload_file<R: Runtime>(
+    window: Window<R>,
+    state: State<'_, FtpState>,
+    local_path: String,
+    remote_name: String,
+    resume: Option<bool>,
+    transfer_type: Option<TransferType>,
+) -> Result<String, String> {
+    let state = state.inner();
+    let resume = resume.unwrap_or(false);
+    let transfer_type = transfer_type.unwrap_or_default();
+    let result = with_reconnect_once(
+        state,
+        upload_file_inner(
+            &window,
+            state,
+            local_path.clone(),
+            remote_name.clone(),
+            resume,
+            transfer_type,
+        ),
+    )
+    .await;
+    invalidate_listings(state, &[&remote_name]).await;
+    result
+}
+
+/// Uploads content the frontend has in memory, such as a new or edited text
+/// file, to `remote_name` without going through a local file.
+#[tauri::command]
+pub async fn upload_bytes(
+    window: Window,
+    state: State<'_, FtpState>,
+    remote_name: String,
+    data: Vec<u8>,
+    transfer_type: Option<TransferType>,
+) -> Result<String, String> {
+    let state = state.inner();
+    let file_type = transfer_type
+        .unwrap_or_default()
+        .file_type(&window, &remote_name);
+    let result = with_reconnect_once(
+        state,
+        upload_from_reader(
+            &window,
+            state,
+            std::io::Cursor::new(data.as_slice()),
+            data.len() as u64,
+            remote_name.clone(),
+            false,
+            file_type,
+        ),
+    )
+    .await;
+    invalidate_listings(state, &[&remote_name]).await;
+    result
+}
+
+/// Compression applied by `upload_file_compressed`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+
+    fn encoder(self) -> Result<Compressor, String> {
+        Ok(match self {
+            Compression::Gzip => Compressor::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            )),
+            Compression::Zstd => Compressor::Zstd(
+                zstd::stream::write::Encoder::new(Vec::new(), 0)
+                    .map_err(|e| format!("Compression failed: {}", e))?,
+            ),
+        })
+    }
+}
+
+/// Encoder buffering its output in memory; the caller drains it into the data
+/// connection after every chunk so memory stays bounded by the chunk size.
+enum Compressor {
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl Compressor {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+        match self {
+            Compressor::Gzip(e) => e.write_all(data),
+            Compressor::Zstd(e) => e.write_all(data),
+        }
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        match self {
+            Compressor::Gzip(e) => std::mem::take(e.get_mut()),
+            Compressor::Zstd(e) => std::mem::take(e.get_mut()),
+        }
+    }
+
+    /// Writes the trailer; `take_output` then returns the last bytes.
+    fn finish(&mut self) -> std::io::Result<()> {
+        match self {
+            Compressor::Gzip(e) => e.try_finish(),
+            Compressor::Zstd(e) => e.do_finish(),
+        }
+    }
+}
+
+async fn upload_compressed_inner(
+    window: &Window,
+    state: &FtpState,
+    local_path: String,
+    remote_name: String,
+    algo: Compression,
+) -> Result<String, String> {
+    let mut file = tokio::fs::File::open(&local_path)
+        .await
+        .map_err(|e| format!("Read failed: {}", e))?;
+    let total_size = file.metadata().await.map_err(|e| e.to_string())?.len();
+    let mut encoder = algo.encoder()?;
+    let transfer_id = format!("ul-{}", uuid::Uuid::new_v4());
+    let registry = window.state::<TransferRegistry>();
+    let _active = registry.track(&transfer_id);
+    let progress = |read: u64, status: &str| {
+        let _ = window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: transfer_id.clone(),
+                filename: remote_name.clone(),
+                progress: read,
+                total: total_size,
+                status: status.into(),
+                batch_id: None,
+            },
+        );
+    };
+
+    with_active_client!(state, |client| {
+        progress(0, "starting");
+        // Compressed data must not go through ASCII line-ending conversion
+        if state.ascii_type.load(Ordering::Relaxed) {
+            timeout(
+                Duration::from_secs(5),
+                client.transfer_type(FileType::Binary),
+            )
+            .await
+            .map_err(|_| "TYPE timed out".to_string())?
+            .map_err(|e| ftp_failure("TYPE failed", e))?;
+            state.ascii_type.store(false, Ordering::Relaxed);
+        }
+        let mut stream = timeout(
+            Duration::from_secs(10),
+            client.put_with_stream(&remote_name),
+        )
+        .await
+        .map_err(|_| data_connection_blocked("Upload", "timed out"))?
+        .map_err(|e| data_command_error("Upload", e))?;
+
+        let mut buffer = vec![0u8; crate::config::buffer_size(window)];
+        let mut read = 0u64;
+        let mut sent = 0u64;
+        let throttle = ProgressThrottle::default();
+        state.data_abort_requested.store(false, Ordering::SeqCst);
+        state.data_transfer_active.store(true, Ordering::SeqCst);
+        let _busy = FlagGuard(&state.data_transfer_active);
+
+        loop {
+            let n = file.read(&mut buffer).await.map_err(|e| e.to_string())?;
+            let compressed = if n == 0 {
+                encoder.finish()
+            } else {
+                encoder.write(&buffer[..n])
+            };
+            compressed.map_err(|e| format!("Compression failed: {}", e))?;
+            let output = encoder.take_output();
+            let written =
+                unless_aborted(state, &registry, &transfer_id, stream.write_all(&output)).await;
+            let Some(written) = written else {
+                // A truncated archive is useless, so it isn't kept for resuming
+                let _ = timeout(Duration::from_secs(10), client.abort(stream)).await;
+                let _ = client.rm(&remote_name).await;
+                progress(read, "cancelled");
+                return Err(format!("Upload of {} was aborted", remote_name));
+            };
+            written.map_err(|e| format!("Upload failed: {}", e))?;
+            sent += output.len() as u64;
+            if n == 0 {
+                break;
+            }
+            read += n as u64;
+            if throttle.ready() {
+                progress(read, "uploading");
+            }
+        }
+
+        timeout(Duration::from_secs(10), client.finalize_put_stream(stream))
+            .await
+            .map_err(|_| "Finalize timed out".to_string())?
+            .map_err(|e| ftp_failure("Finalize failed", e))?;
+
+        progress(total_size, "complete");
+        tracing::info!(
+            bytes = total_size,
+            compressed = sent,
+            "compressed upload complete"
+        );
+        Ok(format!("Uploaded {}", remote_name))
+    })
+}
+
+/// Uploads `local_path` compressed with `algo`, appending `.gz` or `.zst` to
+/// `remote_name`. The server stores the compressed file; nothing unpacks it
+/// remotely. Progress counts bytes read from the local file.
+#[tauri::command]
+pub async fn upload_file_compressed(
+    window: Window,
     state: State<'_, FtpState>,
-    remote_name: String,
     local_path: String,
+    remote_name: String,
+    algo: Compression,
 ) -> Result<String, String> {
-    // Generate a unique ID for this transfer
-    let transfer_id = format!("dl-{}", uuid::Uuid::new_v4());
+    let state = state.inner();
+    let remote_name = format!("{}.{}", remote_name, algo.extension());
+    let result = with_reconnect_once(
+        state,
+        upload_compressed_inner(
+            &window,
+            state,
+            local_path.clone(),
+            remote_name.clone(),
+            algo,
+        ),
+    )
+    .await;
+    invalidate_listings(state, &[&remote_name]).await;
+    result
+}
+
+/// Aborts the running single-file download or upload with `ABOR`, keeping the
+/// control connection open. The transfer emits a `cancelled` progress event and
+/// removes its partial local file.
+#[tauri::command]
+pub async fn abort_data_transfer(state: State<'_, FtpState>) -> Result<(), String> {
+    if !state.data_transfer_active.load(Ordering::SeqCst) {
+        return Err("No data transfer in progress".into());
+    }
+    state.data_abort_requested.store(true, Ordering::SeqCst);
+    state.data_abort.notify_waiters();
+    Ok(())
+}
+
+async fn delete_remote_file_inner(state: &FtpState, path: String) -> Result<String, String> {
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.rm(&path))
+            .await
+            .map_err(|_| "Delete timed out".to_string())?
+            .map_err(|e| ftp_failure("Delete failed", e))?;
+        Ok(format!("Deleted file: {}", path))
+    })
+}
+
+#[tauri::command]
+pub async fn delete_remote_file(
+    state: State<'_, FtpState>,
+    path: String,
+) -> Result<String, String> {
+    let state = state.inner();
+    let result = with_reconnect_once(state, delete_remote_file_inner(state, path.clone())).await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+async fn delete_remote_dir_inner(state: &FtpState, path: String) -> Result<String, String> {
+    // Note: rmdir usually only works if the directory is empty.
+    // For recursive deletion, a more complex approach is needed
+    // (listing contents and deleting recursively) but this is a starting point.
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.rmdir(&path))
+            .await
+            .map_err(|_| "Delete timed out".to_string())?
+            .map_err(|e| ftp_failure("Delete failed (directory must be empty)", e))?;
+        Ok(format!("Deleted directory: {}", path))
+    })
+}
+
+#[tauri::command]
+pub async fn delete_remote_dir(state: State<'_, FtpState>, path: String) -> Result<String, String> {
+    let state = state.inner();
+    let result = with_reconnect_once(state, delete_remote_dir_inner(state, path.clone())).await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+async fn rename_remote_file_inner(
+    state: &FtpState,
+    old_path: String,
+    new_path: String,
+) -> Result<String, String> {
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.rename(&old_path, &new_path))
+            .await
+            .map_err(|_| "Rename timed out".to_string())?
+            .map_err(|e| ftp_failure("Rename failed", e))?;
+        Ok(format!("Renamed {} to {}", old_path, new_path))
+    })
+}
+
+#[tauri::command]
+pub async fn rename_remote_file(
+    state: State<'_, FtpState>,
+    old_path: String,
+    new_path: String,
+) -> Result<String, String> {
+    let state = state.inner();
+    let result = with_reconnect_once(
+        state,
+        rename_remote_file_inner(state, old_path.clone(), new_path.clone()),
+    )
+    .await;
+    invalidate_listings(state, &[&old_path, &new_path]).await;
+    result
+}
+
+async fn create_remote_dir_inner(state: &FtpState, path: String) -> Result<String, String> {
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.mkdir(&path))
+            .await
+            .map_err(|_| "Mkdir timed out".to_string())?
+            .map_err(|e| ftp_failure("Mkdir failed", e))?;
+        Ok(format!("Created directory: {}", path))
+    })
+}
+
+#[tauri::command]
+pub async fn create_remote_dir(state: State<'_, FtpState>, path: String) -> Result<String, String> {
+    let state = state.inner();
+    let result = with_reconnect_once(state, create_remote_dir_inner(state, path.clone())).await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+/// Cumulative prefixes of a remote path: `"/a/b/c"` -> `["/a", "/a/b", "/a/b/c"]`.
+fn path_prefixes(path: &str) -> Vec<String> {
+    let mut current = if path.starts_with('/') {
+        String::from("/")
+    } else {
+        String::new()
+    };
+    let mut prefixes = Vec::new();
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        if !current.is_empty() && !current.ends_with('/') {
+            current.push('/');
+        }
+        current.push_str(component);
+        prefixes.push(current.clone());
+    }
+    prefixes
+}
+
+/// MKD answers 550 when the directory already exists, which `mkdir -p` treats as success.
+fn is_file_unavailable(err: &FtpError) -> bool {
+    matches!(err, FtpError::UnexpectedResponse(resp) if resp.status == Status::FileUnavailable)
+}
+
+async fn create_remote_dir_all_inner(state: &FtpState, path: String) -> Result<String, String> {
+    let prefixes = path_prefixes(&path);
+    if prefixes.is_empty() {
+        return Err("Empty directory path".into());
+    }
+
+    with_active_client!(state, |client| {
+        for dir in &prefixes {
+            match timeout(Duration::from_secs(5), client.mkdir(dir))
+                .await
+                .map_err(|_| "Mkdir timed out".to_string())?
+            {
+                Ok(()) => {}
+                Err(e) if is_file_unavailable(&e) => {}
+                Err(e) => return Err(ftp_failure(format!("Mkdir failed for {}", dir), e)),
+            }
+        }
+        Ok(format!("Created directory: {}", path))
+    })
+}
+
+/// Creates `path` and any missing parents, like `mkdir -p`.
+#[tauri::command]
+pub async fn create_remote_dir_all(
+    state: State<'_, FtpState>,
+    path: String,
+) -> Result<String, String> {
+    let state = state.inner();
+    let result = with_reconnect(state, || create_remote_dir_all_inner(state, path.clone())).await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+/// Result of a recursive transfer that may stop to ask before moving a lot of data.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FolderTransferOutcome {
+    Completed {
+        message: String,
+        downloaded_bytes: u64,
+        files_ok: u64,
+        /// Files and folders that failed and were skipped; the rest were downloaded.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        files_failed: Vec<FailedFile>,
+        /// Directory symlinks that were not followed, see `SymlinkWalk`.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        skipped_symlinks: Vec<String>,
+    },
+    /// Nothing was transferred; retry with `confirmed: true` to proceed.
+    ConfirmationRequired {
+        total_bytes: u64,
+        threshold_bytes: u64,
+    },
+    /// Stopped by `cancel_folder_download`; files already downloaded are kept.
+    Cancelled { message: String, files_done: u64 },
+}
+
+/// Joins a remote directory and a child path with single `/` separators. Empty
+/// segments (doubled or trailing slashes) are dropped, a root `base` of `/`
+/// yields `/child`, and an empty `child` gives back the normalized `base`.
+pub fn join_remote_path(base: &str, child: &str) -> String {
+    let joined = base
+        .split('/')
+        .chain(child.split('/'))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    if base.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+fn absolute_remote_path(cwd: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        join_remote_path(path, "")
+    } else {
+        join_remote_path(cwd, path)
+    }
+}
+
+async fn download_remote_folder_inner(
+    window: &Window,
+    state: &FtpState,
+    remote_dir: String,
+    local_dir: String,
+    confirm_threshold: u64,
+    filter: &PathFilter,
+    follow_symlinks: bool,
+) -> Result<FolderTransferOutcome, String> {
+    let local_path = std::path::Path::new(&local_dir);
+
+    with_active_client!(state, |client| {
+        let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let absolute_remote = absolute_remote_path(&orig_cwd, &remote_dir);
+
+        // Measured from LIST sizes up front: the confirmation check and the batch total
+        let total = client
+            .size_tree(
+                &absolute_remote,
+                "",
+                filter,
+                &SymlinkWalk::new(follow_symlinks),
+                &state.dialect(),
+            )
+            .await;
+        let _ = client.cwd(&orig_cwd).await;
+        let total = total?;
+        if confirm_threshold > 0 && total > confirm_threshold {
+            return Ok(FolderTransferOutcome::ConfirmationRequired {
+                total_bytes: total,
+                threshold_bytes: confirm_threshold,
+            });
+        }
+
+        let batch = BatchProgress::start(window, "dl", &remote_dir, total);
+        let links = SymlinkWalk::new(follow_symlinks);
+        let result = client
+            .download_tree(
+                &absolute_remote,
+                "",
+                local_path,
+                filter,
+                &links,
+                &batch,
+                &state.dialect(),
+            )
+            .await;
+
+        let _ = client.cwd(&orig_cwd).await;
+
+        let bytes = result?;
+        if batch.is_cancelled() {
+            let files_done = batch.files_done();
+            return Ok(FolderTransferOutcome::Cancelled {
+                message: format!(
+                    "Download of '{}' cancelled after {} files ({} bytes)",
+                    remote_dir, files_done, bytes
+                ),
+                files_done,
+            });
+        }
+        let files_ok = batch.files_done();
+        let files_failed = batch.take_failures();
+        batch.finish();
+        let skipped_symlinks = links.into_skipped();
+        let mut message = format!("Downloaded folder '{}' ({} bytes)", remote_dir, bytes);
+        if !files_failed.is_empty() {
+            message.push_str(&format!(", {} failed", files_failed.len()));
+        }
+        if !skipped_symlinks.is_empty() {
+            message.push_str(&format!(
+                ", skipped {} symlinked folder(s)",
+                skipped_symlinks.len()
+            ));
+        }
+        Ok(FolderTransferOutcome::Completed {
+            message,
+            downloaded_bytes: bytes,
+            files_ok,
+            files_failed,
+            skipped_symlinks,
+        })
+    })
+}
+
+/// Downloads a remote folder recursively. Unless `confirmed`, folders larger than
+/// the configured `confirm_threshold_bytes` are only measured and reported back.
+/// `include`/`exclude` are glob patterns, see `PathFilter`. Symlinked folders
+/// are skipped and listed in the outcome unless `follow_symlinks` is set.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_remote_folder(
+    window: Window,
+    state: State<'_, FtpState>,
+    remote_dir: String,
+    local_dir: String,
+    confirmed: Option<bool>,
+    include: Option<String>,
+    exclude: Option<String>,
+    follow_symlinks: Option<bool>,
+) -> Result<FolderTransferOutcome, String> {
+    let state = state.inner();
+    let follow_symlinks = follow_symlinks.unwrap_or(false);
+    let filter = PathFilter::new(include.as_deref(), exclude.as_deref())?;
+    let confirm_threshold = if confirmed.unwrap_or(false) {
+        0
+    } else {
+        crate::config::confirm_threshold_bytes(&window)
+    };
+    with_reconnect(state, || {
+        download_remote_folder_inner(
+            &window,
+            state,
+            remote_dir.clone(),
+            local_dir.clone(),
+            confirm_threshold,
+            &filter,
+            follow_symlinks,
+        )
+    })
+    .await
+}
+
+/// Stops a running `download_remote_folder` before its next file or subfolder.
+/// `batch_id` is the `transfer_id` of the folder's batch progress events.
+#[tauri::command]
+pub fn cancel_folder_download(
+    registry: State<'_, TransferRegistry>,
+    batch_id: String,
+) -> Result<(), String> {
+    if registry.cancel(&batch_id) {
+        Ok(())
+    } else {
+        Err(format!("No folder download with id {}", batch_id))
+    }
+}
+
+async fn get_remote_dir_size_inner(state: &FtpState, path: String) -> Result<u64, String> {
+    with_active_client!(state, |client| {
+        let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let filter = PathFilter::default();
+        let result = client
+            .size_tree(
+                &absolute_remote_path(&orig_cwd, &path),
+                "",
+                &filter,
+                &SymlinkWalk::default(),
+                &state.dialect(),
+            )
+            .await;
+        let _ = client.cwd(&orig_cwd).await;
+        result
+    })
+}
+
+/// Total size in bytes of all files under a remote directory.
+#[tauri::command]
+pub async fn get_remote_dir_size(state: State<'_, FtpState>, path: String) -> Result<u64, String> {
+    let state = state.inner();
+    with_reconnect(state, || get_remote_dir_size_inner(state, path.clone())).await
+}
+
+#[derive(Serialize)]
+pub struct PrunedEntry {
+    pub path: String,
+    pub age_days: i64,
+    pub deleted: bool,
+}
+
+/// Include/exclude globs for recursive transfers, matched against each entry's
+/// path relative to the folder being transferred (e.g. `photos/2024/a.jpg`).
+///
+/// Exclude wins: an excluded directory is skipped with everything under it.
+/// Include only narrows files, so directories are always descended into.
+/// `*` also matches `/`, so `*.jpg` selects JPEGs at any depth.
+#[derive(Default)]
+pub struct PathFilter {
+    include: Option<GlobMatcher>,
+    exclude: Option<GlobMatcher>,
+}
+
+impl PathFilter {
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self, String> {
+        let compile = |pattern: Option<&str>| {
+            pattern
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    Glob::new(p)
+                        .map(|g| g.compile_matcher())
+                        .map_err(|e| format!("Invalid pattern {}: {}", p, e))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn allows(&self, rel_path: &str, is_dir: bool) -> bool {
+        if self.exclude.as_ref().is_some_and(|m| m.is_match(rel_path)) {
+            return false;
+        }
+        match &self.include {
+            Some(include) if !is_dir => include.is_match(rel_path),
+            _ => true,
+        }
+    }
+}
+
+/// How a recursive walk treats symlinks to directories. They are skipped unless
+/// `follow` is set; followed or not, a directory already visited (by the path
+/// the server reports after `CWD`) is never entered twice, so link cycles end.
+#[derive(Default)]
+pub struct SymlinkWalk {
+    follow: bool,
+    visited: std::sync::Mutex<std::collections::HashSet<String>>,
+    skipped: std::sync::Mutex<Vec<String>>,
+}
+
+impl SymlinkWalk {
+    pub fn new(follow: bool) -> Self {
+        Self {
+            follow,
+            ..Default::default()
+        }
+    }
+
+    /// Records `real_path` as visited; false if it already was.
+    fn visit(&self, real_path: String) -> bool {
+        self.visited
+            .lock()
+            .map(|mut v| v.insert(real_path))
+            .unwrap_or(true)
+    }
+
+    fn skip(&self, remote_path: &str) {
+        tracing::info!(remote_path, "skipping directory symlink");
+        if let Ok(mut skipped) = self.skipped.lock() {
+            skipped.push(remote_path.to_string());
+        }
+    }
+
+    fn into_skipped(self) -> Vec<String> {
+        self.skipped.into_inner().unwrap_or_default()
+    }
+}
+
+fn join_relative(rel_dir: &str, name: &str) -> String {
+    if rel_dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", rel_dir, name)
+    }
+}
+
+type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+
+/// LIST of the current directory, honouring `FtpConfigPayload::remote_encoding`.
+trait ListLines {
+    fn list_lines<'a>(
+        &'a mut self,
+        dialect: &'a ListingDialect,
+    ) -> BoxFuture<'a, Result<Vec<String>, FtpError>>;
+}
+
+// suppaftp decodes LIST output as lossy UTF-8, so other encodings need the raw
+// bytes: the session opens its own passive data connection for that, secured
+// with the control connection's TLS setup on FTPS.
+macro_rules! impl_list_lines {
+    ($stream:ty) => {
+        impl ListLines for $stream {
+            fn list_lines<'a>(
+                &'a mut self,
+                dialect: &'a ListingDialect,
+            ) -> BoxFuture<'a, Result<Vec<String>, FtpError>> {
+                Box::pin(async move {
+                    let Some(encoding) = dialect.encoding else {
+                        return self.list(None).await;
+                    };
+
+                    let pasv = self.custom_command("PASV", &[Status::PassiveMode]).await?;
+                    let mut addr = parse_pasv_reply(&String::from_utf8_lossy(&pasv.body))
+                        .ok_or(FtpError::BadResponse)?;
+                    if dialect.fix_passive_nat {
+                        let control = self
+                            .get_ref()
+                            .peer_addr()
+                            .map_err(FtpError::ConnectionError)?;
+                        addr.set_ip(control.ip());
+                    }
+                    let data = tokio::net::TcpStream::connect(addr)
+                        .await
+                        .map_err(FtpError::ConnectionError)?;
+                    self.custom_command("LIST", &[Status::AboutToSend, Status::AlreadyOpen])
+                        .await?;
+
+                    let mut raw = Vec::new();
+                    match &dialect.data_tls {
+                        Some(tls) => {
+                            let mut data = tls
+                                .connector
+                                .connect(tls.server_name.clone(), data)
+                                .await
+                                .map_err(|e| FtpError::SecureError(e.to_string()))?;
+                            read_listing(&mut data, &mut raw).await?;
+                            self.finalize_retr_stream(data).await?;
+                        }
+                        None => {
+                            let mut data = data;
+                            read_listing(&mut data, &mut raw).await?;
+                            self.finalize_retr_stream(data).await?;
+                        }
+                    }
+                    Ok(decode_listing(&raw, encoding))
+                })
+            }
+        }
+    };
+}
+
+impl_list_lines!(SecureStream);
+impl_list_lines!(PlainStream);
+
+/// Reads a listing's data connection to the end. Servers that close a TLS data
+/// connection without `close_notify` still sent the whole listing.
+async fn read_listing(
+    data: &mut (impl tokio::io::AsyncRead + Unpin),
+    raw: &mut Vec<u8>,
+) -> Result<(), FtpError> {
+    match data.read_to_end(raw).await {
+        Err(e) if e.kind() != std::io::ErrorKind::UnexpectedEof => {
+            Err(FtpError::ConnectionError(e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Extracts the data address from a `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)` reply.
+fn parse_pasv_reply(reply: &str) -> Option<std::net::SocketAddr> {
+    let start = reply.find('(')?;
+    let end = reply[start..].find(')')? + start;
+    let nums: Vec<u8> = reply[start + 1..end]
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    if nums.len() != 6 {
+        return None;
+    }
+    let ip = std::net::Ipv4Addr::new(nums[0], nums[1], nums[2], nums[3]);
+    let port = u16::from(nums[4]) << 8 | u16::from(nums[5]);
+    Some((ip, port).into())
+}
+
+/// Recursive walks over a connected session.
+trait TreeOps {
+    /// Downloads `remote_dir` into `local_dir`, returning the bytes written.
+    /// `rel_dir` is `remote_dir` relative to the folder being downloaded, for `filter`.
+    /// Each file is reported as part of `batch`, and the walk stops early once
+    /// `batch` is cancelled. Files and subfolders the server or the local disk
+    /// refuse are recorded with `BatchProgress::fail` and skipped; only a lost
+    /// connection ends the walk with an error.
+    fn download_tree<'a>(
+        &'a mut self,
+        remote_dir: &'a str,
+        rel_dir: &'a str,
+        local_dir: &'a std::path::Path,
+        filter: &'a PathFilter,
+        links: &'a SymlinkWalk,
+        batch: &'a BatchProgress<'_>,
+        dialect: &'a ListingDialect,
+    ) -> BoxFuture<'a, Result<u64, String>>;
+
+    /// Sums the sizes of the files under `remote_dir` that pass `filter`, from their LIST entries.
+    fn size_tree<'a>(
+        &'a mut self,
+        remote_dir: &'a str,
+        rel_dir: &'a str,
+        filter: &'a PathFilter,
+        links: &'a SymlinkWalk,
+        dialect: &'a ListingDialect,
+    ) -> BoxFuture<'a, Result<u64, String>>;
+
+    /// Whether the symlink at `entry_remote_path` is a directory to walk into,
+    /// `Some(true)`, or a file, `Some(false)`. Directory links that `links`
+    /// doesn't follow are recorded as skipped and give `None`. Leaves the
+    /// session in `remote_dir`.
+    fn resolve_symlink<'a>(
+        &'a mut self,
+        remote_dir: &'a str,
+        entry_remote_path: &'a str,
+        links: &'a SymlinkWalk,
+    ) -> BoxFuture<'a, Result<Option<bool>, String>>;
+
+    fn prune_tree<'a>(
+        &'a mut self,
+        remote_dir: &'a str,
+        cutoff: NaiveDateTime,
+        recursive: bool,
+        dry_run: bool,
+        pruned: &'a mut Vec<PrunedEntry>,
+        dialect: &'a ListingDialect,
+    ) -> BoxFuture<'a, Result<(), String>>;
+
+    /// Collects every file under `remote_dir` into `files`, keyed by its path
+    /// relative to the walk root. `on_dir` gets each visited `rel_dir` and the running file count.
+    /// Symlinks to directories are not followed.
+    fn list_tree<'a>(
+        &'a mut self,
+        remote_dir: &'a str,
+        rel_dir: &'a str,
+        files: &'a mut BTreeMap<String, RemoteFileEntry>,
+        on_dir: &'a (dyn Fn(&str, usize) + Sync),
+        dialect: &'a ListingDialect,
+    ) -> BoxFuture<'a, Result<(), String>>;
+}
+
+// Written once and stamped out per stream type, for the same reason as `with_active_client!`
+macro_rules! impl_tree_ops {
+    ($stream:ty) => {
+        impl TreeOps for $stream {
+            fn download_tree<'a>(
+                &'a mut self,
+                remote_dir: &'a str,
+                rel_dir: &'a str,
+                local_dir: &'a std::path::Path,
+                filter: &'a PathFilter,
+                links: &'a SymlinkWalk,
+                batch: &'a BatchProgress<'_>,
+                dialect: &'a ListingDialect,
+            ) -> BoxFuture<'a, Result<u64, String>> {
+                Box::pin(async move {
+                    self.cwd(remote_dir)
+                        .await
+                        .map_err(|e| ftp_failure(format!("CWD failed to {}", remote_dir), e))?;
+                    let real_path = self.pwd().await.unwrap_or_else(|_| remote_dir.to_string());
+                    if !links.visit(real_path) {
+                        links.skip(remote_dir);
+                        return Ok(0);
+                    }
+
+                    if !local_dir.exists() {
+                        std::fs::create_dir_all(local_dir)
+                            .map_err(|e| format!("Failed to create local dir: {}", e))?;
+                    }
+
+                    let lines = self
+                        .list_lines(dialect)
+                        .await
+                        .map_err(|e| ftp_failure(format!("LIST failed in {}", remote_dir), e))?;
+
+                    let mut total_bytes = 0;
+
+                    let mut entries = Vec::new();
+                    for l in lines {
+                        if let Some(entry) = parse_list_line(&l, dialect) {
+                            entries.push(entry);
+                        }
+                    }
+
+                    for entry in entries {
+                        let entry_local_path = match join_untrusted_name(local_dir, &entry.name) {
+                            Ok(path) => path,
+                            Err(e) => {
+                                tracing::warn!(error = %e, remote_dir, "skipping listing entry");
+                                continue;
+                            }
+                        };
+                        let entry_remote_path = join_remote_path(remote_dir, &entry.name);
+                        let entry_rel_path = join_relative(rel_dir, &entry.name);
+
+                        if !filter.allows(&entry_rel_path, entry.is_dir) {
+                            continue;
+                        }
+                        if batch.is_cancelled() {
+                            break;
+                        }
+                        let is_dir = match entry.symlink_target {
+                            Some(_) => self
+                                .resolve_symlink(remote_dir, &entry_remote_path, links)
+                                .await?,
+                            None => Some(entry.is_dir),
+                        };
+                        let Some(is_dir) = is_dir else {
+                            continue;
+                        };
+                        if is_dir {
+                            match self
+                                .download_tree(
+                                    &entry_remote_path,
+                                    &entry_rel_path,
+                                    &entry_local_path,
+                                    filter,
+                                    links,
+                                    batch,
+                                    dialect,
+                                )
+                                .await
+                            {
+                                Ok(bytes) => total_bytes += bytes,
+                                Err(e) => batch.fail(
+                                    &format!("dl-{}", uuid::Uuid::new_v4()),
+                                    &entry_remote_path,
+                                    e,
+                                ),
+                            }
+                            // Fails only if the connection is gone, which ends the walk
+                            self.cwd(remote_dir).await.map_err(|e| {
+                                ftp_failure(format!("CWD failed returning to {}", remote_dir), e)
+                            })?;
+                        } else {
+                            let transfer_id = format!("dl-{}", uuid::Uuid::new_v4());
+                            batch.file_progress(
+                                &transfer_id,
+                                &entry_remote_path,
+                                0,
+                                entry.size,
+                                "starting",
+                            );
+                            let fail = |error: String| {
+                                batch.fail(&transfer_id, &entry_remote_path, error)
+                            };
+                            let mut stream = match self.retr_as_stream(&entry.name).await {
+                                Ok(stream) => stream,
+                                Err(e) if !is_dead_connection(&e) => {
+                                    fail(format!("Download failed: {}", e));
+                                    continue;
+                                }
+                                Err(e) => {
+                                    return Err(ftp_failure(
+                                        format!("Download failed for {}", entry.name),
+                                        e,
+                                    ))
+                                }
+                            };
+                            let mut buf = Vec::new();
+                            let read = stream.read_to_end(&mut buf).await;
+                            // Finalized even after a failed read, to keep the control
+                            // connection in step for the next file
+                            match (read, self.finalize_retr_stream(stream).await) {
+                                (_, Err(e)) if is_dead_connection(&e) => {
+                                    return Err(ftp_failure(
+                                        format!("Finalize failed for {}", entry.name),
+                                        e,
+                                    ));
+                                }
+                                (Err(e), _) => {
+                                    fail(format!("Read stream failed: {}", e));
+                                    continue;
+                                }
+                                (Ok(_), Err(e)) => {
+                                    fail(format!("Finalize failed: {}", e));
+                                    continue;
+                                }
+                                (Ok(_), Ok(())) => {}
+                            }
+
+                            if let Err(e) = std::fs::write(&entry_local_path, &buf) {
+                                fail(format!("Save failed: {}", e));
+                                continue;
+                            }
+                            let size = buf.len() as u64;
+                            batch.file_progress(
+                                &transfer_id,
+                                &entry_remote_path,
+                                size,
+                                size,
+                                "complete",
+                            );
+                            batch.advance(size, "downloading");
+                            total_bytes += size;
+                        }
+                    }
+
+                    Ok(total_bytes)
+                })
+            }
+
+            fn size_tree<'a>(
+                &'a mut self,
+                remote_dir: &'a str,
+                rel_dir: &'a str,
+                filter: &'a PathFilter,
+                links: &'a SymlinkWalk,
+                dialect: &'a ListingDialect,
+            ) -> BoxFuture<'a, Result<u64, String>> {
+                Box::pin(async move {
+                    self.cwd(remote_dir)
+                        .await
+                        .map_err(|e| ftp_failure(format!("CWD failed to {}", remote_dir), e))?;
+                    let real_path = self.pwd().await.unwrap_or_else(|_| remote_dir.to_string());
+                    if !links.visit(real_path) {
+                        links.skip(remote_dir);
+                        return Ok(0);
+                    }
+                    let lines = self
+                        .list_lines(dialect)
+                        .await
+                        .map_err(|e| ftp_failure(format!("LIST failed in {}", remote_dir), e))?;
+
+                    let mut total = 0;
+                    for entry in lines.iter().filter_map(|l| parse_list_line(l, dialect)) {
+                        let entry_rel_path = join_relative(rel_dir, &entry.name);
+                        if !filter.allows(&entry_rel_path, entry.is_dir) {
+                            continue;
+                        }
+                        let entry_remote_path = join_remote_path(remote_dir, &entry.name);
+                        let is_dir = match entry.symlink_target {
+                            Some(_) => self
+                                .resolve_symlink(remote_dir, &entry_remote_path, links)
+                                .await?,
+                            None => Some(entry.is_dir),
+                        };
+                        match is_dir {
+                            Some(true) => {
+                                total += self
+                                    .size_tree(&entry_remote_path, &entry_rel_path, filter, links, dialect)
+                                    .await?;
+                            }
+                            Some(false) => total += entry.size,
+                            None => {}
+                        }
+                    }
+
+                    Ok(total)
+                })
+            }
+
+            fn resolve_symlink<'a>(
+                &'a mut self,
+                remote_dir: &'a str,
+                entry_remote_path: &'a str,
+                links: &'a SymlinkWalk,
+            ) -> BoxFuture<'a, Result<Option<bool>, String>> {
+                Box::pin(async move {
+                    // LIST doesn't reliably say what a link points to, so try entering it
+                    if self.cwd(entry_remote_path).await.is_err() {
+                        return Ok(Some(false));
+                    }
+                    self.cwd(remote_dir)
+                        .await
+                        .map_err(|e| ftp_failure(format!("CWD failed returning to {}", remote_dir), e))?;
+                    if links.follow {
+                        Ok(Some(true))
+                    } else {
+                        links.skip(entry_remote_path);
+                        Ok(None)
+                    }
+                })
+            }
+
+            fn prune_tree<'a>(
+                &'a mut self,
+                remote_dir: &'a str,
+                cutoff: NaiveDateTime,
+                recursive: bool,
+                dry_run: bool,
+                pruned: &'a mut Vec<PrunedEntry>,
+                dialect: &'a ListingDialect,
+            ) -> BoxFuture<'a, Result<(), String>> {
+                Box::pin(async move {
+                    self.cwd(remote_dir)
+                        .await
+                        .map_err(|e| ftp_failure(format!("CWD failed to {}", remote_dir), e))?;
+                    let lines = self
+                        .list_lines(dialect)
+                        .await
+                        .map_err(|e| ftp_failure(format!("LIST failed in {}", remote_dir), e))?;
+                    let entries: Vec<RemoteFileEntry> =
+                        lines.iter().filter_map(|l| parse_list_line(l, dialect)).collect();
+                    let now = Utc::now().naive_utc();
+
+                    for entry in entries {
+                        let entry_remote_path = join_remote_path(remote_dir, &entry.name);
+                        // A link can lead back up the tree or outside `remote_dir`,
+                        // and its MDTM is its target's, so links are left alone
+                        if entry.symlink_target.is_some() {
+                            continue;
+                        }
+
+                        if entry.is_dir {
+                            if recursive {
+                                self.prune_tree(&entry_remote_path, cutoff, true, dry_run, pruned, dialect)
+                                    .await?;
+                                self.cwd(remote_dir).await.map_err(|e| {
+                                    ftp_failure(format!("CWD failed returning to {}", remote_dir), e)
+                                })?;
+                            }
+                            continue;
+                        }
+
+                        // MDTM is second-accurate and in UTC; the LIST date is only a fallback
+                        let modified = match self.mdtm(&entry.name).await {
+                            Ok(t) => Some(t),
+                            Err(_) => parse_list_time(&entry.modified, dialect),
+                        };
+                        let Some(modified) = modified else {
+                            continue;
+                        };
+                        if modified >= cutoff {
+                            continue;
+                        }
+
+                        if !dry_run {
+                            self.rm(&entry.name).await.map_err(|e| {
+                                ftp_failure(format!("Delete failed for {}", entry_remote_path), e)
+                            })?;
+                        }
+                        pruned.push(PrunedEntry {
+                            path: entry_remote_path,
+                            age_days: (now - modified).num_days(),
+                            deleted: !dry_run,
+                        });
+                    }
+
+                    Ok(())
+                })
+            }
+
+            fn list_tree<'a>(
+                &'a mut self,
+                remote_dir: &'a str,
+                rel_dir: &'a str,
+                files: &'a mut BTreeMap<String, RemoteFileEntry>,
+                on_dir: &'a (dyn Fn(&str, usize) + Sync),
+                dialect: &'a ListingDialect,
+            ) -> BoxFuture<'a, Result<(), String>> {
+                Box::pin(async move {
+                    self.cwd(remote_dir)
+                        .await
+                        .map_err(|e| ftp_failure(format!("CWD failed to {}", remote_dir), e))?;
+                    let lines = self
+                        .list_lines(dialect)
+                        .await
+                        .map_err(|e| ftp_failure(format!("LIST failed in {}", remote_dir), e))?;
+                    let entries: Vec<RemoteFileEntry> =
+                        lines.iter().filter_map(|l| parse_list_line(l, dialect)).collect();
+
+                    for entry in entries {
+                        let entry_rel_path = join_relative(rel_dir, &entry.name);
+                        // Links to directories are skipped: they may loop back up
+                        // the tree, and their target is listed where it really is
+                        if entry.is_dir && entry.symlink_target.is_some() {
+                            continue;
+                        }
+                        if entry.is_dir {
+                            let entry_remote_path = join_remote_path(remote_dir, &entry.name);
+                            self.list_tree(&entry_remote_path, &entry_rel_path, files, on_dir, dialect)
+                                .await?;
+                        } else {
+                            files.insert(entry_rel_path, entry);
+                        }
+                    }
+                    on_dir(rel_dir, files.len());
+
+                    Ok(())
+                })
+            }
+        }
+    };
+}
+
+impl_tree_ops!(SecureStream);
+impl_tree_ops!(PlainStream);
+
+async fn prune_remote_inner(
+    state: &FtpState,
+    path: String,
+    older_than_days: u32,
+    recursive: bool,
+    dry_run: bool,
+) -> Result<Vec<PrunedEntry>, String> {
+    let cutoff = Utc::now().naive_utc() - chrono::Duration::days(older_than_days as i64);
+    let mut pruned = Vec::new();
+
+    with_active_client!(state, |client| {
+        let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let result = client
+            .prune_tree(
+                &path,
+                cutoff,
+                recursive,
+                dry_run,
+                &mut pruned,
+                &state.dialect(),
+            )
+            .await;
+        let _ = client.cwd(&orig_cwd).await;
+        result?;
+        Ok(pruned)
+    })
+}
+
+/// Deletes (or with `dry_run`, only reports) files under `path` older than `older_than_days`.
+#[tauri::command]
+pub async fn prune_remote(
+    state: State<'_, FtpState>,
+    path: String,
+    older_than_days: u32,
+    recursive: bool,
+    dry_run: bool,
+) -> Result<Vec<PrunedEntry>, String> {
+    let state = state.inner();
+    let result = with_reconnect(state, || {
+        prune_remote_inner(state, path.clone(), older_than_days, recursive, dry_run)
+    })
+    .await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+/// A file present on both sides whose sizes disagree. The modification times
+/// are for display; the remote one is in the server's time zone.
+#[derive(Serialize)]
+pub struct DifferingFile {
+    pub path: String,
+    pub local_size: u64,
+    pub remote_size: u64,
+    pub local_modified: Option<String>,
+    pub remote_modified: Option<String>,
+}
+
+/// Files found under only one of two directories, or under both but different.
+/// Paths are relative to the compared directories and use `/` separators.
+#[derive(Serialize, Default)]
+pub struct DirectoryDiff {
+    pub only_local: Vec<String>,
+    pub only_remote: Vec<String>,
+    pub differing: Vec<DifferingFile>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DiffProgress {
+    /// "local" or "remote", or "a" or "b" for `diff_remotes`.
+    pub side: String,
+    /// Directory just scanned, relative to the compared directory.
+    pub directory: String,
+    pub files_scanned: usize,
+}
 
-    // Get file size for progress bar
-    let _size = {
-        // We try to get size from LIST or just use 0 if unknown
-        // For simplicity, we'll try MDTM or just use a default
-        0 // Placeholder if we can't get it easily without a separate call
-    };
+struct LocalFileInfo {
+    size: u64,
+    modified: Option<NaiveDateTime>,
+}
 
-    // Try secure client first
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            // Try to get size
-            let total_size = timeout(Duration::from_secs(5), client.size(&remote_name))
-                .await
-                .map_err(|_| "SIZE timed out".to_string())?
-                .unwrap_or(0) as u64;
+fn local_tree(
+    dir: &std::path::Path,
+    rel_dir: &str,
+    files: &mut BTreeMap<String, LocalFileInfo>,
+    on_dir: &dyn Fn(&str, usize),
+) -> Result<(), String> {
+    let read_dir = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+    for entry in read_dir.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let rel_path = join_relative(rel_dir, &entry.file_name().to_string_lossy());
+        if metadata.is_dir() {
+            local_tree(&entry.path(), &rel_path, files, on_dir)?;
+        } else {
+            let modified = metadata
+                .modified()
+                .ok()
+                .map(|t| chrono::DateTime::<Utc>::from(t).naive_utc());
+            files.insert(
+                rel_path,
+                LocalFileInfo {
+                    size: metadata.len(),
+                    modified,
+                },
+            );
+        }
+    }
+    on_dir(rel_dir, files.len());
+    Ok(())
+}
 
-            let mut stream = timeout(Duration::from_secs(10), client.retr_as_stream(&remote_name))
-                .await
-                .map_err(|_| "Download initiation timed out".to_string())?
-                .map_err(|e| format!("Download failed: {}", e))?;
+/// Whether a local file and its remote counterpart look different. Only sizes
+/// are compared: LIST dates are in the server's unknown time zone, so against
+/// local UTC times they would flag every file on a server hours away from UTC.
+fn files_differ(local: &LocalFileInfo, remote: &RemoteFileEntry) -> bool {
+    local.size != remote.size
+}
 
-            let mut file = tokio::fs::File::create(&local_path)
-                .await
-                .map_err(|e| format!("Capture failed: {}", e))?;
+async fn list_remote_tree_files(
+    state: &FtpState,
+    remote_dir: String,
+    on_dir: &(dyn Fn(&str, usize) + Sync),
+) -> Result<BTreeMap<String, RemoteFileEntry>, String> {
+    let mut files = BTreeMap::new();
+    with_active_client!(state, |client| {
+        let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let result = client
+            .list_tree(
+                &absolute_remote_path(&orig_cwd, &remote_dir),
+                "",
+                &mut files,
+                on_dir,
+                &state.dialect(),
+            )
+            .await;
+        let _ = client.cwd(&orig_cwd).await;
+        result?;
+        Ok(files)
+    })
+}
 
-            let mut buffer = [0u8; 16384];
-            let mut downloaded = 0u64;
+/// An entry of `list_remote_tree`, with the contents of directories nested under it.
+#[derive(Serialize, Clone)]
+pub struct RemoteTreeNode {
+    #[serde(flatten)]
+    pub entry: RemoteFileEntry,
+    /// Path relative to the listed directory, `/`-separated.
+    pub path: String,
+    pub children: Vec<RemoteTreeNode>,
+}
 
-            loop {
-                let n = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
-                if n == 0 {
-                    break;
+/// Splits `LIST -R` output run in `root` into listings keyed by directory relative
+/// to it. Sections start with a `dir:` header, relative (`./sub`) or absolute
+/// depending on the server; the first one, `root` itself, may have none.
+fn parse_recursive_list(
+    root: &str,
+    lines: &[String],
+    dialect: &ListingDialect,
+) -> BTreeMap<String, Vec<RemoteFileEntry>> {
+    let mut listings: BTreeMap<String, Vec<RemoteFileEntry>> = BTreeMap::new();
+    let mut current = String::new();
+    listings.insert(current.clone(), Vec::new());
+    for line in lines.iter().map(|l| l.trim_end()) {
+        if line.is_empty() || line.starts_with("total ") {
+            continue;
+        }
+        if let Some(entry) = parse_list_line(line, dialect) {
+            listings.entry(current.clone()).or_default().push(entry);
+        } else if let Some(header) = line.strip_suffix(':') {
+            current = header
+                .strip_prefix(root)
+                .unwrap_or(header)
+                .trim_start_matches("./")
+                .trim_start_matches('.')
+                .trim_matches('/')
+                .to_string();
+            listings.entry(current.clone()).or_default();
+        }
+    }
+    listings
+}
+
+/// Whether every directory in `listings` has its own section, which tells a real
+/// recursive listing apart from a server that ignored `-R`.
+fn is_complete_tree(listings: &BTreeMap<String, Vec<RemoteFileEntry>>) -> bool {
+    listings.iter().all(|(rel_dir, entries)| {
+        entries
+            .iter()
+            .filter(|e| e.is_dir && e.symlink_target.is_none())
+            .all(|e| listings.contains_key(&join_relative(rel_dir, &e.name)))
+    })
+}
+
+fn build_remote_tree(
+    listings: &BTreeMap<String, Vec<RemoteFileEntry>>,
+    rel_dir: &str,
+) -> Vec<RemoteTreeNode> {
+    listings
+        .get(rel_dir)
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|entry| {
+                    let path = join_relative(rel_dir, &entry.name);
+                    let children = if entry.is_dir {
+                        build_remote_tree(listings, &path)
+                    } else {
+                        Vec::new()
+                    };
+                    RemoteTreeNode {
+                        entry: entry.clone(),
+                        path,
+                        children,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn list_remote_tree_inner(
+    state: &FtpState,
+    path: String,
+) -> Result<BTreeMap<String, Vec<RemoteFileEntry>>, String> {
+    let dialect = state.dialect();
+    with_active_client!(state, |client| {
+        let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let root = absolute_remote_path(&orig_cwd, &path);
+        let result = async {
+            timeout(Duration::from_secs(5), client.cwd(&root))
+                .await
+                .map_err(|_| "CWD timed out".to_string())?
+                .map_err(|e| ftp_failure(format!("CWD failed to {}", root), e))?;
+
+            match timeout(Duration::from_secs(60), client.list(Some("-R"))).await {
+                Ok(Ok(lines)) => {
+                    let listings = parse_recursive_list(&root, &lines, &dialect);
+                    if is_complete_tree(&listings) {
+                        return Ok(listings);
+                    }
+                    tracing::debug!("server ignored LIST -R, walking the tree instead");
                 }
-                file.write_all(&buffer[..n])
-                    .await
-                    .map_err(|e| e.to_string())?;
-                downloaded += n as u64;
-
-                // Emit progress
-                if total_size > 0 {
-                    let _ = window.emit(
-                        "transfer-progress",
-                        TransferProgress {
-                            transfer_id: transfer_id.clone(),
-                            filename: remote_name.clone(),
-                            progress: downloaded,
-                            total: total_size,
-                            status: "downloading".into(),
-                        },
-                    );
+                Ok(Err(e)) => {
+                    let err = data_command_error("LIST -R", e);
+                    if is_connection_error(&err) {
+                        return Err(err);
+                    }
+                    tracing::debug!(error = %err, "LIST -R refused, walking the tree instead");
                 }
+                Err(_) => return Err(data_connection_blocked("LIST -R", "timed out")),
             }
 
-            timeout(Duration::from_secs(10), client.finalize_retr_stream(stream))
-                .await
-                .map_err(|_| "Finalize timed out".to_string())?
-                .map_err(|e| format!("Finalize failed: {}", e))?;
+            // One directory at a time, as the server can't do it in one go
+            let mut listings = BTreeMap::new();
+            let mut pending = vec![String::new()];
+            while let Some(rel_dir) = pending.pop() {
+                let dir = join_remote_path(&root, &rel_dir);
+                client
+                    .cwd(&dir)
+                    .await
+                    .map_err(|e| ftp_failure(format!("CWD failed to {}", dir), e))?;
+                let lines = timeout(state.policy().read_timeout(), client.list_lines(&dialect))
+                    .await
+                    .map_err(|_| data_connection_blocked("LIST", "timed out"))?
+                    .map_err(|e| data_command_error("LIST", e))?;
+                let entries: Vec<RemoteFileEntry> = lines
+                    .iter()
+                    .filter_map(|l| parse_list_line(l, &dialect))
+                    .collect();
+                pending.extend(
+                    entries
+                        .iter()
+                        .filter(|e| e.is_dir && e.symlink_target.is_none())
+                        .map(|e| join_relative(&rel_dir, &e.name)),
+                );
+                listings.insert(rel_dir, entries);
+            }
+            Ok(listings)
+        }
+        .await;
+        let _ = client.cwd(&orig_cwd).await;
+        result
+    })
+}
 
-            // Final emit
-            let _ = window.emit(
-                "transfer-progress",
-                TransferProgress {
-                    transfer_id: transfer_id.clone(),
-                    filename: remote_name.clone(),
-                    progress: downloaded,
-                    total: total_size,
-                    status: "complete".into(),
+/// Lists `path` and everything below it as a nested tree. Uses a single
+/// server-side `LIST -R` where the server supports it, and falls back to
+/// listing one directory at a time otherwise. Symlinked directories are not followed.
+#[tauri::command]
+pub async fn list_remote_tree(
+    state: State<'_, FtpState>,
+    path: String,
+) -> Result<Vec<RemoteTreeNode>, String> {
+    let state = state.inner();
+    let listings = with_reconnect(state, || list_remote_tree_inner(state, path.clone())).await?;
+    Ok(build_remote_tree(&listings, ""))
+}
+
+/// Compares a local and a remote directory tree without changing either, so the
+/// user can review the differences before picking a sync direction. Emits
+/// `diff-progress` after each scanned directory.
+#[tauri::command]
+pub async fn diff_directories(
+    window: Window,
+    state: State<'_, FtpState>,
+    local: String,
+    remote: String,
+) -> Result<DirectoryDiff, String> {
+    let state = state.inner();
+    let local_root = std::path::PathBuf::from(&local);
+    if !local_root.is_dir() {
+        return Err(format!("Not a directory: {}", local));
+    }
+
+    let emitter = window.clone();
+    let local_files = tauri::async_runtime::spawn_blocking(move || {
+        let mut files = BTreeMap::new();
+        let on_dir = |dir: &str, files_scanned: usize| {
+            let _ = emitter.emit(
+                "diff-progress",
+                DiffProgress {
+                    side: "local".into(),
+                    directory: dir.to_string(),
+                    files_scanned,
                 },
             );
+        };
+        local_tree(&local_root, "", &mut files, &on_dir).map(|_| files)
+    })
+    .await
+    .map_err(|e| format!("Local scan failed: {}", e))??;
 
-            return Ok(format!("Downloaded {}", remote_name));
+    let on_dir = |dir: &str, files_scanned: usize| {
+        let _ = window.emit(
+            "diff-progress",
+            DiffProgress {
+                side: "remote".into(),
+                directory: dir.to_string(),
+                files_scanned,
+            },
+        );
+    };
+    let remote_files = with_reconnect(state, || {
+        list_remote_tree_files(state, remote.clone(), &on_dir)
+    })
+    .await?;
+
+    let mut diff = DirectoryDiff::default();
+    for (path, local_file) in &local_files {
+        match remote_files.get(path) {
+            None => diff.only_local.push(path.clone()),
+            Some(remote_file) if files_differ(local_file, remote_file) => {
+                diff.differing.push(DifferingFile {
+                    path: path.clone(),
+                    local_size: local_file.size,
+                    remote_size: remote_file.size,
+                    local_modified: local_file
+                        .modified
+                        .map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                    remote_modified: remote_file.modified_iso.clone(),
+                });
+            }
+            Some(_) => {}
         }
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let total_size = timeout(Duration::from_secs(5), client.size(&remote_name))
-                .await
-                .map_err(|_| "SIZE timed out".to_string())?
-                .unwrap_or(0) as u64;
+    diff.only_remote = remote_files
+        .keys()
+        .filter(|path| !local_files.contains_key(*path))
+        .cloned()
+        .collect();
+
+    Ok(diff)
+}
+
+/// Files found on only one of two servers, or on both but different. Paths are
+/// relative to the compared directories and use `/` separators.
+#[derive(Serialize, Default)]
+pub struct RemoteDiff {
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    pub differing: Vec<RemoteDifference>,
+    /// The `HASH` algorithm same-size files were compared with; `None` when
+    /// only sizes were compared.
+    pub hash_algorithm: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RemoteDifference {
+    pub path: String,
+    pub size_a: u64,
+    pub size_b: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash_a: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash_b: Option<String>,
+}
+
+/// `HASH` algorithms `diff_remotes` can compare with, most preferred first.
+const HASH_ALGORITHMS: [&str; 4] = ["SHA-256", "SHA-512", "SHA-1", "MD5"];
+
+/// Algorithms a server lists in its `HASH` feature, e.g. "HASH SHA-256*;SHA-1;MD5".
+async fn hash_algorithms(state: &FtpState) -> Vec<String> {
+    let info = state.server_info.lock().await;
+    info.iter()
+        .flat_map(|info| &info.features)
+        .find_map(|feature| feature.strip_prefix("HASH "))
+        .map(|algorithms| {
+            algorithms
+                .split(';')
+                .map(|a| a.trim().trim_end_matches('*').to_uppercase())
+                .filter(|a| !a.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Hashes `paths`, relative to the absolute `root`, with `HASH` after
+/// selecting `algorithm` via `OPTS HASH`.
+async fn remote_hashes_inner(
+    state: &FtpState,
+    root: &str,
+    paths: &[String],
+    algorithm: &str,
+) -> Result<BTreeMap<String, String>, String> {
+    with_active_client!(state, |client| {
+        timeout(
+            Duration::from_secs(5),
+            client.custom_command(format!("OPTS HASH {}", algorithm), &[Status::CommandOk]),
+        )
+        .await
+        .map_err(|_| "OPTS HASH timed out".to_string())?
+        .map_err(|e| ftp_failure("OPTS HASH failed", e))?;
+
+        let mut hashes = BTreeMap::new();
+        for path in paths {
+            let reply = timeout(
+                state.policy().read_timeout(),
+                client.custom_command(
+                    format!("HASH {}", join_remote_path(root, path)),
+                    &[Status::File],
+                ),
+            )
+            .await
+            .map_err(|_| "HASH timed out".to_string())?
+            .map_err(|e| ftp_failure(format!("HASH failed for {}", path), e))?;
+            // "213 SHA-256 0-49 <hash> <path>"
+            if let Some(hash) = String::from_utf8_lossy(&reply.body)
+                .split_whitespace()
+                .nth(3)
+            {
+                hashes.insert(path.clone(), hash.to_lowercase());
+            }
+        }
+        Ok(hashes)
+    })
+}
+
+/// Connects `side` to a saved connection and lists the files under `path`,
+/// reporting each scanned directory as `side_name`. Evaluates to the listing
+/// and the absolute path of the compared directory.
+async fn scan_remote_side(
+    window: &Window,
+    side: &FtpState,
+    side_name: &str,
+    connection_id: &str,
+    path: &str,
+) -> Result<(BTreeMap<String, RemoteFileEntry>, String), String> {
+    let config = saved_connection_config(window.app_handle().clone(), connection_id)?;
+    connect_with_config(side, config).await?;
+    let root = absolute_remote_path(&get_remote_pwd_inner(side).await?, path);
+    let on_dir = |dir: &str, files_scanned: usize| {
+        let _ = window.emit(
+            "diff-progress",
+            DiffProgress {
+                side: side_name.into(),
+                directory: dir.to_string(),
+                files_scanned,
+            },
+        );
+    };
+    let files =
+        with_reconnect(side, || list_remote_tree_files(side, root.clone(), &on_dir)).await?;
+    Ok((files, root))
+}
+
+async fn diff_remote_sides(
+    window: &Window,
+    side_a: &FtpState,
+    side_b: &FtpState,
+    (conn_a, path_a): (&str, &str),
+    (conn_b, path_b): (&str, &str),
+    compare_hashes: bool,
+) -> Result<RemoteDiff, String> {
+    let (files_a, root_a) = scan_remote_side(window, side_a, "a", conn_a, path_a).await?;
+    let (files_b, root_b) = scan_remote_side(window, side_b, "b", conn_b, path_b).await?;
+
+    let mut diff = RemoteDiff::default();
+    let mut same_size = Vec::new();
+    for (path, a) in &files_a {
+        match files_b.get(path) {
+            None => diff.only_a.push(path.clone()),
+            Some(b) if a.size != b.size => diff.differing.push(RemoteDifference {
+                path: path.clone(),
+                size_a: a.size,
+                size_b: b.size,
+                hash_a: None,
+                hash_b: None,
+            }),
+            Some(_) => same_size.push(path.clone()),
+        }
+    }
+    diff.only_b = files_b
+        .keys()
+        .filter(|path| !files_a.contains_key(*path))
+        .cloned()
+        .collect();
+
+    if !compare_hashes || same_size.is_empty() {
+        return Ok(diff);
+    }
+    let (algorithms_a, algorithms_b) =
+        (hash_algorithms(side_a).await, hash_algorithms(side_b).await);
+    let Some(algorithm) = HASH_ALGORITHMS
+        .into_iter()
+        .find(|a| algorithms_a.iter().any(|x| x == a) && algorithms_b.iter().any(|x| x == a))
+    else {
+        tracing::info!("servers share no HASH algorithm, compared sizes only");
+        return Ok(diff);
+    };
+    let hashes_a = with_reconnect(side_a, || {
+        remote_hashes_inner(side_a, &root_a, &same_size, algorithm)
+    })
+    .await?;
+    let hashes_b = with_reconnect(side_b, || {
+        remote_hashes_inner(side_b, &root_b, &same_size, algorithm)
+    })
+    .await?;
+    for path in same_size {
+        let (hash_a, hash_b) = (hashes_a.get(&path), hashes_b.get(&path));
+        if hash_a != hash_b {
+            let size = files_a[&path].size;
+            diff.differing.push(RemoteDifference {
+                path,
+                size_a: size,
+                size_b: size,
+                hash_a: hash_a.cloned(),
+                hash_b: hash_b.cloned(),
+            });
+        }
+    }
+    diff.differing.sort_by(|x, y| x.path.cmp(&y.path));
+    diff.hash_algorithm = Some(algorithm.to_string());
+    Ok(diff)
+}
+
+/// Compares `path_a` on saved connection `conn_a` with `path_b` on `conn_b`,
+/// e.g. to verify a migration. Both servers get their own sessions for the
+/// duration, leaving the active one alone. Sizes come from the listings; with
+/// `compare_hashes`, same-size files are also hashed with `HASH` when both
+/// servers support a common algorithm. Emits `diff-progress` per directory.
+#[tauri::command]
+pub async fn diff_remotes(
+    window: Window,
+    conn_a: String,
+    path_a: String,
+    conn_b: String,
+    path_b: String,
+    compare_hashes: Option<bool>,
+) -> Result<RemoteDiff, String> {
+    let (side_a, side_b) = (FtpState::default(), FtpState::default());
+    let result = diff_remote_sides(
+        &window,
+        &side_a,
+        &side_b,
+        (&conn_a, &path_a),
+        (&conn_b, &path_b),
+        compare_hashes.unwrap_or(false),
+    )
+    .await;
+    close_sessions(&side_a).await;
+    close_sessions(&side_b).await;
+    result
+}
+
+/// The octal mode of the entry called `name` in a LIST of its parent directory.
+fn octal_from_listing(
+    lines: &[String],
+    name: &str,
+    dialect: &ListingDialect,
+) -> Result<String, String> {
+    let perms = lines
+        .iter()
+        .filter_map(|l| parse_list_line(l, dialect))
+        .find(|e| e.name == name)
+        .map(|e| e.permissions)
+        .ok_or_else(|| format!("{} is not in its directory's listing", name))?;
+
+    permissions_to_octal(&perms).ok_or_else(|| format!("Unrecognized permission string: {}", perms))
+}
+
+async fn get_remote_permissions_inner(state: &FtpState, path: String) -> Result<String, String> {
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    if name.is_empty() {
+        return Err("The root directory has no listing entry to read permissions from".into());
+    }
+    let parent = remote_parent(&path);
+    let dialect = state.dialect();
 
-            let mut stream = timeout(Duration::from_secs(10), client.retr_as_stream(&remote_name))
+    // Listing a directory shows what's in it, so the entry is looked up in its parent
+    with_active_client!(state, |client| {
+        let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let lines: Result<Vec<String>, String> = async {
+            if !parent.is_empty() {
+                timeout(Duration::from_secs(5), client.cwd(parent))
+                    .await
+                    .map_err(|_| "CWD timed out".to_string())?
+                    .map_err(|e| ftp_failure(format!("CWD failed to {}", parent), e))?;
+            }
+            timeout(Duration::from_secs(10), client.list_lines(&dialect))
                 .await
-                .map_err(|_| "Download initiation timed out".to_string())?
-                .map_err(|e| format!("Download failed: {}", e))?;
+                .map_err(|_| data_connection_blocked("LIST", "timed out"))?
+                .map_err(|e| data_command_error("LIST", e))
+        }
+        .await;
+        if !parent.is_empty() {
+            let _ = client.cwd(&orig_cwd).await;
+        }
+        octal_from_listing(&lines?, name, &dialect)
+    })
+}
+
+/// Returns the octal mode of a remote file or directory, derived from the LIST
+/// permission string of its entry in the parent directory.
+#[tauri::command]
+pub async fn get_remote_permissions(
+    state: State<'_, FtpState>,
+    path: String,
+) -> Result<String, String> {
+    let state = state.inner();
+    with_reconnect(state, || get_remote_permissions_inner(state, path.clone())).await
+}
+
+async fn set_remote_permissions_inner(
+    state: &FtpState,
+    path: String,
+    octal: String,
+) -> Result<String, String> {
+    let valid = (3..=4).contains(&octal.len()) && octal.chars().all(|c| ('0'..='7').contains(&c));
+    if !valid {
+        return Err(format!("Invalid octal mode: {}", octal));
+    }
+    let command = format!("CHMOD {} {}", octal, path);
+
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.site(&command))
+            .await
+            .map_err(|_| "CHMOD timed out".to_string())?
+            .map_err(|e| ftp_failure("CHMOD failed", e))?;
+        Ok(format!("Changed permissions of {} to {}", path, octal))
+    })
+}
+
+/// Sets the mode of a remote file via `SITE CHMOD`.
+#[tauri::command]
+pub async fn set_remote_permissions(
+    state: State<'_, FtpState>,
+    path: String,
+    octal: String,
+) -> Result<String, String> {
+    let state = state.inner();
+    let result = with_reconnect(state, || {
+        set_remote_permissions_inner(state, path.clone(), octal.clone())
+    })
+    .await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+const LATENCY_SAMPLES: u32 = 5;
 
-            let mut file = tokio::fs::File::create(&local_path)
+async fn benchmark_connection_inner(
+    state: &FtpState,
+    size: usize,
+) -> Result<BenchmarkResult, String> {
+    with_active_client!(state, |client| {
+        let started = Instant::now();
+        for _ in 0..LATENCY_SAMPLES {
+            timeout(Duration::from_secs(5), client.noop())
                 .await
-                .map_err(|e| format!("Capture failed: {}", e))?;
+                .map_err(|_| "NOOP timed out".to_string())?
+                .map_err(|e| ftp_failure("NOOP failed", e))?;
+        }
+        let latency = started.elapsed() / LATENCY_SAMPLES;
+
+        let name = format!(".quicksync-benchmark-{}", uuid::Uuid::new_v4());
+        let payload = vec![0u8; size];
+        if client.transfer_type(FileType::Binary).await.is_ok() {
+            state.ascii_type.store(false, Ordering::Relaxed);
+        }
 
-            let mut buffer = [0u8; 16384];
-            let mut downloaded = 0u64;
+        let started = Instant::now();
+        let upload: Result<(), String> = async {
+            let mut stream = timeout(Duration::from_secs(10), client.put_with_stream(&name))
+                .await
+                .map_err(|_| data_connection_blocked("Upload", "timed out"))?
+                .map_err(|e| data_command_error("Upload", e))?;
+            stream
+                .write_all(&payload)
+                .await
+                .map_err(|e| format!("Upload failed: {}", e))?;
+            client
+                .finalize_put_stream(stream)
+                .await
+                .map_err(|e| ftp_failure("Finalize failed", e))
+        }
+        .await;
+        let upload_time = started.elapsed();
 
-            loop {
-                let n = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
-                if n == 0 {
-                    break;
-                }
-                file.write_all(&buffer[..n])
-                    .await
-                    .map_err(|e| e.to_string())?;
-                downloaded += n as u64;
-
-                if total_size > 0 {
-                    let _ = window.emit(
-                        "transfer-progress",
-                        TransferProgress {
-                            transfer_id: transfer_id.clone(),
-                            filename: remote_name.clone(),
-                            progress: downloaded,
-                            total: total_size,
-                            status: "downloading".into(),
-                        },
-                    );
+        let started = Instant::now();
+        let download: Result<usize, String> = match upload {
+            Ok(()) => {
+                async {
+                    let mut stream = timeout(Duration::from_secs(10), client.retr_as_stream(&name))
+                        .await
+                        .map_err(|_| data_connection_blocked("Download", "timed out"))?
+                        .map_err(|e| data_command_error("Download", e))?;
+                    let mut buf = Vec::with_capacity(size);
+                    stream
+                        .read_to_end(&mut buf)
+                        .await
+                        .map_err(|e| format!("Read stream failed: {}", e))?;
+                    client
+                        .finalize_retr_stream(stream)
+                        .await
+                        .map_err(|e| ftp_failure("Finalize failed", e))?;
+                    Ok(buf.len())
                 }
+                .await
             }
+            Err(e) => Err(e),
+        };
+        let download_time = started.elapsed();
+
+        // The test file goes whether or not the measurements worked
+        let _ = timeout(Duration::from_secs(5), client.rm(&name)).await;
+        let downloaded = download?;
+
+        Ok(BenchmarkResult {
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            upload_bytes_per_sec: bytes_per_sec(size, upload_time),
+            download_bytes_per_sec: bytes_per_sec(downloaded, download_time),
+            bytes: size as u64,
+        })
+    })
+}
+
+/// Measures NOOP latency and throughput by uploading, downloading and deleting
+/// a `size_bytes` test file in the current remote directory.
+#[tauri::command]
+pub async fn benchmark_connection(
+    state: State<'_, FtpState>,
+    size_bytes: Option<usize>,
+) -> Result<BenchmarkResult, String> {
+    let size = size_bytes
+        .unwrap_or(BENCHMARK_DEFAULT_BYTES)
+        .min(BENCHMARK_MAX_BYTES);
+    let state = state.inner();
+    let result = with_reconnect(state, || benchmark_connection_inner(state, size)).await;
+    invalidate_listings(state, &[""]).await;
+    result
+}
+
+/// Parses an RFC 3339 timestamp, or a bare `YYYY-MM-DDTHH:MM:SS` taken as UTC,
+/// into the `YYYYMMDDHHMMSS` UTC form MFMT expects.
+fn ftp_timestamp(iso: &str) -> Result<String, String> {
+    let utc = chrono::DateTime::parse_from_rfc3339(iso)
+        .map(|t| t.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(iso, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|_| format!("Invalid timestamp: {}", iso))?;
+    Ok(utc.format("%Y%m%d%H%M%S").to_string())
+}
+
+async fn set_remote_mtime_inner(
+    state: &FtpState,
+    path: String,
+    stamp: String,
+) -> Result<String, String> {
+    with_active_client!(state, |client| {
+        let features = timeout(Duration::from_secs(5), client.feat())
+            .await
+            .map_err(|_| "FEAT timed out".to_string())?
+            .map_err(|e| ftp_failure("FEAT failed", e))?;
+        // Some servers without MFMT accept the older "MDTM <time> <path>" form instead
+        let verb = if features.contains_key("MFMT") {
+            "MFMT"
+        } else if features.contains_key("MDTM") {
+            "MDTM"
+        } else {
+            return Err("Server does not support setting modification times (no MFMT)".into());
+        };
+
+        timeout(
+            Duration::from_secs(5),
+            client.custom_command(format!("{} {} {}", verb, stamp, path), &[Status::File]),
+        )
+        .await
+        .map_err(|_| format!("{} timed out", verb))?
+        .map_err(|e| {
+            ftp_failure(
+                format!("Server refused to set the modification time ({})", verb),
+                e,
+            )
+        })?;
+        Ok(format!("Set modification time of {}", path))
+    })
+}
+
+/// Sets the modification time of a remote file from an ISO 8601 timestamp.
+#[tauri::command]
+pub async fn set_remote_mtime(
+    state: State<'_, FtpState>,
+    path: String,
+    iso_timestamp: String,
+) -> Result<String, String> {
+    let stamp = ftp_timestamp(&iso_timestamp)?;
+    let state = state.inner();
+    let result = with_reconnect(state, || {
+        set_remote_mtime_inner(state, path.clone(), stamp.clone())
+    })
+    .await;
+    invalidate_listings(state, &[&path]).await;
+    result
+}
+
+async fn download_remote_range_inner(
+    state: &FtpState,
+    path: String,
+    start: u64,
+    length: u64,
+    local_path: String,
+) -> Result<String, String> {
+    if length == 0 {
+        return Err("The range to download is empty".into());
+    }
+    if !supports_rest(state).await {
+        return Err("Server does not support REST; ranged downloads are unavailable".into());
+    }
 
+    let file = tokio::fs::File::create(&local_path)
+        .await
+        .map_err(|e| format!("Save failed: {}", e))?;
+    let mut file = tokio::io::BufWriter::new(file);
+    let written = match retr_range_into(state, &path, start, length, &mut file).await {
+        Ok(written) => written,
+        Err(e) => {
+            drop(file);
+            let _ = tokio::fs::remove_file(&local_path).await;
+            return Err(e);
+        }
+    };
+    file.flush()
+        .await
+        .map_err(|e| format!("Save failed: {}", e))?;
+    Ok(format!(
+        "Downloaded {} bytes of {} starting at {}",
+        written, path, start
+    ))
+}
+
+/// Copies up to `length` bytes of `path` starting at `start` (via `REST`) into
+/// `sink`, returning how many were copied; fewer when the file ends first.
+async fn retr_range_into<W>(
+    state: &FtpState,
+    path: &str,
+    start: u64,
+    length: u64,
+    sink: &mut W,
+) -> Result<u64, String>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send,
+{
+    with_active_client!(state, |client| {
+        timeout(
+            Duration::from_secs(5),
+            client.resume_transfer(start as usize),
+        )
+        .await
+        .map_err(|_| "REST timed out".to_string())?
+        .map_err(|e| ftp_failure("REST failed", e))?;
+        let stream = timeout(Duration::from_secs(10), client.retr_as_stream(path))
+            .await
+            .map_err(|_| data_connection_blocked("Download", "timed out"))?
+            .map_err(|e| data_command_error("Download", e))?;
+
+        let mut limited = stream.take(length);
+        let copied = tokio::io::copy(&mut limited, sink)
+            .await
+            .map_err(|e| format!("Read stream failed: {}", e))?;
+        let mut stream = limited.into_inner();
+
+        // One byte past the range tells whether the file goes on after it
+        let mut probe = [0u8; 1];
+        let more = copied == length
+            && stream
+                .read(&mut probe)
+                .await
+                .map_err(|e| format!("Read stream failed: {}", e))?
+                > 0;
+        if more {
+            // We stop reading early, so the transfer has to be aborted rather than finalized
+            timeout(Duration::from_secs(10), client.abort(stream))
+                .await
+                .map_err(|_| "ABOR timed out".to_string())?
+                .map_err(|e| ftp_failure("ABOR failed", e))?;
+        } else {
             timeout(Duration::from_secs(10), client.finalize_retr_stream(stream))
                 .await
                 .map_err(|_| "Finalize timed out".to_string())?
-                .map_err(|e| format!("Finalize failed: {}", e))?;
+                .map_err(|e| ftp_failure("Finalize failed", e))?;
+        }
+        Ok(copied)
+    })
+}
+
+async fn read_range_inner(
+    state: &FtpState,
+    path: &str,
+    start: u64,
+    length: u64,
+) -> Result<Vec<u8>, String> {
+    if length > MEMORY_BATCH_LIMIT as u64 {
+        return Err(format!(
+            "Can't read {} bytes into memory; the limit is {}",
+            length, MEMORY_BATCH_LIMIT
+        ));
+    }
+    let mut buf = Vec::new();
+    retr_range_into(state, path, start, length, &mut buf).await?;
+    Ok(buf)
+}
+
+/// Reads `length` bytes of a remote file starting at `start`; fewer when the file
+/// ends first. Needs `REST` support, see `supports_rest`.
+pub(crate) async fn read_remote_range(
+    state: &FtpState,
+    path: &str,
+    start: u64,
+    length: u64,
+) -> Result<Vec<u8>, String> {
+    with_reconnect(state, || read_range_inner(state, path, start, length)).await
+}
+
+async fn remote_file_size_inner(state: &FtpState, path: &str) -> Result<u64, String> {
+    with_active_client!(state, |client| {
+        timeout(Duration::from_secs(5), client.size(path))
+            .await
+            .map_err(|_| "SIZE timed out".to_string())?
+            .map(|size| size as u64)
+            .map_err(|e| ftp_failure("SIZE failed", e))
+    })
+}
+
+pub(crate) async fn remote_file_size(state: &FtpState, path: &str) -> Result<u64, String> {
+    with_reconnect(state, || remote_file_size_inner(state, path)).await
+}
+
+/// Whether the server advertised `REST` in its `FEAT` reply at login.
+pub(crate) async fn supports_rest(state: &FtpState) -> bool {
+    state
+        .server_info
+        .lock()
+        .await
+        .as_ref()
+        .is_some_and(|info| info.features.iter().any(|f| f.starts_with("REST")))
+}
+
+/// Downloads `length` bytes of a remote file starting at byte `start` (via `REST`).
+#[tauri::command]
+pub async fn download_remote_range(
+    state: State<'_, FtpState>,
+    path: String,
+    start: u64,
+    length: u64,
+    local_path: String,
+) -> Result<String, String> {
+    let state = state.inner();
+    with_reconnect(state, || {
+        download_remote_range_inner(state, path.clone(), start, length, local_path.clone())
+    })
+    .await
+}
+
+/// Upper bound on the bytes one `download_remote_files_to_memory` call holds.
+const MEMORY_BATCH_LIMIT: usize = 64 * 1024 * 1024;
+
+async fn download_remote_files_to_memory_inner(
+    state: &FtpState,
+    paths: Vec<String>,
+    max_bytes_each: usize,
+) -> Result<Vec<(String, Result<Vec<u8>, String>)>, String> {
+    with_active_client!(state, |client| {
+        let mut results = Vec::with_capacity(paths.len());
+        let mut total = 0usize;
+
+        for path in &paths {
+            let budget = max_bytes_each.min(MEMORY_BATCH_LIMIT - total);
+            if budget == 0 && max_bytes_each > 0 {
+                results.push((path.clone(), Err("Batch memory limit reached".to_string())));
+                continue;
+            }
+
+            let result: Result<Vec<u8>, String> = async {
+                let stream = timeout(Duration::from_secs(10), client.retr_as_stream(path))
+                    .await
+                    .map_err(|_| data_connection_blocked("Download", "timed out"))?
+                    .map_err(|e| data_command_error("Download", e))?;
+
+                // One byte past the budget tells a capped file from one that fits exactly
+                let mut buf = Vec::new();
+                let mut limited = stream.take(budget as u64 + 1);
+                limited
+                    .read_to_end(&mut buf)
+                    .await
+                    .map_err(|e| format!("Read stream failed: {}", e))?;
+                let stream = limited.into_inner();
 
-            let _ = window.emit(
-                "transfer-progress",
-                TransferProgress {
-                    transfer_id: transfer_id.clone(),
-                    filename: remote_name.clone(),
-                    progress: downloaded,
-                    total: total_size,
-                    status: "complete".into(),
-                },
-            );
+                if buf.len() > budget {
+                    buf.truncate(budget);
+                    timeout(Duration::from_secs(10), client.abort(stream))
+                        .await
+                        .map_err(|_| "ABOR timed out".to_string())?
+                        .map_err(|e| ftp_failure("ABOR failed", e))?;
+                } else {
+                    timeout(Duration::from_secs(10), client.finalize_retr_stream(stream))
+                        .await
+                        .map_err(|_| "Finalize timed out".to_string())?
+                        .map_err(|e| ftp_failure("Finalize failed", e))?;
+                }
+                Ok(buf)
+            }
+            .await;
 
-            return Ok(format!("Downloaded {}", remote_name));
+            // A dead session fails every remaining file, so let `with_reconnect` retry the batch
+            if let Err(e) = &result {
+                if is_connection_error(e) {
+                    return Err(e.clone());
+                }
+            }
+            if let Ok(bytes) = &result {
+                total += bytes.len();
+            }
+            results.push((path.clone(), result));
         }
-    }
-    Err("No active FTP connection".into())
+
+        Ok(results)
+    })
 }
 
+/// Reads several small remote files into memory over the one control
+/// connection, each capped at `max_bytes_each` and all together at
+/// `MEMORY_BATCH_LIMIT`. Failures are reported per file.
 #[tauri::command]
-pub async fn upload_file(
-    window: Window,
+pub async fn download_remote_files_to_memory(
     state: State<'_, FtpState>,
-    local_path: String,
-    remote_name: String,
-) -> Result<String, String> {
-    let transfer_id = format!("ul-{}", uuid::Uuid::new_v4());
+    paths: Vec<String>,
+    max_bytes_each: usize,
+) -> Result<Vec<(String, Result<Vec<u8>, String>)>, String> {
+    let state = state.inner();
+    with_reconnect(state, || {
+        download_remote_files_to_memory_inner(state, paths.clone(), max_bytes_each)
+    })
+    .await
+}
 
-    let file = tokio::fs::File::open(&local_path)
+async fn copy_remote_inner(
+    window: &Window,
+    state: &FtpState,
+    src: String,
+    dst: String,
+) -> Result<String, String> {
+    // One control connection carries one transfer at a time, so the copy is
+    // uploaded over a second session to the same server while it downloads
+    let config = state
+        .last_config
+        .lock()
         .await
-        .map_err(|e| format!("Read failed: {}", e))?;
-    let metadata = file.metadata().await.map_err(|e| e.to_string())?;
-    let total_size = metadata.len();
+        .clone()
+        .ok_or_else(|| format!("{}: No active FTP connection", NOT_CONNECTED))?;
+    let sink = FtpState::default();
+    sink.set_policy(state.policy());
+    connect_with_config(&sink, config)
+        .await
+        .map_err(|e| format!("Could not open a second session for the copy: {}", e))?;
+    let result = pipe_remote_copy(window, state, &sink, &src, &dst).await;
+    close_sessions(&sink).await;
+    result
+}
 
-    // Try secure client first
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let data = std::fs::read(&local_path).map_err(|e| e.to_string())?;
-            let mut cursor = std::io::Cursor::new(data);
+/// Streams `src` from the session in `state` into `dst` on `sink`, chunk by
+/// chunk. A relative `dst` is taken relative to the current directory of `state`.
+async fn pipe_remote_copy(
+    window: &Window,
+    state: &FtpState,
+    sink: &FtpState,
+    src: &str,
+    dst: &str,
+) -> Result<String, String> {
+    let transfer_id = format!("cp-{}", uuid::Uuid::new_v4());
+    let registry = window.state::<TransferRegistry>();
+    let _active = registry.track(&transfer_id);
+    let emit_progress = |progress: u64, total: u64, status: &str| {
+        let _ = window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: transfer_id.clone(),
+                filename: dst.to_string(),
+                progress,
+                total,
+                status: status.into(),
+                batch_id: None,
+            },
+        );
+    };
+    let throttle = ProgressThrottle::default();
 
-            timeout(
-                Duration::from_secs(60),
-                client.put_file(&remote_name, &mut cursor),
-            )
+    with_active_client!(state, |source| {
+        let total_size = timeout(Duration::from_secs(5), source.size(src))
             .await
-            .map_err(|_| "Upload timed out".to_string())?
-            .map_err(|e| format!("Upload failed: {}", e))?;
+            .map_err(|_| "SIZE timed out".to_string())?
+            .unwrap_or(0) as u64;
+        let cwd = source.pwd().await.unwrap_or_else(|_| "/".to_string());
+        let dst_path = absolute_remote_path(&cwd, dst);
 
-            let _ = window.emit(
-                "transfer-progress",
-                TransferProgress {
-                    transfer_id: transfer_id.clone(),
-                    filename: remote_name.clone(),
-                    progress: total_size,
-                    total: total_size,
-                    status: "complete".into(),
-                },
-            );
+        let mut reader = timeout(Duration::from_secs(10), source.retr_as_stream(src))
+            .await
+            .map_err(|_| data_connection_blocked("Download", "timed out"))?
+            .map_err(|e| data_command_error("Download", e))?;
 
-            return Ok(format!("Uploaded {}", remote_name));
-        }
-    }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let data = std::fs::read(&local_path).map_err(|e| e.to_string())?;
-            let mut cursor = std::io::Cursor::new(data);
+        with_active_client!(sink, |target| {
+            let mut writer =
+                match timeout(Duration::from_secs(10), target.put_with_stream(&dst_path)).await {
+                    Ok(Ok(writer)) => writer,
+                    failed => {
+                        let _ = timeout(Duration::from_secs(10), source.abort(reader)).await;
+                        return Err(match failed {
+                            Ok(Err(e)) => data_command_error("Upload", e),
+                            _ => data_connection_blocked("Upload", "timed out"),
+                        });
+                    }
+                };
 
-            timeout(
-                Duration::from_secs(60),
-                client.put_file(&remote_name, &mut cursor),
-            )
-            .await
-            .map_err(|_| "Upload timed out".to_string())?
-            .map_err(|e| format!("Upload failed: {}", e))?;
+            let mut buffer = vec![0u8; crate::config::buffer_size(window)];
+            let mut copied = 0u64;
+            loop {
+                let step = if registry.is_cancelled(&transfer_id) {
+                    Err(format!("Copy of {} was cancelled", src))
+                } else {
+                    match reader.read(&mut buffer).await {
+                        Ok(0) => break,
+                        Ok(n) => writer
+                            .write_all(&buffer[..n])
+                            .await
+                            .map(|_| n)
+                            .map_err(|e| format!("Upload failed: {}", e)),
+                        Err(e) => Err(format!("Read stream failed: {}", e)),
+                    }
+                };
+                let n = match step {
+                    Ok(n) => n,
+                    Err(e) => {
+                        let _ = timeout(Duration::from_secs(10), source.abort(reader)).await;
+                        let _ = timeout(Duration::from_secs(10), target.abort(writer)).await;
+                        // Don't leave a truncated copy behind
+                        let _ = timeout(Duration::from_secs(5), target.rm(&dst_path)).await;
+                        let status = if registry.is_cancelled(&transfer_id) {
+                            "cancelled"
+                        } else {
+                            "failed"
+                        };
+                        emit_progress(copied, total_size, status);
+                        return Err(e);
+                    }
+                };
+                copied += n as u64;
+                if throttle.ready() {
+                    emit_progress(copied, total_size, "copying");
+                }
+            }
 
-            let _ = window.emit(
-                "transfer-progress",
-                TransferProgress {
-                    transfer_id: transfer_id.clone(),
-                    filename: remote_name.clone(),
-                    progress: total_size,
-                    total: total_size,
-                    status: "complete".into(),
-                },
-            );
+            timeout(Duration::from_secs(10), source.finalize_retr_stream(reader))
+                .await
+                .map_err(|_| "Finalize timed out".to_string())?
+                .map_err(|e| ftp_failure("Finalize failed", e))?;
+            timeout(Duration::from_secs(10), target.finalize_put_stream(writer))
+                .await
+                .map_err(|_| "Finalize timed out".to_string())?
+                .map_err(|e| ftp_failure("Finalize failed", e))?;
 
-            return Ok(format!("Uploaded {}", remote_name));
-        }
-    }
-    Err("No active FTP connection".into())
+            emit_progress(copied, total_size, "complete");
+            Ok(format!("Copied {} to {}", src, dst))
+        })
+    })
 }
 
+/// Duplicates a remote file on the same server (FTP has no server-side copy).
 #[tauri::command]
-pub async fn delete_remote_file(
+pub async fn copy_remote(
+    window: Window,
     state: State<'_, FtpState>,
-    path: String,
+    src: String,
+    dst: String,
 ) -> Result<String, String> {
-    // Try secure client
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.rm(&path))
-                .await
-                .map_err(|_| "Delete timed out".to_string())?
-                .map_err(|e| format!("Delete failed: {}", e))?;
-            return Ok(format!("Deleted file: {}", path));
+    let state = state.inner();
+    let result = with_reconnect_once(
+        state,
+        copy_remote_inner(&window, state, src.clone(), dst.clone()),
+    )
+    .await;
+    invalidate_listings(state, &[&dst]).await;
+    result
+}
+
+/// `Remote` over the active FTP session. Paths are remote paths; a bare name
+/// is taken relative to the current directory.
+pub struct FtpRemote<'w> {
+    window: &'w Window,
+    state: &'w FtpState,
+}
+
+impl<'w> FtpRemote<'w> {
+    pub fn new(window: &'w Window) -> Self {
+        Self {
+            window,
+            state: window.state::<FtpState>().inner(),
         }
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.rm(&path))
-                .await
-                .map_err(|_| "Delete timed out".to_string())?
-                .map_err(|e| format!("Delete failed: {}", e))?;
-            return Ok(format!("Deleted file: {}", path));
-        }
+}
+
+/// The directory holding `path`, or "" for a bare name.
+fn remote_parent(path: &str) -> &str {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", _)) => "/",
+        Some((parent, _)) => parent,
+        None => "",
     }
-    Err("No active FTP connection".into())
 }
 
-#[tauri::command]
-pub async fn delete_remote_dir(state: State<'_, FtpState>, path: String) -> Result<String, String> {
-    // Note: rmdir usually only works if the directory is empty.
-    // For recursive deletion, a more complex approach is needed
-    // (listing contents and deleting recursively) but this is a starting point.
-    // Try secure client
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.rmdir(&path))
-                .await
-                .map_err(|_| "Delete timed out".to_string())?
-                .map_err(|e| format!("Delete generic failed (directory must be empty): {}", e))?;
-            return Ok(format!("Deleted directory: {}", path));
-        }
+impl Remote for FtpRemote<'_> {
+    fn list<'a>(
+        &'a self,
+        path: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<RemoteEntry>, String>> {
+        Box::pin(async move {
+            let state = self.state;
+            let entries = with_reconnect(state, || {
+                list_remote_directory_inner(state, path.map(String::from))
+            })
+            .await?;
+            // The listing leaves the session in the listed directory
+            let base = with_reconnect(state, || get_remote_pwd_inner(state)).await?;
+            Ok(entries
+                .into_iter()
+                .map(|e| RemoteEntry {
+                    path: join_remote_path(&base, &e.name),
+                    size: (!e.is_dir).then_some(e.size),
+                    modified: e.modified_iso,
+                    name: e.name,
+                    is_dir: e.is_dir,
+                })
+                .collect())
+        })
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.rmdir(&path))
-                .await
-                .map_err(|_| "Delete timed out".to_string())?
-                .map_err(|e| format!("Delete genric failed (directory must be empty): {}", e))?;
-            return Ok(format!("Deleted directory: {}", path));
-        }
+
+    fn download<'a>(
+        &'a self,
+        path: &'a str,
+        local_path: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let state = self.state;
+            with_reconnect(state, || {
+                download_remote_file_inner(
+                    self.window,
+                    state,
+                    path.to_string(),
+                    local_path.to_string(),
+                    TransferType::Auto,
+                )
+            })
+            .await
+        })
     }
-    Err("No active FTP connection".into())
-}
 
-#[tauri::command]
-pub async fn rename_remote_file(
-    state: State<'_, FtpState>,
-    old_path: String,
-    new_path: String,
-) -> Result<String, String> {
-    // Try secure client
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.rename(&old_path, &new_path))
-                .await
-                .map_err(|_| "Rename timed out".to_string())?
-                .map_err(|e| format!("Rename failed: {}", e))?;
-            return Ok(format!("Renamed {} to {}", old_path, new_path));
-        }
+    fn upload<'a>(
+        &'a self,
+        local_path: &'a str,
+        parent: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let state = self.state;
+            let file_name = std::path::Path::new(local_path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .ok_or_else(|| format!("No file name in {}", local_path))?;
+            let remote_name = join_remote_path(parent.unwrap_or(""), file_name);
+            let result = with_reconnect_once(
+                state,
+                upload_file_inner(
+                    self.window,
+                    state,
+                    local_path.to_string(),
+                    remote_name.clone(),
+                    false,
+                    TransferType::Auto,
+                ),
+            )
+            .await;
+            invalidate_listings(state, &[&remote_name]).await;
+            result
+        })
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.rename(&old_path, &new_path))
-                .await
-                .map_err(|_| "Rename timed out".to_string())?
-                .map_err(|e| format!("Rename failed: {}", e))?;
-            return Ok(format!("Renamed {} to {}", old_path, new_path));
-        }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let state = self.state;
+            let path = path.to_string();
+            // The path doesn't say whether it is a file, so try a directory when DELE refuses
+            let result =
+                match with_reconnect_once(state, delete_remote_file_inner(state, path.clone()))
+                    .await
+                {
+                    Ok(done) => Ok(done),
+                    Err(file_err) => {
+                        with_reconnect_once(state, delete_remote_dir_inner(state, path.clone()))
+                            .await
+                            .map_err(|_| file_err)
+                    }
+                };
+            invalidate_listings(state, &[&path]).await;
+            result
+        })
     }
-    Err("No active FTP connection".into())
-}
 
-#[tauri::command]
-pub async fn create_remote_dir(state: State<'_, FtpState>, path: String) -> Result<String, String> {
-    // Try secure client
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.mkdir(&path))
+    fn mkdir<'a>(
+        &'a self,
+        parent: Option<&'a str>,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let state = self.state;
+            let path = join_remote_path(parent.unwrap_or(""), name);
+            let result = with_reconnect_once(state, create_remote_dir_inner(state, path.clone()))
                 .await
-                .map_err(|_| "Mkdir timed out".to_string())?
-                .map_err(|e| format!("Mkdir failed: {}", e))?;
-            return Ok(format!("Created directory: {}", path));
-        }
+                .map(|_| path.clone());
+            invalidate_listings(state, &[&path]).await;
+            result
+        })
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            timeout(Duration::from_secs(5), client.mkdir(&path))
-                .await
-                .map_err(|_| "Mkdir timed out".to_string())?
-                .map_err(|e| format!("Mkdir failed: {}", e))?;
-            return Ok(format!("Created directory: {}", path));
-        }
+
+    fn rename<'a>(
+        &'a self,
+        path: &'a str,
+        new_name: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let state = self.state;
+            let new_path = join_remote_path(remote_parent(path), new_name);
+            let result = with_reconnect_once(
+                state,
+                rename_remote_file_inner(state, path.to_string(), new_path.clone()),
+            )
+            .await;
+            invalidate_listings(state, &[path, &new_path]).await;
+            result
+        })
     }
-    Err("No active FTP connection".into())
 }
 
-#[async_recursion::async_recursion]
-async fn recursive_download_secure(
-    client: &mut SecureStream,
-    remote_dir: &str,
-    local_dir: &std::path::Path,
-) -> Result<u64, String> {
-    use tokio::io::AsyncReadExt;
+/// Round trips against a real server. They run only when `QUICKSYNC_TEST_FTP`
+/// is set to `user:password@host:port`, e.g. for a throwaway container:
+///
+/// ```text
+/// docker run -d -p 2121:21 -p 30000-30009:30000-30009 \
+///     -e FTP_USER_NAME=test -e FTP_USER_PASS=test -e FTP_USER_HOME=/home/test \
+///     -e PUBLICHOST=127.0.0.1 stilliard/pure-ftpd
+/// QUICKSYNC_TEST_FTP=test:test@127.0.0.1:2121 cargo test
+/// ```
+///
+/// Each test works in its own `quicksync-test-*` folder and removes it again.
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if !local_dir.exists() {
-        std::fs::create_dir_all(local_dir)
-            .map_err(|e| format!("Failed to create local dir: {}", e))?;
+    fn test_server() -> Option<FtpConfigPayload> {
+        server_from_env("QUICKSYNC_TEST_FTP", false)
     }
 
-    client
-        .cwd(remote_dir)
-        .await
-        .map_err(|e| format!("CWD failed to {}: {}", remote_dir, e))?;
-    let lines = client
-        .list(None)
-        .await
-        .map_err(|e| format!("LIST failed in {}: {}", remote_dir, e))?;
-
-    let mut total_bytes = 0;
+    /// An FTPS server that requires data connections to reuse the control
+    /// connection's TLS session, such as vsftpd with `require_ssl_reuse=YES`.
+    fn test_tls_reuse_server() -> Option<FtpConfigPayload> {
+        server_from_env("QUICKSYNC_TEST_FTPS_REUSE", true)
+    }
 
-    let mut entries = Vec::new();
-    for l in lines {
-        if let Some(entry) = parse_list_line(&l) {
-            entries.push(entry);
-        }
+    /// Reads `user:password@host:port` from the environment variable `var`.
+    fn server_from_env(var: &str, secure: bool) -> Option<FtpConfigPayload> {
+        let spec = std::env::var(var).ok()?;
+        let (credentials, address) = spec.rsplit_once('@')?;
+        let (username, password) = credentials.split_once(':')?;
+        let (host, port) = address.rsplit_once(':')?;
+        serde_json::from_value(serde_json::json!({
+            "host": host,
+            "port": port.parse::<u16>().ok()?,
+            "username": username,
+            "password": password,
+            "secure": secure,
+        }))
+        .ok()
     }
 
-    for entry in entries {
-        let entry_remote_path = format!("{}/{}", remote_dir, entry.name);
-        let entry_local_path = local_dir.join(&entry.name);
+    /// A connected session and an absolute path for a fresh test folder.
+    async fn connect(config: FtpConfigPayload) -> (FtpState, String) {
+        let state = FtpState::default();
+        connect_with_config(&state, config).await.unwrap();
+        let root = get_remote_pwd_inner(&state).await.unwrap();
+        let dir = join_remote_path(&root, &format!("quicksync-test-{}", uuid::Uuid::new_v4()));
+        (state, dir)
+    }
 
-        if entry.is_dir {
-            total_bytes +=
-                recursive_download_secure(client, &entry_remote_path, &entry_local_path).await?;
+    async fn put(state: &FtpState, path: &str, data: &[u8]) -> Result<(), String> {
+        with_active_client!(state, |client| {
             client
-                .cwd(remote_dir)
+                .transfer_type(FileType::Binary)
                 .await
-                .map_err(|e| format!("CWD failed returning to {}: {}", remote_dir, e))?;
-        } else {
+                .map_err(|e| e.to_string())?;
             let mut stream = client
-                .retr_as_stream(&entry.name)
-                .await
-                .map_err(|e| format!("Download failed for {}: {}", entry.name, e))?;
-            let mut buf = Vec::new();
-            stream
-                .read_to_end(&mut buf)
+                .put_with_stream(path)
                 .await
-                .map_err(|e| format!("Read stream failed for {}: {}", entry.name, e))?;
+                .map_err(|e| e.to_string())?;
+            stream.write_all(data).await.map_err(|e| e.to_string())?;
             client
-                .finalize_retr_stream(stream)
+                .finalize_put_stream(stream)
                 .await
-                .map_err(|e| format!("Finalize failed for {}: {}", entry.name, e))?;
+                .map_err(|e| e.to_string())
+        })
+    }
 
-            std::fs::write(&entry_local_path, &buf)
-                .map_err(|e| format!("Save failed for {}: {}", entry.name, e))?;
-            total_bytes += buf.len() as u64;
-        }
+    async fn list(state: &FtpState, dir: &str) -> Vec<RemoteFileEntry> {
+        state.listing_cache.clear();
+        list_remote_directory_inner(state, Some(dir.to_string()))
+            .await
+            .unwrap()
     }
 
-    Ok(total_bytes)
-}
+    #[test]
+    fn file_round_trip() {
+        let Some(config) = test_server() else {
+            eprintln!("QUICKSYNC_TEST_FTP not set, skipping");
+            return;
+        };
+        tauri::async_runtime::block_on(async {
+            let (state, dir) = connect(config).await;
+            create_remote_dir_inner(&state, dir.clone()).await.unwrap();
+
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let file = join_remote_path(&dir, "data file.bin");
+            put(&state, &file, &data).await.unwrap();
+
+            let entries = list(&state, &dir).await;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, "data file.bin");
+            assert!(!entries[0].is_dir);
+            assert_eq!(entries[0].size, data.len() as u64);
+            assert!(entries[0].modified_iso.is_some());
+
+            assert_eq!(
+                remote_file_size(&state, &file).await.unwrap(),
+                data.len() as u64
+            );
+            let whole = read_remote_range(&state, &file, 0, data.len() as u64)
+                .await
+                .unwrap();
+            assert_eq!(whole, data);
+            // A resumed read starts at the REST offset and stops at the range end
+            let middle = read_remote_range(&state, &file, 4_000, 100).await.unwrap();
+            assert_eq!(middle, &data[4_000..4_100]);
+            let tail = read_remote_range(&state, &file, 9_990, 100).await.unwrap();
+            assert_eq!(tail, &data[9_990..]);
 
-#[async_recursion::async_recursion]
-async fn recursive_download_plain(
-    client: &mut PlainStream,
-    remote_dir: &str,
-    local_dir: &std::path::Path,
-) -> Result<u64, String> {
-    use tokio::io::AsyncReadExt;
+            let renamed = join_remote_path(&dir, "renamed.bin");
+            rename_remote_file_inner(&state, file.clone(), renamed.clone())
+                .await
+                .unwrap();
+            let names: Vec<String> = list(&state, &dir)
+                .await
+                .into_iter()
+                .map(|e| e.name)
+                .collect();
+            assert_eq!(names, ["renamed.bin"]);
 
-    if !local_dir.exists() {
-        std::fs::create_dir_all(local_dir)
-            .map_err(|e| format!("Failed to create local dir: {}", e))?;
+            delete_remote_file_inner(&state, renamed).await.unwrap();
+            assert!(list(&state, &dir).await.is_empty());
+            delete_remote_dir_inner(&state, dir.clone()).await.unwrap();
+            let (parent, name) = dir.rsplit_once('/').unwrap();
+            let parent = if parent.is_empty() { "/" } else { parent };
+            assert!(list(&state, parent).await.iter().all(|e| e.name != name));
+        });
     }
 
-    client
-        .cwd(remote_dir)
-        .await
-        .map_err(|e| format!("CWD failed to {}: {}", remote_dir, e))?;
-    let lines = client
-        .list(None)
-        .await
-        .map_err(|e| format!("LIST failed in {}: {}", remote_dir, e))?;
+    #[test]
+    fn tls_data_connections_resume_the_control_session() {
+        let Some(config) = test_tls_reuse_server() else {
+            eprintln!("QUICKSYNC_TEST_FTPS_REUSE not set, skipping");
+            return;
+        };
+        tauri::async_runtime::block_on(async {
+            let (state, dir) = connect(config).await;
+            create_remote_dir_inner(&state, dir.clone()).await.unwrap();
 
-    let mut total_bytes = 0;
+            // Each of these opens a TLS data connection the server only accepts
+            // when it resumes the control connection's session
+            let file = join_remote_path(&dir, "secure.bin");
+            put(&state, &file, b"over tls").await.unwrap();
+            let names: Vec<String> = list(&state, &dir)
+                .await
+                .into_iter()
+                .map(|e| e.name)
+                .collect();
+            assert_eq!(names, ["secure.bin"]);
+            assert_eq!(
+                read_remote_range(&state, &file, 0, 8).await.unwrap(),
+                b"over tls"
+            );
 
-    let mut entries = Vec::new();
-    for l in lines {
-        if let Some(entry) = parse_list_line(&l) {
-            entries.push(entry);
-        }
+            delete_remote_file_inner(&state, file).await.unwrap();
+            delete_remote_dir_inner(&state, dir).await.unwrap();
+        });
     }
 
-    for entry in entries {
-        let entry_remote_path = format!("{}/{}", remote_dir, entry.name);
-        let entry_local_path = local_dir.join(&entry.name);
+    #[test]
+    fn empty_upload_creates_empty_file() {
+        let Some(config) = test_server() else {
+            eprintln!("QUICKSYNC_TEST_FTP not set, skipping");
+            return;
+        };
+        tauri::async_runtime::block_on(async {
+            let (state, dir) = connect(config).await;
+            create_remote_dir_inner(&state, dir.clone()).await.unwrap();
+            let file = join_remote_path(&dir, "empty.txt");
+            put(&state, &file, &[]).await.unwrap();
 
-        if entry.is_dir {
-            total_bytes +=
-                recursive_download_plain(client, &entry_remote_path, &entry_local_path).await?;
-            client
-                .cwd(remote_dir)
+            assert_eq!(remote_file_size(&state, &file).await.unwrap(), 0);
+            let entries = list(&state, &dir).await;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].size, 0);
+
+            delete_remote_file_inner(&state, file).await.unwrap();
+            delete_remote_dir_inner(&state, dir).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn nested_dirs_and_tree_size() {
+        let Some(config) = test_server() else {
+            eprintln!("QUICKSYNC_TEST_FTP not set, skipping");
+            return;
+        };
+        tauri::async_runtime::block_on(async {
+            let (state, dir) = connect(config).await;
+            let nested = join_remote_path(&dir, "a/b/c");
+            create_remote_dir_all_inner(&state, nested.clone())
                 .await
-                .map_err(|e| format!("CWD failed returning to {}: {}", remote_dir, e))?;
-        } else {
-            let mut stream = client
-                .retr_as_stream(&entry.name)
+                .unwrap();
+            // Existing levels are fine the second time
+            create_remote_dir_all_inner(&state, nested.clone())
                 .await
-                .map_err(|e| format!("Download failed for {}: {}", entry.name, e))?;
-            let mut buf = Vec::new();
-            stream
-                .read_to_end(&mut buf)
+                .unwrap();
+            put(&state, &join_remote_path(&dir, "a/one.txt"), &[1; 300])
                 .await
-                .map_err(|e| format!("Read stream failed for {}: {}", entry.name, e))?;
-            client
-                .finalize_retr_stream(stream)
+                .unwrap();
+            put(&state, &join_remote_path(&nested, "two.txt"), &[2; 700])
                 .await
-                .map_err(|e| format!("Finalize failed for {}: {}", entry.name, e))?;
+                .unwrap();
 
-            std::fs::write(&entry_local_path, &buf)
-                .map_err(|e| format!("Save failed for {}: {}", entry.name, e))?;
-            total_bytes += buf.len() as u64;
-        }
+            let entries = list(&state, &join_remote_path(&dir, "a")).await;
+            let mut kinds: Vec<(String, bool)> =
+                entries.into_iter().map(|e| (e.name, e.is_dir)).collect();
+            kinds.sort();
+            assert_eq!(
+                kinds,
+                [("b".to_string(), true), ("one.txt".to_string(), false)]
+            );
+            assert_eq!(
+                get_remote_dir_size_inner(&state, dir.clone())
+                    .await
+                    .unwrap(),
+                1_000
+            );
+
+            delete_remote_file_inner(&state, join_remote_path(&nested, "two.txt"))
+                .await
+                .unwrap();
+            delete_remote_file_inner(&state, join_remote_path(&dir, "a/one.txt"))
+                .await
+                .unwrap();
+            for level in ["a/b/c", "a/b", "a", ""] {
+                delete_remote_dir_inner(&state, join_remote_path(&dir, level))
+                    .await
+                    .unwrap();
+            }
+        });
     }
 
-    Ok(total_bytes)
-}
+    #[test]
+    fn resumed_upload_and_rename_through_commands() {
+        let Some(config) = test_server() else {
+            eprintln!("QUICKSYNC_TEST_FTP not set, skipping");
+            return;
+        };
+        let app = tauri::test::mock_app();
+        app.manage(FtpState::default());
+        app.manage(TransferRegistry::default());
+        let window = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap()
+            .as_ref()
+            .window();
+        tauri::async_runtime::block_on(async {
+            connect_ftp(app.state(), config).await.unwrap();
+            let root = get_remote_pwd(app.state()).await.unwrap();
+            let id = uuid::Uuid::new_v4();
+            let dir = join_remote_path(&root, &format!("quicksync-test-{}", id));
+            create_remote_dir(app.state(), dir.clone()).await.unwrap();
 
-#[tauri::command]
-pub async fn download_remote_folder(
-    state: State<'_, FtpState>,
-    remote_dir: String,
-    local_dir: String,
-) -> Result<String, String> {
-    let local_path = std::path::Path::new(&local_dir);
+            let local_dir = std::env::temp_dir().join(format!("quicksync-test-{}", id));
+            std::fs::create_dir_all(&local_dir).unwrap();
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let source = local_dir.join("source.bin");
+            std::fs::write(&source, &data).unwrap();
+            let source = source.to_string_lossy().to_string();
+            let copy = local_dir.join("copy.bin").to_string_lossy().to_string();
+            let file = join_remote_path(&dir, "partial.bin");
 
-    // Try secure client
-    {
-        let mut lock = state.secure_client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
+            // What an interrupted upload left behind, marked so that starting
+            // over instead of appending would show
+            put(app.state::<FtpState>().inner(), &file, &[0xAA; 4_000])
+                .await
+                .unwrap();
+            upload_file(
+                window.clone(),
+                app.state(),
+                source.clone(),
+                file.clone(),
+                Some(true),
+                None,
+            )
+            .await
+            .unwrap();
+            download_remote_file(
+                window.clone(),
+                app.state(),
+                file.clone(),
+                copy.clone(),
+                None,
+            )
+            .await
+            .unwrap();
+            let resumed = std::fs::read(&copy).unwrap();
+            assert_eq!(resumed.len(), data.len());
+            assert_eq!(&resumed[..4_000], &[0xAA; 4_000][..]);
+            assert_eq!(&resumed[4_000..], &data[4_000..]);
 
-            let absolute_remote = if remote_dir.starts_with('/') {
-                remote_dir.clone()
-            } else {
-                let sep = if orig_cwd.ends_with('/') { "" } else { "/" };
-                format!("{}{}{}", orig_cwd, sep, remote_dir)
-            };
+            // Without resume the whole file is replaced
+            upload_file(
+                window.clone(),
+                app.state(),
+                source.clone(),
+                file.clone(),
+                Some(false),
+                None,
+            )
+            .await
+            .unwrap();
 
-            let result = recursive_download_secure(client, &absolute_remote, local_path).await;
+            let renamed = join_remote_path(&dir, "renamed.bin");
+            rename_remote_file(app.state(), file, renamed.clone())
+                .await
+                .unwrap();
+            let entries = list_remote_directory(app.state(), Some(dir.clone()), None, None, None)
+                .await
+                .unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, "renamed.bin");
+            assert_eq!(entries[0].size, data.len() as u64);
+            download_remote_file(
+                window.clone(),
+                app.state(),
+                renamed.clone(),
+                copy.clone(),
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(std::fs::read(&copy).unwrap(), data);
 
-            let _ = client.cwd(&orig_cwd).await;
+            delete_remote_file(app.state(), renamed).await.unwrap();
+            delete_remote_dir(app.state(), dir).await.unwrap();
+            std::fs::remove_dir_all(&local_dir).unwrap();
+        });
+    }
 
-            let bytes = result?;
-            return Ok(format!(
-                "Downloaded folder '{}' ({} bytes)",
-                remote_dir, bytes
-            ));
+    #[test]
+    fn parses_unix_list_lines() {
+        let dialect = ListingDialect::default();
+        let file = parse_list_line(
+            "-rw-r--r--   1 user group 12345 Jan  1 12:00 my file.txt",
+            &dialect,
+        )
+        .unwrap();
+        assert_eq!(file.name, "my file.txt");
+        assert!(!file.is_dir);
+        assert_eq!(file.size, 12345);
+        assert_eq!(file.permissions, "-rw-r--r--");
+
+        let dir = parse_list_line(
+            "drwxr-xr-x   2 user group  4096 Mar 15  2021 photos",
+            &dialect,
+        )
+        .unwrap();
+        assert!(dir.is_dir);
+        assert_eq!(dir.modified_iso.as_deref(), Some("2021-03-15T00:00:00"));
+
+        let link = parse_list_line(
+            "lrwxrwxrwx   1 user group    9 Jan  1 12:00 www -> html/",
+            &dialect,
+        )
+        .unwrap();
+        assert_eq!(link.name, "www");
+        assert!(link.is_dir);
+        assert_eq!(link.symlink_target.as_deref(), Some("html/"));
+
+        assert!(
+            parse_list_line("drwxr-xr-x   2 user group  4096 Jan  1 12:00 ..", &dialect).is_none()
+        );
+        assert!(parse_list_line("total 12", &dialect).is_none());
+    }
+
+    #[test]
+    fn parses_localized_month_names() {
+        let dialect = ListingDialect::default();
+        let french =
+            parse_list_line("-rw-r--r--   1 user group 10 janv. 5  2020 a.txt", &dialect).unwrap();
+        assert_eq!(french.modified_iso.as_deref(), Some("2020-01-05T00:00:00"));
+        let german =
+            parse_list_line("-rw-r--r--   1 user group 10 Mär 15  2021 b.txt", &dialect).unwrap();
+        assert_eq!(german.modified_iso.as_deref(), Some("2021-03-15T00:00:00"));
+
+        // Names from the connection's config only apply to its own listings
+        let config: FtpConfigPayload = serde_json::from_value(serde_json::json!({
+            "host": "localhost",
+            "port": 21,
+            "username": "user",
+            "secure": false,
+            "month_names": ["sty", "lut", "mar", "kwi", "maj", "cze", "lip", "sie", "wrz", "paź", "lis", "gru"],
+        }))
+        .unwrap();
+        let polish = ListingDialect::from_config(&config).unwrap();
+        let line = "-rw-r--r--   1 user group 10 paź 3  2019 c.txt";
+        let entry = parse_list_line(line, &polish).unwrap();
+        assert_eq!(entry.modified_iso.as_deref(), Some("2019-10-03T00:00:00"));
+        assert_eq!(parse_list_line(line, &dialect).unwrap().modified_iso, None);
+    }
+
+    #[test]
+    fn parses_links_to_parent_directories_as_links() {
+        // Walks that descend into `is_dir` entries have to check `symlink_target`
+        // first, or a link like this one sends them round in circles
+        let dialect = ListingDialect::default();
+        let link = parse_list_line(
+            "lrwxrwxrwx   1 user group    3 Jan  1 12:00 current -> ../",
+            &dialect,
+        )
+        .unwrap();
+        assert_eq!(link.name, "current");
+        assert!(link.is_dir);
+        assert_eq!(link.symlink_target.as_deref(), Some("../"));
+    }
+
+    #[test]
+    fn reads_permissions_of_the_named_entry() {
+        let lines: Vec<String> = [
+            "-rw-r--r--   1 user group 10 Jan  1 12:00 notes.txt",
+            "drwxr-x---   2 user group 4096 Jan  1 12:00 private",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let dialect = ListingDialect::default();
+        assert_eq!(
+            octal_from_listing(&lines, "private", &dialect).unwrap(),
+            "750"
+        );
+        assert_eq!(
+            octal_from_listing(&lines, "notes.txt", &dialect).unwrap(),
+            "644"
+        );
+        assert!(octal_from_listing(&lines, "missing", &dialect).is_err());
+    }
+
+    #[test]
+    fn refuses_raw_commands_that_change_session_state() {
+        assert_eq!(check_raw_command("site chmod 644 a.txt").unwrap(), "SITE");
+        assert_eq!(check_raw_command("NOOP").unwrap(), "NOOP");
+        for command in [
+            "type a",
+            "CWD /tmp",
+            "REIN",
+            "PASV",
+            "PORT 1,2,3,4,5,6",
+            "pass x",
+        ] {
+            assert!(check_raw_command(command).is_err(), "{}", command);
         }
+        assert!(check_raw_command("RETR a.txt").is_err());
+        assert!(check_raw_command("NOOP\r\nDELE a.txt").is_err());
+
+        let logged = crate::secret::redact("> pass hunter2\n> ACCT billing-42\n", &[]);
+        assert_eq!(logged, "> pass ***\n> ACCT ***\n");
     }
-    // Try plain client
-    {
-        let mut lock = state.client.lock().await;
-        if let Some(ref mut client) = *lock {
-            let orig_cwd = client.pwd().await.unwrap_or_else(|_| "/".to_string());
 
-            let absolute_remote = if remote_dir.starts_with('/') {
-                remote_dir.clone()
-            } else {
-                let sep = if orig_cwd.ends_with('/') { "" } else { "/" };
-                format!("{}{}{}", orig_cwd, sep, remote_dir)
-            };
+    #[test]
+    fn path_filter_matches_nested_relative_paths() {
+        let filter = PathFilter::new(Some("*.jpg"), Some("**/raw")).unwrap();
+        assert!(filter.allows("a.jpg", false));
+        assert!(filter.allows("photos/2024/a.jpg", false));
+        assert!(!filter.allows("photos/2024/a.png", false));
+        // Include doesn't stop the walk from descending
+        assert!(filter.allows("photos/2024", true));
+        assert!(!filter.allows("raw", true));
+        assert!(!filter.allows("photos/raw", true));
 
-            let result = recursive_download_plain(client, &absolute_remote, local_path).await;
+        // Exclude wins over include
+        let filter = PathFilter::new(Some("*.jpg"), Some("*/thumbs/*")).unwrap();
+        assert!(!filter.allows("photos/thumbs/a.jpg", false));
+        assert!(filter.allows("photos/a.jpg", false));
 
-            let _ = client.cwd(&orig_cwd).await;
+        assert!(PathFilter::new(Some(""), None)
+            .unwrap()
+            .allows("a.png", false));
+        assert!(PathFilter::new(Some("a[.jpg"), None).is_err());
+    }
 
-            let bytes = result?;
-            return Ok(format!(
-                "Downloaded folder '{}' ({} bytes)",
-                remote_dir, bytes
-            ));
+    #[test]
+    fn joins_remote_paths() {
+        assert_eq!(join_remote_path("/", "a"), "/a");
+        assert_eq!(join_remote_path("/", ""), "/");
+        assert_eq!(join_remote_path("/home/", "a"), "/home/a");
+        assert_eq!(join_remote_path("/home//user/", "b/c/"), "/home/user/b/c");
+        assert_eq!(join_remote_path("/home/user", ""), "/home/user");
+        assert_eq!(join_remote_path("home", "a"), "home/a");
+        assert_eq!(join_remote_path("", "a"), "a");
+        assert_eq!(absolute_remote_path("/home", "/etc/"), "/etc");
+        assert_eq!(absolute_remote_path("/home", "docs"), "/home/docs");
+    }
+
+    #[test]
+    fn refuses_unsafe_names_from_listings() {
+        use crate::fs_commands::join_untrusted_name;
+        let dir = std::path::Path::new("downloads");
+        assert_eq!(
+            join_untrusted_name(dir, "a.txt").unwrap(),
+            dir.join("a.txt")
+        );
+        assert!(join_untrusted_name(dir, "..hidden").is_ok());
+        for name in [
+            "../../etc/passwd",
+            "..",
+            ".",
+            "",
+            "/etc/passwd",
+            "sub/a.txt",
+            "sub\\a.txt",
+            "..\\..\\boot.ini",
+        ] {
+            assert!(join_untrusted_name(dir, name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn filters_listings_to_directories_or_extensions() {
+        use crate::fs_commands::read_directory;
+        let root = std::env::temp_dir().join(format!("quicksync-listing-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("sub").join("nested")).unwrap();
+        for file in ["a.JPG", "b.txt", "sub/c.jpg"] {
+            std::fs::write(root.join(file), b"x").unwrap();
         }
+        let names = |dir: std::path::PathBuf, extensions: Option<Vec<String>>, dirs_only| {
+            let mut names: Vec<String> = read_directory(dir, None, extensions, Some(dirs_only))
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(root.clone(), None, true), ["sub"]);
+        // Subdirectories listed for a folder picker can be opened in turn
+        assert_eq!(names(root.join("sub"), None, true), ["nested"]);
+        assert_eq!(
+            names(root.clone(), Some(vec![".jpg".into()]), false),
+            ["a.JPG", "sub"]
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let dialect = ListingDialect::default();
+        let mut remote: Vec<RemoteFileEntry> = [
+            "-rw-r--r--   1 user group 10 Jan  1 12:00 a.jpg",
+            "-rw-r--r--   1 user group 10 Jan  1 12:00 b.TXT",
+            "drwxr-xr-x   2 user group 4096 Jan  1 12:00 docs",
+        ]
+        .iter()
+        .filter_map(|l| parse_list_line(l, &dialect))
+        .collect();
+        crate::sort::filter_extensions(&mut remote, &["TXT".to_string()]);
+        let names: Vec<&str> = remote.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["b.TXT", "docs"]);
     }
-    Err("No active FTP connection".into())
 }
@@ -1,5 +1,6 @@
+use std::time::Duration;
 use tauri::menu::{CheckMenuItemBuilder, Menu, MenuItemBuilder, Submenu};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_autostart::ManagerExt;
 use tauri_plugin_dialog::DialogExt;
@@ -8,6 +9,14 @@ pub mod cloud_client;
 pub mod config;
 pub mod fs_commands;
 mod ftp_client;
+mod listing_cache;
+mod logging;
+pub mod oauth;
+mod preview;
+pub mod remote;
+pub mod secret;
+mod sort;
+pub mod transfers;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -15,6 +24,45 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+#[derive(serde::Serialize)]
+struct AppInfo {
+    version: &'static str,
+    tauri_version: &'static str,
+    rustc_version: &'static str,
+    target_os: &'static str,
+    target_arch: &'static str,
+    debug_build: bool,
+    /// Remote backends compiled into this build; the frontend hides UI for the rest.
+    providers: Vec<&'static str>,
+    autostart: bool,
+}
+
+/// Build and version details for the About dialog and bug reports.
+#[tauri::command]
+fn get_app_info() -> AppInfo {
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        tauri_version: tauri::VERSION,
+        rustc_version: env!("RUSTC_VERSION"),
+        target_os: std::env::consts::OS,
+        target_arch: std::env::consts::ARCH,
+        debug_build: cfg!(debug_assertions),
+        providers: vec!["ftp", "ftps", "google", "dropbox"],
+        autostart: cfg!(not(any(target_os = "android", target_os = "ios"))),
+    }
+}
+
+/// Label for the autostart toggle, worded the way each OS describes it.
+fn autostart_label() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "Start with Windows"
+    } else if cfg!(target_os = "macos") {
+        "Open at Login"
+    } else {
+        "Start at login"
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -25,7 +73,13 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(ftp_client::FtpState::default())
+        .manage(cloud_client::CloudListingCache::default())
+        .manage(config::TransferSettings::default())
+        .manage(transfers::TransferRegistry::default())
+        .manage(fs_commands::LocalNav::default())
+        .manage(preview::PreviewServer::default())
         .setup(|app| {
             // Read saved config to set initial menu state
             let app_config = match config::load_config(app.handle().clone()) {
@@ -34,6 +88,46 @@ pub fn run() {
             };
             let is_light = app_config.theme.as_deref() == Some("light");
 
+            let log_level = app_config.log_level.as_deref().unwrap_or("info");
+            match logging::init(app.handle(), log_level) {
+                Ok(guard) => {
+                    app.manage(guard);
+                }
+                Err(e) => {
+                    logging::init_stderr(log_level);
+                    tracing::warn!(error = %e, "file logging unavailable, logging to stderr");
+                }
+            }
+
+            let cache_ttl = Duration::from_secs(app_config.listing_cache_ttl_secs);
+            app.state::<ftp_client::FtpState>()
+                .listing_cache
+                .set_ttl(cache_ttl);
+            app.state::<cloud_client::CloudListingCache>()
+                .set_ttl(cache_ttl);
+            app.state::<config::TransferSettings>().apply(&app_config);
+            app.state::<ftp_client::FtpState>()
+                .set_policy(app_config.transfer_policy.clone());
+            cloud_client::set_endpoints(app_config.cloud_endpoints.clone());
+            for conn in &app_config.cloud_connections {
+                conn.register();
+                // Saves a lookup per account before team folders show up
+                if conn.root_namespace_id.is_some() {
+                    cloud_client::set_dropbox_path_root(
+                        conn.access_token.expose(),
+                        conn.root_namespace_id.clone(),
+                    );
+                }
+            }
+            transfers::notify_on_completion(app.handle());
+            transfers::record_progress(app.handle());
+            ftp_client::keep_alive(app.handle());
+
+            let with_accel = |builder: MenuItemBuilder, id: &str| match app_config.shortcut(id) {
+                Some(accel) => builder.accelerator(accel),
+                None => builder,
+            };
+
             // Build CheckMenuItems
             let theme_light = CheckMenuItemBuilder::new("Light")
                 .id("theme_light")
@@ -47,16 +141,35 @@ pub fn run() {
             let autostart_manager = app.autolaunch();
             let is_autostart = autostart_manager.is_enabled().unwrap_or(false);
 
-            let autostart_item = CheckMenuItemBuilder::new("Start on Windows load")
+            let autostart_item = CheckMenuItemBuilder::new(autostart_label())
                 .id("autostart")
                 .checked(is_autostart)
                 .build(app)?;
 
+            let refresh_item = with_accel(MenuItemBuilder::new("Refresh"), "refresh")
+                .id("refresh")
+                .build(app)?;
+            let disconnect_item = with_accel(MenuItemBuilder::new("Disconnect"), "disconnect")
+                .id("disconnect")
+                .build(app)?;
+            let toggle_theme_item =
+                with_accel(MenuItemBuilder::new("Toggle Theme"), "toggle_theme")
+                    .id("toggle_theme")
+                    .build(app)?;
+
             // Build Submenus
-            let theme_submenu =
-                Submenu::with_items(app, "Theme", true, &[&theme_light, &theme_dark])?;
+            let theme_submenu = Submenu::with_items(
+                app,
+                "Theme",
+                true,
+                &[&theme_light, &theme_dark, &toggle_theme_item],
+            )?;
+
+            let view_submenu =
+                Submenu::with_items(app, "View", true, &[&refresh_item, &theme_submenu])?;
 
-            let view_submenu = Submenu::with_items(app, "View", true, &[&theme_submenu])?;
+            let connection_submenu =
+                Submenu::with_items(app, "Connection", true, &[&disconnect_item])?;
 
             let options_submenu = Submenu::with_items(app, "Options", true, &[&autostart_item])?;
 
@@ -64,7 +177,15 @@ pub fn run() {
             let help_submenu = Submenu::with_items(app, "Help", true, &[&about_item])?;
 
             // Build Main Menu
-            let menu = Menu::with_items(app, &[&view_submenu, &options_submenu, &help_submenu])?;
+            let menu = Menu::with_items(
+                app,
+                &[
+                    &view_submenu,
+                    &connection_submenu,
+                    &options_submenu,
+                    &help_submenu,
+                ],
+            )?;
 
             app.set_menu(menu)?;
 
@@ -78,17 +199,29 @@ pub fn run() {
                     let _ = theme_dark.set_checked(true);
                     let _ = theme_light.set_checked(false);
                     let _ = app_handle.emit("theme-changed", "dark");
+                } else if event.id() == "toggle_theme" {
+                    let to_light = !theme_light.is_checked().unwrap_or(false);
+                    let _ = theme_light.set_checked(to_light);
+                    let _ = theme_dark.set_checked(!to_light);
+                    let theme = if to_light { "light" } else { "dark" };
+                    let _ = app_handle.emit("theme-changed", theme);
+                } else if event.id() == "refresh" {
+                    let _ = app_handle.emit("menu-refresh", ());
+                } else if event.id() == "disconnect" {
+                    let _ = app_handle.emit("menu-disconnect", ());
                 } else if event.id() == "autostart" {
                     let autostart_manager = app_handle.autolaunch();
                     if let Ok(enabled) = autostart_manager.is_enabled() {
                         if enabled {
                             let _ = autostart_manager.disable();
-                            let _ = autostart_item.set_checked(false);
                         } else {
                             let _ = autostart_manager.enable();
-                            let _ = autostart_item.set_checked(true);
                         }
                     }
+                    // Re-read the real state so the checkbox never drifts from the OS
+                    // (e.g. when writing the LaunchAgent or .desktop entry failed).
+                    let is_enabled = autostart_manager.is_enabled().unwrap_or(false);
+                    let _ = autostart_item.set_checked(is_enabled);
                 } else if event.id() == "about" {
                     app_handle
                         .dialog()
@@ -102,29 +235,112 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             greet,
+            get_app_info,
             config::load_config,
             config::save_config,
+            config::get_config_dir,
+            config::open_config_dir,
+            config::update_cloud_token,
+            config::update_ftp_password,
+            config::duplicate_connection,
+            config::get_transfer_policy,
+            config::set_transfer_policy,
+            config::add_quick_path,
+            config::remove_quick_path,
+            config::list_quick_paths,
             ftp_client::connect_ftp,
+            ftp_client::connect_saved_ftp,
             ftp_client::disconnect_ftp,
             ftp_client::list_remote_directory,
             ftp_client::get_remote_pwd,
+            ftp_client::remote_cd,
+            ftp_client::remote_cd_up,
+            ftp_client::remote_pwd,
             ftp_client::download_remote_file,
+            ftp_client::download_remote_file_default,
             ftp_client::upload_file,
+            ftp_client::upload_bytes,
+            ftp_client::upload_file_compressed,
+            ftp_client::abort_data_transfer,
             ftp_client::delete_remote_file,
             ftp_client::delete_remote_dir,
             ftp_client::rename_remote_file,
             ftp_client::create_remote_dir,
+            ftp_client::create_remote_dir_all,
             ftp_client::download_remote_folder,
+            ftp_client::cancel_folder_download,
+            ftp_client::get_remote_dir_size,
+            ftp_client::prune_remote,
+            ftp_client::diff_directories,
+            ftp_client::diff_remotes,
+            ftp_client::get_connection_info,
+            ftp_client::list_remote_tree,
+            ftp_client::get_remote_permissions,
+            ftp_client::set_remote_permissions,
+            ftp_client::set_remote_mtime,
+            ftp_client::benchmark_connection,
+            ftp_client::download_remote_range,
+            ftp_client::download_remote_files_to_memory,
+            ftp_client::copy_remote,
+            ftp_client::get_server_welcome,
+            ftp_client::send_site_command,
+            ftp_client::send_raw_command,
             fs_commands::list_directory,
+            fs_commands::list_directory_stream,
+            fs_commands::find_duplicates,
+            fs_commands::get_local_cwd,
+            fs_commands::set_local_cwd,
+            fs_commands::go_up_local_cwd,
             fs_commands::get_home_dir,
+            fs_commands::get_special_dirs,
             fs_commands::get_file_icon,
             fs_commands::copy_to_local,
             fs_commands::delete_local_file,
+            fs_commands::get_free_space,
+            fs_commands::is_writable,
+            fs_commands::reveal_in_file_manager,
+            fs_commands::copy_path_to_clipboard,
             cloud_client::list_cloud_directory,
+            cloud_client::prefetch_cloud_tree,
             cloud_client::download_cloud_file,
             cloud_client::upload_cloud_file,
-            cloud_client::delete_cloud_file
+            cloud_client::upload_cloud_bytes,
+            cloud_client::delete_cloud_file,
+            cloud_client::get_cloud_changes,
+            cloud_client::benchmark_cloud_connection,
+            cloud_client::download_cloud_folder,
+            cloud_client::create_cloud_folder,
+            cloud_client::rename_cloud_file,
+            cloud_client::resolve_cloud_path,
+            cloud_client::list_cloud_revisions,
+            cloud_client::restore_cloud_revision,
+            cloud_client::list_cloud_trash,
+            cloud_client::restore_cloud_file,
+            cloud_client::empty_cloud_trash,
+            cloud_client::get_dropbox_root_namespace,
+            cloud_client::upload_cloud_folder,
+            listing_cache::invalidate_cache,
+            logging::get_log_path,
+            logging::open_log_folder,
+            oauth::start_oauth_flow,
+            preview::start_preview_server,
+            preview::stop_preview_server,
+            remote::remote_list,
+            remote::remote_download,
+            remote::remote_upload,
+            remote::remote_delete,
+            remote::remote_mkdir,
+            remote::remote_rename,
+            transfers::cancel_cloud_transfer,
+            transfers::cancel_all_transfers,
+            transfers::get_active_transfers,
+            transfers::get_queue_eta
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                ftp_client::shutdown(app_handle);
+            }
+        });
 }
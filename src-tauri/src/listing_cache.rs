@@ -0,0 +1,128 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::cloud_client::CloudListingCache;
+use crate::ftp_client::FtpState;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(30);
+const DEFAULT_CAPACITY: usize = 64;
+
+struct CacheInner<T> {
+    ttl: Duration,
+    capacity: usize,
+    entries: HashMap<String, (Instant, T)>,
+    // Least recently used key at the front
+    order: VecDeque<String>,
+}
+
+/// Small TTL + LRU cache for directory listings, keyed by path (or folder id).
+pub struct ListingCache<T> {
+    inner: Mutex<CacheInner<T>>,
+}
+
+impl<T> Default for ListingCache<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+impl<T> ListingCache<T> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                ttl,
+                capacity,
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// A TTL of zero disables caching.
+    pub fn set_ttl(&self, ttl: Duration) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.ttl = ttl;
+            if ttl.is_zero() {
+                inner.entries.clear();
+                inner.order.clear();
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        self.invalidate(|_| true);
+    }
+
+    pub fn invalidate(&self, matches: impl Fn(&str) -> bool) {
+        self.invalidate_where(|key, _| matches(key));
+    }
+
+    /// Drops the listings for which `matches` holds, given their key and contents.
+    pub fn invalidate_where(&self, matches: impl Fn(&str, &T) -> bool) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let CacheInner { entries, order, .. } = &mut *inner;
+            entries.retain(|key, (_, value)| !matches(key, value));
+            order.retain(|key| entries.contains_key(key));
+        }
+    }
+}
+
+impl<T: Clone> ListingCache<T> {
+    pub fn get(&self, key: &str) -> Option<T> {
+        let mut inner = self.inner.lock().ok()?;
+        let ttl = inner.ttl;
+        let fresh = match inner.entries.get(key) {
+            Some((stored_at, _)) => stored_at.elapsed() < ttl,
+            None => return None,
+        };
+        inner.order.retain(|k| k != key);
+        if !fresh {
+            inner.entries.remove(key);
+            return None;
+        }
+        inner.order.push_back(key.to_string());
+        inner.entries.get(key).map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: String, value: T) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        if inner.ttl.is_zero() {
+            return;
+        }
+        inner.order.retain(|k| *k != key);
+        inner.order.push_back(key.clone());
+        inner.entries.insert(key, (Instant::now(), value));
+        while inner.order.len() > inner.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Drops cached listings for `path` (a remote path or cloud folder id), or everything when omitted.
+#[tauri::command]
+pub fn invalidate_cache(
+    ftp_state: State<'_, FtpState>,
+    cloud_cache: State<'_, CloudListingCache>,
+    path: Option<String>,
+) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            let trimmed = path.trim_end_matches('/').to_string();
+            ftp_state.listing_cache.invalidate(|key| {
+                crate::ftp_client::listing_key_dir(key).trim_end_matches('/') == trimmed
+            });
+            cloud_cache.invalidate(|key| key.ends_with(&format!(":{}", path)));
+        }
+        None => {
+            ftp_state.listing_cache.clear();
+            cloud_cache.clear();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::OpenerExt;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::EnvFilter;
+
+const LOG_FILE_PREFIX: &str = "quicksync";
+const MAX_LOG_FILES: usize = 7;
+
+/// Keeps the background log writer alive; dropping it flushes and stops logging.
+pub struct LogGuard(#[allow(dead_code)] WorkerGuard);
+
+fn get_log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let mut log_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    log_dir.push("logs");
+
+    if !log_dir.exists() {
+        fs::create_dir_all(&log_dir).map_err(|e| e.to_string())?;
+    }
+
+    Ok(log_dir)
+}
+
+/// Installs the global subscriber writing daily-rotated files into the config dir.
+/// `level` accepts anything `EnvFilter` does, e.g. `"info"` or `"tauri_app_lib=debug"`.
+pub fn init(app: &AppHandle, level: &str) -> Result<LogGuard, String> {
+    let log_dir = get_log_dir(app)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(&log_dir)
+        .map_err(|e| format!("Failed to create log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(false)
+        .try_init()
+        .map_err(|e| format!("Failed to initialize logging: {}", e))?;
+
+    Ok(LogGuard(guard))
+}
+
+/// Logs to stderr instead, for when `init` couldn't set up the log files.
+pub fn init_stderr(level: &str) {
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+#[tauri::command]
+pub fn get_log_path(app: AppHandle) -> Result<String, String> {
+    get_log_dir(&app).map(|p| p.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn open_log_folder(app: AppHandle) -> Result<(), String> {
+    let log_dir = get_log_dir(&app)?;
+    app.opener()
+        .open_path(log_dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open log folder: {}", e))
+}
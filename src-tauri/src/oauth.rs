@@ -0,0 +1,237 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::time::timeout;
+
+use crate::secret::SecretString;
+
+// The redirect URI has to match the one registered with each provider exactly
+// (Microsoft rejects any difference, including the port), so it is fixed.
+const REDIRECT_PORT: u16 = 17823;
+const REDIRECT_PATH: &str = "/callback";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OAuthTokens {
+    pub access_token: SecretString,
+    pub refresh_token: Option<SecretString>,
+    /// Lifetime of `access_token` in seconds, as reported by the provider.
+    pub expires_in: Option<u64>,
+    /// Granted scopes; Microsoft echoes these back in the token response.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Absolute expiry in unix seconds, filled in from `expires_in` when the tokens are granted.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+impl OAuthTokens {
+    /// The absolute expiry, computing it from `expires_in` if it wasn't stamped yet.
+    pub fn expires_at(&self) -> Option<i64> {
+        self.expires_at.or_else(|| {
+            self.expires_in
+                .map(|secs| chrono::Utc::now().timestamp() + secs as i64)
+        })
+    }
+}
+
+struct OAuthProvider {
+    auth_url: &'static str,
+    token_url: &'static str,
+    scope: &'static str,
+    extra_params: &'static [(&'static str, &'static str)],
+    /// Whether the token endpoint expects `scope` to be repeated on the code exchange.
+    scope_on_exchange: bool,
+    /// Sent with `force_account_select`, replacing any `extra_params` of the same name.
+    account_select_params: &'static [(&'static str, &'static str)],
+}
+
+fn provider_config(provider: &str) -> Result<OAuthProvider, String> {
+    match provider {
+        "google" => Ok(OAuthProvider {
+            auth_url: "https://accounts.google.com/o/oauth2/v2/auth",
+            token_url: "https://oauth2.googleapis.com/token",
+            scope: "https://www.googleapis.com/auth/drive",
+            extra_params: &[("access_type", "offline"), ("prompt", "consent")],
+            scope_on_exchange: false,
+            account_select_params: &[("prompt", "select_account consent")],
+        }),
+        "dropbox" => Ok(OAuthProvider {
+            auth_url: "https://www.dropbox.com/oauth2/authorize",
+            token_url: "https://api.dropboxapi.com/oauth2/token",
+            scope: "",
+            extra_params: &[("token_access_type", "offline")],
+            scope_on_exchange: false,
+            account_select_params: &[("force_reapprove", "true")],
+        }),
+        "microsoft" | "onedrive" => Ok(OAuthProvider {
+            auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+            token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            scope: "Files.ReadWrite offline_access",
+            extra_params: &[("response_mode", "query")],
+            scope_on_exchange: true,
+            account_select_params: &[("prompt", "select_account")],
+        }),
+        _ => Err(format!("Provider {} not recognized.", provider)),
+    }
+}
+
+fn redirect_uri() -> String {
+    format!("http://localhost:{}{}", REDIRECT_PORT, REDIRECT_PATH)
+}
+
+/// Pulls `code` out of the loopback request line, checking `state` along the way.
+fn parse_callback(request: &str, expected_state: &str) -> Result<String, String> {
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| "Malformed OAuth callback request".to_string())?;
+    let query = target.split_once('?').map(|(_, q)| q).unwrap_or("");
+
+    let mut code = None;
+    let mut state = None;
+    let mut error = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = urlencoding::decode(value)
+            .map(|v| v.into_owned())
+            .unwrap_or_else(|_| value.to_string());
+        match key {
+            "code" => code = Some(value),
+            "state" => state = Some(value),
+            "error" => error = Some(value),
+            _ => {}
+        }
+    }
+
+    if let Some(err) = error {
+        return Err(format!("Authorization was denied: {}", err));
+    }
+    if state.as_deref() != Some(expected_state) {
+        return Err("OAuth state mismatch".into());
+    }
+    code.ok_or_else(|| "OAuth callback did not contain a code".to_string())
+}
+
+async fn wait_for_code(listener: TcpListener, expected_state: &str) -> Result<String, String> {
+    let (mut socket, _) = listener
+        .accept()
+        .await
+        .map_err(|e| format!("OAuth callback failed: {}", e))?;
+
+    let mut buf = vec![0u8; 8192];
+    let n = socket
+        .read(&mut buf)
+        .await
+        .map_err(|e| format!("OAuth callback failed: {}", e))?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let result = parse_callback(&request, expected_state);
+
+    let body = match &result {
+        Ok(_) => "Sign-in complete. You can close this window and return to QuickSync Drives.",
+        Err(_) => "Sign-in failed. Please return to QuickSync Drives and try again.",
+    };
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+
+    result
+}
+
+/// Runs the authorization-code flow in the system browser and returns the granted tokens.
+/// `force_account_select` makes the provider ask which account to use, so the
+/// user can sign in as someone else without clearing browser cookies.
+#[tauri::command]
+#[tracing::instrument(skip(app, client_id, client_secret))]
+pub async fn start_oauth_flow(
+    app: AppHandle,
+    provider: String,
+    client_id: String,
+    client_secret: Option<String>,
+    force_account_select: Option<bool>,
+) -> Result<OAuthTokens, String> {
+    let cfg = provider_config(&provider)?;
+    let state = uuid::Uuid::new_v4().to_string();
+    let redirect_uri = redirect_uri();
+
+    let listener = TcpListener::bind(("127.0.0.1", REDIRECT_PORT))
+        .await
+        .map_err(|e| format!("Failed to start OAuth callback listener: {}", e))?;
+
+    let mut auth_url = format!(
+        "{}?client_id={}&redirect_uri={}&response_type=code&state={}",
+        cfg.auth_url,
+        urlencoding::encode(&client_id),
+        urlencoding::encode(&redirect_uri),
+        state
+    );
+    if !cfg.scope.is_empty() {
+        auth_url.push_str(&format!("&scope={}", urlencoding::encode(cfg.scope)));
+    }
+    let overrides = if force_account_select.unwrap_or(false) {
+        cfg.account_select_params
+    } else {
+        &[]
+    };
+    let params = cfg
+        .extra_params
+        .iter()
+        .filter(|(key, _)| !overrides.iter().any(|(k, _)| k == key))
+        .chain(overrides);
+    for (key, value) in params {
+        auth_url.push_str(&format!("&{}={}", key, urlencoding::encode(value)));
+    }
+
+    app.opener()
+        .open_url(&auth_url, None::<&str>)
+        .map_err(|e| format!("Failed to open browser: {}", e))?;
+
+    let code = timeout(Duration::from_secs(300), wait_for_code(listener, &state))
+        .await
+        .map_err(|_| "Timed out waiting for authorization".to_string())??;
+
+    let mut form = vec![
+        ("grant_type", "authorization_code".to_string()),
+        ("code", code),
+        ("redirect_uri", redirect_uri),
+        ("client_id", client_id),
+    ];
+    if let Some(secret) = client_secret.filter(|s| !s.is_empty()) {
+        form.push(("client_secret", secret));
+    }
+    if cfg.scope_on_exchange {
+        form.push(("scope", cfg.scope.to_string()));
+    }
+
+    let res = Client::new()
+        .post(cfg.token_url)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| format!("Token request failed: {}", e))?;
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let err_text = res.text().await.unwrap_or_default();
+        tracing::warn!(%status, "OAuth token exchange failed");
+        return Err(format!("Token exchange error: {}", err_text));
+    }
+
+    let mut tokens = res
+        .json::<OAuthTokens>()
+        .await
+        .map_err(|e| format!("Failed to parse token response: {}", e))?;
+    tokens.expires_at = tokens.expires_at();
+    tracing::info!(
+        has_refresh_token = tokens.refresh_token.is_some(),
+        "OAuth flow complete"
+    );
+    Ok(tokens)
+}
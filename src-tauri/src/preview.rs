@@ -0,0 +1,293 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::ftp_client::{self, FtpState};
+
+/// Bytes fetched from the server per `REST`/`RETR` round trip.
+const PREVIEW_CHUNK: u64 = 2 * 1024 * 1024;
+
+/// Largest request line and headers accepted; players send far less.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// Local HTTP server that lets a `<video>` or `<audio>` element stream a remote
+/// FTP file, fetching the byte ranges the player asks for on demand.
+#[derive(Default)]
+pub struct PreviewServer {
+    task: std::sync::Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+    /// Bumped on every start and stop; responses of an older server stop early.
+    generation: Arc<AtomicU64>,
+}
+
+impl PreviewServer {
+    fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Some(task) = self.task.lock().ok().and_then(|mut t| t.take()) {
+            task.abort();
+        }
+    }
+}
+
+struct PreviewFile {
+    path: String,
+    size: u64,
+    content_type: &'static str,
+    /// Random URL segment so other local processes can't guess the address.
+    token: String,
+}
+
+fn content_type(path: &str) -> &'static str {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "ogv" => "video/ogg",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Resolves a `Range` header against a file of `size` bytes into an inclusive
+/// byte range. `Err` means the range can't be satisfied; multiple ranges are
+/// not supported and fall back to the whole file.
+fn parse_range(header: &str, size: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (first, last) = spec.split_once('-').ok_or(())?;
+    let (first, last) = (first.trim(), last.trim());
+    let range = if first.is_empty() {
+        // "bytes=-500" is the last 500 bytes
+        let suffix: u64 = last.parse().map_err(|_| ())?;
+        if suffix == 0 {
+            return Err(());
+        }
+        (size.saturating_sub(suffix), size.saturating_sub(1))
+    } else {
+        let first: u64 = first.parse().map_err(|_| ())?;
+        let last = match last {
+            "" => size.saturating_sub(1),
+            last => last
+                .parse::<u64>()
+                .map_err(|_| ())?
+                .min(size.saturating_sub(1)),
+        };
+        (first, last)
+    };
+    if range.0 >= size || range.0 > range.1 {
+        return Err(());
+    }
+    Ok(Some(range))
+}
+
+async fn write_head(
+    socket: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, String)],
+) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    socket.write_all(head.as_bytes()).await
+}
+
+/// Reads the request line and headers, which may arrive over several reads.
+/// `None` if the client closed the connection or sent more than `MAX_REQUEST_HEAD`
+/// bytes without ending them.
+async fn read_request_head(socket: &mut TcpStream) -> std::io::Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        // Rescan a few bytes back in case the terminator straddles two reads
+        let scan_from = head.len().saturating_sub(3);
+        head.extend_from_slice(&buf[..n]);
+        if let Some(end) = head[scan_from..].windows(4).position(|w| w == b"\r\n\r\n") {
+            head.truncate(scan_from + end);
+            return Ok(Some(String::from_utf8_lossy(&head).into_owned()));
+        }
+        if head.len() > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+    }
+}
+
+async fn serve_request(
+    app: &AppHandle,
+    mut socket: TcpStream,
+    file: &PreviewFile,
+    generation: &AtomicU64,
+    current: u64,
+) -> std::io::Result<()> {
+    let Some(request) = read_request_head(&mut socket).await? else {
+        return write_head(
+            &mut socket,
+            "400 Bad Request",
+            &[("Content-Length", "0".into())],
+        )
+        .await;
+    };
+    let mut lines = request.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+
+    if !target.starts_with(&format!("/{}/", file.token)) {
+        return write_head(
+            &mut socket,
+            "404 Not Found",
+            &[("Content-Length", "0".into())],
+        )
+        .await;
+    }
+    if method != "GET" && method != "HEAD" {
+        return write_head(
+            &mut socket,
+            "405 Method Not Allowed",
+            &[
+                ("Allow", "GET, HEAD".into()),
+                ("Content-Length", "0".into()),
+            ],
+        )
+        .await;
+    }
+
+    let range = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("range"))
+        .map(|(_, value)| parse_range(value, file.size));
+    let (status, first, last) = match range {
+        Some(Err(())) => {
+            return write_head(
+                &mut socket,
+                "416 Range Not Satisfiable",
+                &[
+                    ("Content-Range", format!("bytes */{}", file.size)),
+                    ("Content-Length", "0".into()),
+                ],
+            )
+            .await;
+        }
+        Some(Ok(Some((first, last)))) => ("206 Partial Content", first, last),
+        _ => ("200 OK", 0, file.size.saturating_sub(1)),
+    };
+    let length = if file.size == 0 { 0 } else { last - first + 1 };
+
+    let mut headers = vec![
+        ("Content-Type", file.content_type.to_string()),
+        ("Content-Length", length.to_string()),
+        ("Accept-Ranges", "bytes".to_string()),
+    ];
+    if status.starts_with("206") {
+        headers.push((
+            "Content-Range",
+            format!("bytes {}-{}/{}", first, last, file.size),
+        ));
+    }
+    write_head(&mut socket, status, &headers).await?;
+    if method == "HEAD" {
+        return Ok(());
+    }
+
+    let state = app.state::<FtpState>();
+    let mut offset = first;
+    let end = first + length;
+    while offset < end && generation.load(Ordering::SeqCst) == current {
+        let chunk_len = PREVIEW_CHUNK.min(end - offset);
+        let chunk = match ftp_client::read_remote_range(&state, &file.path, offset, chunk_len).await
+        {
+            Ok(chunk) if !chunk.is_empty() => chunk,
+            Ok(_) => break,
+            Err(e) => {
+                tracing::warn!(path = %file.path, error = %e, "preview read failed");
+                break;
+            }
+        };
+        offset += chunk.len() as u64;
+        // Fails once the player drops the connection, e.g. after seeking
+        socket.write_all(&chunk).await?;
+    }
+    Ok(())
+}
+
+/// Starts serving the remote file at `path` on a local port for media preview
+/// and returns its URL. Replaces any preview already running.
+#[tauri::command]
+pub async fn start_preview_server(
+    app: AppHandle,
+    server: State<'_, PreviewServer>,
+    path: String,
+) -> Result<String, String> {
+    server.stop();
+
+    let ftp = app.state::<FtpState>();
+    if !ftp_client::supports_rest(&ftp).await {
+        return Err("Server does not support REST; previews need ranged downloads".into());
+    }
+    let size = ftp_client::remote_file_size(&ftp, &path).await?;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| format!("Failed to start preview server: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to start preview server: {}", e))?
+        .port();
+
+    let name = path.rsplit('/').next().unwrap_or_default().to_string();
+    let file = Arc::new(PreviewFile {
+        content_type: content_type(&path),
+        path,
+        size,
+        token: uuid::Uuid::new_v4().simple().to_string(),
+    });
+    let url = format!(
+        "http://127.0.0.1:{}/{}/{}",
+        port,
+        file.token,
+        urlencoding::encode(&name)
+    );
+
+    let current = server.generation.load(Ordering::SeqCst);
+    let generation = server.generation.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        // Players open a new connection per seek while the old one drains
+        while let Ok((socket, _)) = listener.accept().await {
+            let app = app.clone();
+            let file = file.clone();
+            let generation = generation.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = serve_request(&app, socket, &file, &generation, current).await;
+            });
+        }
+    });
+    if let Ok(mut slot) = server.task.lock() {
+        *slot = Some(task);
+    }
+    Ok(url)
+}
+
+#[tauri::command]
+pub fn stop_preview_server(server: State<'_, PreviewServer>) {
+    server.stop();
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use tauri::Window;
+
+use crate::cloud_client::CloudRemote;
+use crate::ftp_client::FtpRemote;
+
+pub type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+
+/// A listing entry in the shape shared by every backend.
+#[derive(Serialize, Clone)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    /// ISO 8601 when the backend reports a usable time.
+    pub modified: Option<String>,
+    /// What to pass back as `path` for this entry: a path for FTP, an id for cloud drives.
+    pub path: String,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteKind {
+    Ftp,
+    Google,
+    Dropbox,
+}
+
+/// Which backend a generic `remote_*` command talks to. FTP uses the active
+/// session; cloud drives need the account's access token.
+#[derive(Deserialize, Clone)]
+pub struct RemoteTarget {
+    pub kind: RemoteKind,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// The file operations every backend supports. `path` is whatever identifies
+/// an item on that backend, see `RemoteEntry::path`; `None` means the root or
+/// current directory.
+pub trait Remote: Send + Sync {
+    fn list<'a>(&'a self, path: Option<&'a str>)
+        -> BoxFuture<'a, Result<Vec<RemoteEntry>, String>>;
+    fn download<'a>(
+        &'a self,
+        path: &'a str,
+        local_path: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>>;
+    /// Uploads `local_path` into the `parent` folder under its own file name.
+    fn upload<'a>(
+        &'a self,
+        local_path: &'a str,
+        parent: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, String>>;
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, String>>;
+    fn mkdir<'a>(
+        &'a self,
+        parent: Option<&'a str>,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>>;
+    /// Renames an item in place; `new_name` is a bare name, not a path.
+    fn rename<'a>(
+        &'a self,
+        path: &'a str,
+        new_name: &'a str,
+    ) -> BoxFuture<'a, Result<String, String>>;
+}
+
+fn remote_for(window: &Window, target: RemoteTarget) -> Result<Box<dyn Remote + '_>, String> {
+    match target.kind {
+        RemoteKind::Ftp => Ok(Box::new(FtpRemote::new(window))),
+        RemoteKind::Google | RemoteKind::Dropbox => {
+            let token = target
+                .token
+                .filter(|t| !t.is_empty())
+                .ok_or_else(|| format!("{:?} needs an access token", target.kind))?;
+            Ok(Box::new(CloudRemote::new(window, target.kind, token)))
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn remote_list(
+    window: Window,
+    target: RemoteTarget,
+    path: Option<String>,
+) -> Result<Vec<RemoteEntry>, String> {
+    remote_for(&window, target)?.list(path.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn remote_download(
+    window: Window,
+    target: RemoteTarget,
+    path: String,
+    local_path: String,
+) -> Result<String, String> {
+    remote_for(&window, target)?
+        .download(&path, &local_path)
+        .await
+}
+
+#[tauri::command]
+pub async fn remote_upload(
+    window: Window,
+    target: RemoteTarget,
+    local_path: String,
+    parent: Option<String>,
+) -> Result<String, String> {
+    remote_for(&window, target)?
+        .upload(&local_path, parent.as_deref())
+        .await
+}
+
+#[tauri::command]
+pub async fn remote_delete(
+    window: Window,
+    target: RemoteTarget,
+    path: String,
+) -> Result<String, String> {
+    remote_for(&window, target)?.delete(&path).await
+}
+
+#[tauri::command]
+pub async fn remote_mkdir(
+    window: Window,
+    target: RemoteTarget,
+    parent: Option<String>,
+    name: String,
+) -> Result<String, String> {
+    remote_for(&window, target)?
+        .mkdir(parent.as_deref(), &name)
+        .await
+}
+
+#[tauri::command]
+pub async fn remote_rename(
+    window: Window,
+    target: RemoteTarget,
+    path: String,
+    new_name: String,
+) -> Result<String, String> {
+    remote_for(&window, target)?.rename(&path, &new_name).await
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// A password or token. Serializes as the plain string so the config file and
+/// the frontend see no difference, but `Debug` prints a placeholder so it can't
+/// end up in logs by accident, and the memory is wiped on drop.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+impl Zeroize for SecretString {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+const MASK: &str = "***";
+
+/// Masks `secrets` wherever they occur in `message`, along with the argument of
+/// any echoed `PASS` or `ACCT` command, before the message is logged or shown.
+pub fn redact(message: &str, secrets: &[&SecretString]) -> String {
+    let mut redacted = message.to_string();
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        redacted = redacted.replace(secret.expose(), MASK);
+    }
+    redacted
+        .split_inclusive('\n')
+        .map(mask_credential_command)
+        .collect()
+}
+
+fn mask_credential_command(line: &str) -> String {
+    // ASCII uppercasing keeps byte offsets, so positions carry over to `line`
+    let upper = line.to_ascii_uppercase();
+    let found = ["PASS ", "ACCT "]
+        .into_iter()
+        .flat_map(|verb| upper.match_indices(verb))
+        .filter(|(at, _)| {
+            !line[..*at]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric())
+        })
+        .min_by_key(|(at, _)| *at);
+    match found {
+        Some((at, verb)) => {
+            let newline = if line.ends_with('\n') { "\n" } else { "" };
+            format!("{}{}{}", &line[..at + verb.len()], MASK, newline)
+        }
+        None => line.to_string(),
+    }
+}
@@ -0,0 +1,104 @@
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    /// File extension; entries without one sort first.
+    Type,
+}
+
+/// Column sort for directory listings. Directories always come before files;
+/// the key orders entries within each group.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct SortSpec {
+    pub key: SortKey,
+    #[serde(default = "default_ascending")]
+    pub ascending: bool,
+}
+
+fn default_ascending() -> bool {
+    true
+}
+
+impl Default for SortSpec {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Name,
+            ascending: true,
+        }
+    }
+}
+
+/// What a listing entry exposes for sorting.
+pub trait Sortable {
+    fn name(&self) -> &str;
+    fn is_dir(&self) -> bool;
+    fn size(&self) -> Option<u64>;
+    /// ISO 8601 timestamp, which orders the same as the time it encodes.
+    fn modified(&self) -> Option<&str>;
+}
+
+fn extension(name: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Keeps directories and the files whose extension is one of `extensions`,
+/// compared case-insensitively and with or without the leading dot.
+pub fn filter_extensions<T: Sortable>(entries: &mut Vec<T>, extensions: &[String]) {
+    let wanted: Vec<String> = extensions
+        .iter()
+        .map(|e| e.trim_start_matches('.').to_lowercase())
+        .collect();
+    entries.retain(|e| e.is_dir() || wanted.contains(&extension(e.name())));
+}
+
+/// Orders present values by `ascending` and puts missing ones last either way.
+fn compare_optional<T: Ord>(a: Option<T>, b: Option<T>, ascending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if ascending => a.cmp(&b),
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sorts `entries` by `spec`. Ties, including entries missing the key, fall back
+/// to the case-insensitive name and then the exact name, so the order is total.
+pub fn sort_entries<T: Sortable>(entries: &mut [T], spec: SortSpec) {
+    entries.sort_by(|a, b| {
+        let by_key = match spec.key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => compare_optional(a.size(), b.size(), spec.ascending),
+            SortKey::Modified => compare_optional(a.modified(), b.modified(), spec.ascending),
+            SortKey::Type => {
+                let order = extension(a.name()).cmp(&extension(b.name()));
+                if spec.ascending {
+                    order
+                } else {
+                    order.reverse()
+                }
+            }
+        };
+        let by_name = a
+            .name()
+            .to_lowercase()
+            .cmp(&b.name().to_lowercase())
+            .then_with(|| a.name().cmp(b.name()));
+        let by_name = if spec.key == SortKey::Name && !spec.ascending {
+            by_name.reverse()
+        } else {
+            by_name
+        };
+
+        b.is_dir().cmp(&a.is_dir()).then(by_key).then(by_name)
+    });
+}
@@ -0,0 +1,534 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Listener, Manager, State, Window};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::ftp_client::{FtpState, TransferProgress};
+
+/// Transfers finishing faster than this don't get a notification, so folder
+/// transfers of many small files don't flood the notification center.
+const NOTIFY_MIN_DURATION: Duration = Duration::from_secs(5);
+
+/// How far back `TransferRegistry::queue_eta` looks to measure throughput.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Tracks running transfers by `transfer_id`, their latest progress, and which
+/// of them were asked to cancel.
+#[derive(Default)]
+pub struct TransferRegistry {
+    active: Mutex<HashSet<String>>,
+    cancelled: Mutex<HashSet<String>>,
+    progress: Mutex<HashMap<String, TransferProgress>>,
+    /// Bytes moved by any transfer, with when they were reported.
+    moved: Mutex<VecDeque<(Instant, u64)>>,
+    /// Set by `record_progress`, for reporting transfers that end without an event.
+    app: std::sync::OnceLock<AppHandle>,
+}
+
+/// Unregisters the transfer when dropped, so early returns can't leave it behind.
+pub struct ActiveTransfer<'a> {
+    registry: &'a TransferRegistry,
+    transfer_id: String,
+}
+
+impl Drop for ActiveTransfer<'_> {
+    fn drop(&mut self) {
+        self.registry.finish(&self.transfer_id);
+    }
+}
+
+impl TransferRegistry {
+    pub fn track(&self, transfer_id: &str) -> ActiveTransfer<'_> {
+        if let Ok(mut active) = self.active.lock() {
+            active.insert(transfer_id.to_string());
+        }
+        ActiveTransfer {
+            registry: self,
+            transfer_id: transfer_id.to_string(),
+        }
+    }
+
+    /// Flags a running transfer for cancellation. Returns false if it isn't running.
+    pub fn cancel(&self, transfer_id: &str) -> bool {
+        let running = self
+            .active
+            .lock()
+            .map(|active| active.contains(transfer_id))
+            .unwrap_or(false);
+        if running {
+            if let Ok(mut cancelled) = self.cancelled.lock() {
+                cancelled.insert(transfer_id.to_string());
+            }
+        }
+        running
+    }
+
+    /// Flags every running transfer for cancellation and returns their ids.
+    pub fn cancel_all(&self) -> Vec<String> {
+        let ids: Vec<String> = self
+            .active
+            .lock()
+            .map(|active| active.iter().cloned().collect())
+            .unwrap_or_default();
+        if let Ok(mut cancelled) = self.cancelled.lock() {
+            cancelled.extend(ids.iter().cloned());
+        }
+        ids
+    }
+
+    pub fn is_cancelled(&self, transfer_id: &str) -> bool {
+        self.cancelled
+            .lock()
+            .map(|cancelled| cancelled.contains(transfer_id))
+            .unwrap_or(false)
+    }
+
+    fn finish(&self, transfer_id: &str) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(transfer_id);
+        }
+        let was_cancelled = self
+            .cancelled
+            .lock()
+            .map(|mut cancelled| cancelled.remove(transfer_id))
+            .unwrap_or(false);
+        let unfinished = self
+            .progress
+            .lock()
+            .ok()
+            .and_then(|mut progress| progress.remove(transfer_id));
+        // Progress still recorded means no final event was sent, as when the
+        // transfer returned an error; report how it ended so listeners can settle
+        if let (Some(last), Some(app)) = (unfinished, self.app.get()) {
+            let status = if was_cancelled { "cancelled" } else { "failed" };
+            let _ = app.emit(
+                "transfer-progress",
+                TransferProgress {
+                    status: status.into(),
+                    ..last
+                },
+            );
+        }
+    }
+
+    /// Keeps the latest progress of each running transfer for `get_active_transfers`.
+    fn record(&self, update: TransferProgress) {
+        let running = self
+            .active
+            .lock()
+            .map(|active| active.contains(&update.transfer_id))
+            .unwrap_or(false);
+        let Ok(mut progress) = self.progress.lock() else {
+            return;
+        };
+        // Files of a batch are counted through the batch's own events
+        if update.batch_id.is_none() || update.batch_id.as_ref() == Some(&update.transfer_id) {
+            let before = progress.get(&update.transfer_id).map_or(0, |p| p.progress);
+            self.record_moved(update.progress.saturating_sub(before));
+        }
+        if !running || is_final_status(&update.status) {
+            progress.remove(&update.transfer_id);
+        } else {
+            progress.insert(update.transfer_id.clone(), update);
+        }
+    }
+
+    fn record_moved(&self, bytes: u64) {
+        let Ok(mut moved) = self.moved.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if bytes > 0 {
+            moved.push_back((now, bytes));
+        }
+        while moved
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > THROUGHPUT_WINDOW)
+        {
+            moved.pop_front();
+        }
+    }
+
+    /// Bytes per second moved over the last `THROUGHPUT_WINDOW`, summed across
+    /// transfers, so concurrent transfers count with their combined speed.
+    fn throughput(&self) -> f64 {
+        self.record_moved(0);
+        let Ok(moved) = self.moved.lock() else {
+            return 0.0;
+        };
+        let Some((first, _)) = moved.front() else {
+            return 0.0;
+        };
+        // At least a second, so the first chunks of a transfer don't read as a burst
+        let elapsed = first.elapsed().as_secs_f64().max(1.0);
+        moved.iter().map(|(_, bytes)| *bytes).sum::<u64>() as f64 / elapsed
+    }
+
+    /// Time left until every running transfer is done, at the current combined
+    /// throughput. `None` when a transfer's size is unknown or nothing moved lately.
+    fn queue_eta(&self) -> Option<Duration> {
+        let remaining = {
+            let progress = self.progress.lock().ok()?;
+            let mut remaining = 0;
+            for p in progress.values() {
+                if p.batch_id.is_some() && p.batch_id.as_ref() != Some(&p.transfer_id) {
+                    continue;
+                }
+                if p.total == 0 {
+                    return None;
+                }
+                remaining += p.total.saturating_sub(p.progress);
+            }
+            remaining
+        };
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let throughput = self.throughput();
+        (throughput > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / throughput))
+    }
+}
+
+/// Whether a `transfer-progress` status is the last one of its transfer.
+fn is_final_status(status: &str) -> bool {
+    matches!(status, "complete" | "cancelled" | "failed")
+}
+
+/// Mirrors `transfer-progress` events into the registry so the current set of
+/// transfers can be queried, e.g. after the frontend reloads.
+pub fn record_progress(app: &AppHandle) {
+    let handle = app.clone();
+    let _ = app.state::<TransferRegistry>().app.set(app.clone());
+    app.listen_any("transfer-progress", move |event| {
+        if let Ok(update) = serde_json::from_str::<TransferProgress>(event.payload()) {
+            handle.state::<TransferRegistry>().record(update);
+        }
+    });
+}
+
+/// Latest progress of every transfer still running.
+#[tauri::command]
+pub fn get_active_transfers(registry: State<'_, TransferRegistry>) -> Vec<TransferProgress> {
+    registry
+        .progress
+        .lock()
+        .map(|progress| progress.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Estimated seconds until every running transfer finishes, for a global ETA.
+/// `None` while it can't be estimated, e.g. for a transfer of unknown size.
+#[tauri::command]
+pub fn get_queue_eta(registry: State<'_, TransferRegistry>) -> Option<u64> {
+    registry
+        .queue_eta()
+        .map(|eta| eta.as_secs_f64().ceil() as u64)
+}
+
+#[tauri::command]
+pub fn cancel_cloud_transfer(
+    registry: State<'_, TransferRegistry>,
+    transfer_id: String,
+) -> Result<(), String> {
+    if registry.cancel(&transfer_id) {
+        Ok(())
+    } else {
+        Err(format!("No active transfer with id {}", transfer_id))
+    }
+}
+
+/// Cancels every running FTP and cloud transfer, e.g. before disconnecting.
+/// Each one gets a `cancelled` progress event right away; the transfer loops
+/// then stop and clean up on their own. Returns how many were cancelled.
+#[tauri::command]
+pub fn cancel_all_transfers(
+    app: AppHandle,
+    registry: State<'_, TransferRegistry>,
+    ftp: State<'_, FtpState>,
+) -> usize {
+    let ids = registry.cancel_all();
+    // Wakes an FTP transfer blocked on a stalled data connection
+    ftp.data_abort.notify_waiters();
+
+    let last_progress = registry
+        .progress
+        .lock()
+        .map(|progress| progress.clone())
+        .unwrap_or_default();
+    for id in &ids {
+        let update = match last_progress.get(id) {
+            Some(p) => TransferProgress {
+                status: "cancelled".into(),
+                ..p.clone()
+            },
+            None => TransferProgress {
+                transfer_id: id.clone(),
+                filename: String::new(),
+                progress: 0,
+                total: 0,
+                status: "cancelled".into(),
+                batch_id: None,
+            },
+        };
+        let _ = app.emit("transfer-progress", update);
+    }
+    ids.len()
+}
+
+/// Minimum time between two in-flight progress events of one transfer.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Rate-limits the `downloading`/`uploading` events a transfer loop sends per
+/// chunk, which on fast links would flood the webview with thousands a second.
+/// Events that change the status, like `complete`, bypass it and always go out.
+#[derive(Default)]
+pub struct ProgressThrottle {
+    last: Mutex<Option<Instant>>,
+}
+
+impl ProgressThrottle {
+    /// Whether enough time has passed to emit another event; if so, starts the next interval.
+    pub fn ready(&self) -> bool {
+        let Ok(mut last) = self.last.lock() else {
+            return true;
+        };
+        let now = Instant::now();
+        match *last {
+            Some(at) if now.duration_since(at) < PROGRESS_INTERVAL => false,
+            _ => {
+                *last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// One logical transfer covering a multi-file operation such as a folder
+/// download. It reports bytes done across every file against a grand total
+/// measured up front, under its own id; the per-file events of the batch carry
+/// that id as `batch_id`. Dropping it unfinished reports it `cancelled` if it
+/// was cancelled by its id, and `failed` otherwise.
+pub struct BatchProgress<'a> {
+    window: &'a Window,
+    batch_id: String,
+    label: String,
+    total: u64,
+    done: AtomicU64,
+    files_done: AtomicU64,
+    failed: Mutex<Vec<FailedFile>>,
+    finished: AtomicBool,
+    throttle: ProgressThrottle,
+    _active: ActiveTransfer<'a>,
+}
+
+/// A file a batch skipped after it failed, with the reason.
+#[derive(serde::Serialize, Clone)]
+pub struct FailedFile {
+    pub path: String,
+    pub error: String,
+}
+
+impl<'a> BatchProgress<'a> {
+    /// `kind` is the id prefix of the files in the batch ("dl" or "ul").
+    pub fn start(window: &'a Window, kind: &str, label: &str, total: u64) -> Self {
+        let batch_id = format!("{}-batch-{}", kind, uuid::Uuid::new_v4());
+        let registry = window.state::<TransferRegistry>().inner();
+        let batch = Self {
+            window,
+            _active: registry.track(&batch_id),
+            batch_id,
+            label: label.to_string(),
+            total,
+            done: AtomicU64::new(0),
+            files_done: AtomicU64::new(0),
+            failed: Mutex::default(),
+            finished: AtomicBool::new(false),
+            throttle: ProgressThrottle::default(),
+        };
+        batch.emit("starting");
+        batch
+    }
+
+    pub fn id(&self) -> &str {
+        &self.batch_id
+    }
+
+    /// Whether the batch was cancelled by its id, see `TransferRegistry::cancel`.
+    pub fn is_cancelled(&self) -> bool {
+        self.window
+            .state::<TransferRegistry>()
+            .is_cancelled(&self.batch_id)
+    }
+
+    /// Files counted by `advance` so far.
+    pub fn files_done(&self) -> u64 {
+        self.files_done.load(Ordering::Relaxed)
+    }
+
+    /// Emits progress of one file in the batch.
+    pub fn file_progress(
+        &self,
+        transfer_id: &str,
+        filename: &str,
+        progress: u64,
+        total: u64,
+        status: &str,
+    ) {
+        let _ = self.window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: transfer_id.to_string(),
+                filename: filename.to_string(),
+                progress,
+                total,
+                status: status.into(),
+                batch_id: Some(self.batch_id.clone()),
+            },
+        );
+    }
+
+    /// Records that `path` failed and the batch moved on without it.
+    pub fn fail(&self, transfer_id: &str, path: &str, error: String) {
+        tracing::warn!(path, error = %error, batch = %self.batch_id, "file failed in batch");
+        self.file_progress(transfer_id, path, 0, 0, "failed");
+        if let Ok(mut failed) = self.failed.lock() {
+            failed.push(FailedFile {
+                path: path.to_string(),
+                error,
+            });
+        }
+    }
+
+    /// Files recorded by `fail` so far.
+    pub fn take_failures(&self) -> Vec<FailedFile> {
+        self.failed
+            .lock()
+            .map(|mut failed| std::mem::take(&mut *failed))
+            .unwrap_or_default()
+    }
+
+    /// Counts one more file of `bytes` as done and reports the batch, throttled.
+    pub fn advance(&self, bytes: u64, status: &str) {
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+        if self.throttle.ready() {
+            self.emit(status);
+        }
+    }
+
+    pub fn finish(self) {
+        self.finished.store(true, Ordering::Relaxed);
+        self.emit("complete");
+    }
+
+    fn emit(&self, status: &str) {
+        let done = self.done.load(Ordering::Relaxed);
+        let _ = self.window.emit(
+            "transfer-progress",
+            TransferProgress {
+                transfer_id: self.batch_id.clone(),
+                filename: self.label.clone(),
+                // Sizes measured up front can be stale by the time files are moved
+                progress: done,
+                total: self.total.max(done),
+                status: status.into(),
+                batch_id: Some(self.batch_id.clone()),
+            },
+        );
+    }
+}
+
+impl Drop for BatchProgress<'_> {
+    fn drop(&mut self) {
+        if !self.finished.load(Ordering::Relaxed) {
+            let status = if self.is_cancelled() {
+                "cancelled"
+            } else {
+                "failed"
+            };
+            self.emit(status);
+        }
+    }
+}
+
+/// Payload size used by the connection benchmarks unless another is requested.
+pub const BENCHMARK_DEFAULT_BYTES: usize = 1024 * 1024;
+pub const BENCHMARK_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Measured round-trip latency and throughput of a connection.
+#[derive(serde::Serialize)]
+pub struct BenchmarkResult {
+    /// Average time of a no-op request.
+    pub latency_ms: f64,
+    pub upload_bytes_per_sec: f64,
+    pub download_bytes_per_sec: f64,
+    /// Size of the test payload moved in each direction.
+    pub bytes: u64,
+}
+
+pub fn bytes_per_sec(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Shows a desktop notification when a long-running transfer finishes, driven by
+/// the `transfer-progress` events every transfer already emits.
+pub fn notify_on_completion(app: &AppHandle) {
+    let handle = app.clone();
+    let started = Mutex::new(HashMap::<String, Instant>::new());
+
+    app.listen_any("transfer-progress", move |event| {
+        let Ok(p) = serde_json::from_str::<TransferProgress>(event.payload()) else {
+            return;
+        };
+        let Ok(mut started) = started.lock() else {
+            return;
+        };
+        if !is_final_status(&p.status) {
+            started.entry(p.transfer_id).or_insert_with(Instant::now);
+            return;
+        }
+        let Some(elapsed) = started.remove(&p.transfer_id).map(|t| t.elapsed()) else {
+            return;
+        };
+        if elapsed < NOTIFY_MIN_DURATION || !crate::config::notifications_enabled(&handle) {
+            return;
+        }
+
+        let kind = match p.transfer_id.split('-').next() {
+            Some("dl") => "Download",
+            Some("ul") => "Upload",
+            _ => "Transfer",
+        };
+        let title = format!("{} {}", kind, p.status);
+        let name = std::path::Path::new(&p.filename)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or(p.filename);
+        let body = format!(
+            "{}: {} in {}s",
+            name,
+            format_bytes(p.progress),
+            elapsed.as_secs()
+        );
+
+        let _ = handle
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show();
+    });
+}
@@ -0,0 +1,176 @@
+//! Cloud commands against a local mock of the provider APIs, routed there with
+//! per-connection base URLs and `CloudEndpoints`.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use tauri_app_lib::cloud_client::{self, CloudEndpoints};
+use tauri_app_lib::config::{AuthMode, CloudConnection};
+
+/// A request the mock received: method, path with query, and body.
+type Recorded = Arc<Mutex<Vec<(String, String, String)>>>;
+
+/// Serves canned `(status, json)` replies picked by `respond` from method and
+/// path, on a background thread. Returns the base URL and the requests seen.
+fn mock_server(respond: fn(&str, &str) -> (u16, &'static str)) -> (String, Recorded) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let recorded: Recorded = Arc::default();
+    let log = recorded.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let path = parts.next().unwrap_or_default().to_string();
+
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let (status, reply) = respond(&method, &path);
+            log.lock()
+                .unwrap()
+                .push((method, path, String::from_utf8_lossy(&body).to_string()));
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                reply.len(),
+                reply
+            );
+        }
+    });
+    (base_url, recorded)
+}
+
+#[test]
+fn google_delete_uses_connection_base_url() {
+    let (base_url, recorded) = mock_server(|_, _| (204, ""));
+    cloud_client::set_base_url("google-delete-token", Some(base_url));
+
+    let result = tauri::async_runtime::block_on(cloud_client::delete_cloud_file_inner(
+        "google".into(),
+        "google-delete-token".into(),
+        "abc".into(),
+    ));
+
+    assert!(result.is_ok(), "{:?}", result);
+    let recorded = recorded.lock().unwrap();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].0, "DELETE");
+    assert_eq!(recorded[0].1, "/drive/v3/files/abc");
+}
+
+#[test]
+fn dropbox_create_folder_parses_mock_reply() {
+    let (base_url, recorded) = mock_server(|_, path| match path {
+        "/2/files/create_folder_v2" => (200, r#"{"metadata":{"name":"Docs","id":"id:1"}}"#),
+        // Root namespace lookup; failing it falls back to the home folder
+        _ => (404, "{}"),
+    });
+    cloud_client::set_base_url("dropbox-folder-token", Some(format!("{}/", base_url)));
+
+    let entry = tauri::async_runtime::block_on(cloud_client::create_cloud_folder_inner(
+        "dropbox".into(),
+        "dropbox-folder-token".into(),
+        None,
+        "Docs".into(),
+    ))
+    .unwrap();
+
+    assert_eq!(entry.name, "Docs");
+    assert!(entry.is_dir);
+    assert_eq!(entry.id.as_deref(), Some("id:1"));
+    let recorded = recorded.lock().unwrap();
+    let create = recorded
+        .iter()
+        .find(|(_, path, _)| path == "/2/files/create_folder_v2")
+        .expect("create_folder_v2 was not called");
+    assert!(create.2.contains(r#""path":"/Docs""#), "{}", create.2);
+}
+
+#[test]
+fn configured_endpoints_apply_without_base_url() {
+    let (base_url, recorded) = mock_server(|_, _| {
+        (
+            200,
+            r#"{"revisions":[{"id":"r1","modifiedTime":"2024-01-01T00:00:00Z","size":"12"}]}"#,
+        )
+    });
+    cloud_client::set_endpoints(CloudEndpoints {
+        google_api: base_url,
+        ..Default::default()
+    });
+
+    let revisions = tauri::async_runtime::block_on(cloud_client::list_cloud_revisions(
+        "google".into(),
+        "unregistered-token".into(),
+        "f1".into(),
+    ))
+    .unwrap();
+
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].id, "r1");
+    assert_eq!(revisions[0].size, Some(12));
+    assert!(recorded.lock().unwrap()[0]
+        .1
+        .starts_with("/drive/v3/files/f1/revisions?"));
+}
+
+/// A saved OAuth connection to the mock whose token expires at `expires_at`.
+fn connection(token: &str, base_url: &str, expires_at: i64) -> CloudConnection {
+    CloudConnection {
+        id: token.into(),
+        provider: "google".into(),
+        account_name: "me@example.com".into(),
+        access_token: token.to_string().into(),
+        refresh_token: None,
+        expires_at: Some(expires_at),
+        delta_token: None,
+        root_namespace_id: None,
+        auth_mode: AuthMode::OAuth,
+        base_url: Some(base_url.into()),
+    }
+}
+
+#[test]
+fn expired_token_is_refused_before_sending() {
+    let (base_url, recorded) = mock_server(|_, _| (204, ""));
+    connection("expired-token", &base_url, 1).register();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    connection("fresh-token", &base_url, now + 3_600).register();
+
+    let delete = |token: &str| {
+        tauri::async_runtime::block_on(cloud_client::delete_cloud_file_inner(
+            "google".into(),
+            token.into(),
+            "abc".into(),
+        ))
+    };
+    let err = delete("expired-token").unwrap_err();
+    assert!(err.starts_with(cloud_client::TOKEN_EXPIRED), "{}", err);
+    assert!(recorded.lock().unwrap().is_empty());
+
+    assert!(delete("fresh-token").is_ok());
+    assert_eq!(recorded.lock().unwrap().len(), 1);
+}
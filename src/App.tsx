@@ -34,8 +34,21 @@ interface TransferProgress {
   progress: number;
   total: number;
   status: string;
+  batch_id?: string;
 }
 
+type FolderTransferOutcome =
+  | {
+      status: "completed";
+      message: string;
+      downloaded_bytes: number;
+      files_ok: number;
+      files_failed?: { path: string; error: string }[];
+      skipped_symlinks?: string[];
+    }
+  | { status: "confirmation_required"; total_bytes: number; threshold_bytes: number }
+  | { status: "cancelled"; message: string; files_done: number };
+
 /* ───────── Helpers ───────── */
 function formatSize(size: number | null): string {
   if (size === null || size === 0) return "-";
@@ -51,7 +64,7 @@ function ProgressItem({ transfer }: { transfer: TransferProgress }) {
       <div className="progress-info">
         <span className="progress-filename" title={transfer.filename}>{transfer.filename}</span>
         <span className="progress-status">
-          {transfer.status === 'complete' ? 'Complete' : `${formatSize(transfer.progress)} / ${formatSize(transfer.total)}`}
+          {transfer.status === 'complete' ? 'Complete' : transfer.status === 'cancelled' ? 'Cancelled' : transfer.status === 'failed' ? 'Failed' : `${formatSize(transfer.progress)} / ${formatSize(transfer.total)}`}
         </span>
       </div>
       <div className="progress-bar-container">
@@ -469,12 +482,22 @@ function RemoteFileTree({ onTransferMsg, downloadDir, cloudConfig, onDragOverPan
       const baseDir = localBaseDir || downloadDir;
       const localFolder = `${baseDir}\\${folderName}`;
 
-      const result = await invoke<string>("download_remote_folder", {
-        remoteDir: folderName,
-        localDir: localFolder,
-      });
+      const args = { remoteDir: folderName, localDir: localFolder };
+      let result = await invoke<FolderTransferOutcome>("download_remote_folder", args);
+      if (result.status === "confirmation_required") {
+        if (!window.confirm(`${folderName} is ${formatSize(result.total_bytes)}. Download anyway?`)) {
+          onTransferMsg(`Download of ${folderName} cancelled`);
+          return;
+        }
+        result = await invoke<FolderTransferOutcome>("download_remote_folder", { ...args, confirmed: true });
+      }
 
-      onTransferMsg(result);
+      if (result.status === "completed" && result.files_failed?.length) {
+        const failed = result.files_failed.map((f) => `${f.path}: ${f.error}`).join("\n");
+        onTransferMsg(`${result.message}\n${failed}`);
+      } else if (result.status === "completed" || result.status === "cancelled") {
+        onTransferMsg(result.message);
+      }
     } catch (err: any) {
       onTransferMsg(`Download Folder error: ${err}`);
     }
@@ -959,9 +982,16 @@ function App() {
   const queueRef = useRef(queueH);
   const panesRef = useRef<HTMLElement>(null);
 
-  const { config, loadConfig, saveConfig } = useConfigStore();
+  const { config, loadConfig, reloadConfig, saveConfig } = useConfigStore();
 
   useEffect(() => { loadConfig(); }, [loadConfig]);
+  // Commands such as token refreshes write the config themselves
+  useEffect(() => {
+    const unlisten = listen("config-changed", () => reloadConfig());
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [reloadConfig]);
   useEffect(() => {
     invoke<string>("get_home_dir").then((home) => {
       if (!localStorage.getItem("qs-download-dir")) {
@@ -1011,9 +1041,11 @@ function App() {
   useEffect(() => {
     const unlisten = listen<TransferProgress>("transfer-progress", (event) => {
       const p = event.payload;
+      // Files of a batch are shown through the batch's own rolled-up entry
+      if (p.batch_id && p.batch_id !== p.transfer_id) return;
       setActiveTransfers((prev) => {
         // If complete, we might want to keep it for a few seconds then remove
-        if (p.status === 'complete') {
+        if (p.status === 'complete' || p.status === 'cancelled' || p.status === 'failed') {
           // Trigger a refresh after upload completes
           setRefreshKey(k => k + 1);
 
@@ -1045,7 +1077,13 @@ function App() {
     saveConfig({ ...config, ftp_connections: updated });
   };
 
-  const handleSaveCloud = (conn: CloudConnection) => {
+  const handleSaveCloud = async (conn: CloudConnection) => {
+    if (conn.provider === 'dropbox' && !conn.root_namespace_id) {
+      // Team accounts keep team folders outside the member's home folder
+      const namespace = await invoke<string | null>('get_dropbox_root_namespace', { token: conn.access_token })
+        .catch(() => null);
+      conn = { ...conn, root_namespace_id: namespace ?? undefined };
+    }
     const existing = config.cloud_connections.findIndex((c) => c.id === conn.id);
     const updated = [...config.cloud_connections];
     if (existing >= 0) updated[existing] = conn;
@@ -1059,9 +1097,9 @@ function App() {
     }
 
     if (type === "ftp") {
-      saveConfig({ ...config, ftp_connections: config.ftp_connections.filter((c) => c.id !== id) });
+      saveConfig({ ...config, ftp_connections: config.ftp_connections.filter((c) => c.id !== id) }, true);
     } else {
-      saveConfig({ ...config, cloud_connections: config.cloud_connections.filter((c) => c.id !== id) });
+      saveConfig({ ...config, cloud_connections: config.cloud_connections.filter((c) => c.id !== id) }, true);
     }
   };
 
@@ -1080,10 +1118,17 @@ function App() {
             username: selectedFtpConn.username,
             password: selectedFtpConn.password || "",
             secure: selectedFtpConn.secure || false,
+            account: selectedFtpConn.account,
+            auto_reconnect: selectedFtpConn.auto_reconnect ?? true,
+            remote_encoding: selectedFtpConn.remote_encoding,
+            fix_passive_nat: selectedFtpConn.fix_passive_nat ?? false,
+            keep_alive: selectedFtpConn.keep_alive ?? false,
           },
         });
         setConnectionStatus(result);
       } catch (err: any) {
+        // A newer connect owns the status line now
+        if (String(err).startsWith("ConnectSuperseded")) return;
         setConnectionStatus(`Error: ${err}`);
       }
     } else if (selectedCloudConn) {
@@ -1100,6 +1145,32 @@ function App() {
     }
   };
 
+  // Menu accelerators (Refresh / Disconnect) are handled natively and forwarded as events
+  useEffect(() => {
+    const unlistenRefresh = listen("menu-refresh", () => setRefreshKey((k) => k + 1));
+    const unlistenDisconnect = listen("menu-disconnect", () => handleDisconnect());
+
+    return () => {
+      unlistenRefresh.then((f) => f());
+      unlistenDisconnect.then((f) => f());
+    };
+  }, []);
+
+  // Keep-alive reconnects after sleep/resume happen in the background
+  useEffect(() => {
+    const unlistenRestored = listen<{ host: string }>("connection-restored", (event) =>
+      setConnectionStatus(`Reconnected to ${event.payload.host}`)
+    );
+    const unlistenLost = listen<{ host: string; error?: string }>("connection-lost", (event) =>
+      setConnectionStatus(`Error: lost connection to ${event.payload.host}: ${event.payload.error}`)
+    );
+
+    return () => {
+      unlistenRestored.then((f) => f());
+      unlistenLost.then((f) => f());
+    };
+  }, []);
+
   /* ── Resize handlers ── */
   const onSidebarResize = useCallback((delta: number) => {
     const next = Math.max(160, Math.min(500, sidebarRef.current + delta));
@@ -9,6 +9,11 @@ export interface FtpConnection {
     username: string;
     password?: string;
     secure?: boolean;
+    account?: string;
+    auto_reconnect?: boolean;
+    remote_encoding?: string;
+    fix_passive_nat?: boolean;
+    keep_alive?: boolean;
 }
 
 export interface CloudConnection {
@@ -17,12 +22,42 @@ export interface CloudConnection {
     account_name: string;
     access_token: string;
     refresh_token?: string;
+    expires_at?: number;
+    delta_token?: string;
+    root_namespace_id?: string;
+    auth_mode?: "oauth" | "api_key";
+    base_url?: string;
+}
+
+export interface CloudEndpoints {
+    google_api: string;
+    dropbox_api: string;
+    dropbox_content: string;
+    graph_api: string;
+}
+
+export interface TransferPolicy {
+    connect_timeout_secs: number;
+    read_timeout_secs: number;
+    retries: number;
+    backoff_base_ms: number;
+    buffer_size: number;
+    max_concurrency: number;
 }
 
 export interface AppConfig {
     ftp_connections: FtpConnection[];
     cloud_connections: CloudConnection[];
     theme?: string;
+    shortcuts?: Record<string, string>;
+    notifications_enabled?: boolean;
+    confirm_threshold_bytes?: number;
+    default_download_dir?: string;
+    text_extensions?: string[];
+    transfer_policy?: TransferPolicy;
+    recent_paths?: Record<string, string[]>;
+    favorite_paths?: Record<string, string[]>;
+    cloud_endpoints?: Partial<CloudEndpoints>;
 }
 
 interface ConfigStore {
@@ -30,7 +65,9 @@ interface ConfigStore {
     loading: boolean;
     error: string | null;
     loadConfig: () => Promise<void>;
-    saveConfig: (newConfig: AppConfig) => Promise<void>;
+    /** Picks up changes the backend wrote itself, without showing the loading state. */
+    reloadConfig: () => Promise<void>;
+    saveConfig: (newConfig: AppConfig, allowClear?: boolean) => Promise<void>;
 }
 
 export const useConfigStore = create<ConfigStore>((set) => ({
@@ -48,9 +85,18 @@ export const useConfigStore = create<ConfigStore>((set) => ({
         }
     },
 
-    saveConfig: async (newConfig: AppConfig) => {
+    reloadConfig: async () => {
+        try {
+            const config = await invoke<AppConfig>('load_config');
+            set({ config });
+        } catch (err: any) {
+            set({ error: err.toString() });
+        }
+    },
+
+    saveConfig: async (newConfig: AppConfig, allowClear?: boolean) => {
         try {
-            await invoke('save_config', { config: newConfig });
+            await invoke('save_config', { config: newConfig, allowClear });
             set({ config: newConfig });
         } catch (err: any) {
             set({ error: err.toString() });